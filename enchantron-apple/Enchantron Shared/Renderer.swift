@@ -157,6 +157,10 @@ class Renderer: NSObject, MTKViewDelegate {
         self.currentView.layout(size: size, scale: screenScale)
     }
     
+    func onBackground() {
+        appCtx.onBackground()
+    }
+    
     func magnify(scaleChangeAdditive: Float64, centerPoint: SIMD2<Float64>) {
         DispatchQueue.main.async {
             self.currentView.magnify(
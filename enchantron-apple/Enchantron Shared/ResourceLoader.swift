@@ -12,6 +12,21 @@ import MetalKit
 import simd
 import CoreImage
 
+/// Layout of raw pixel data. The codes must match crate::native::PixelFormat
+enum PixelFormat: Int64 {
+    case rgb = 0
+    case rgba = 1
+    case indexed = 2
+    
+    var bytesPerPixel: Int {
+        switch self {
+        case .rgb: return 3
+        case .rgba: return 4
+        case .indexed: return 1
+        }
+    }
+}
+
 class ResourceLoader {
 
     static let textureLoaderOptions : [ MTKTextureLoader.Option : Any] = [
@@ -46,6 +61,65 @@ class ResourceLoader {
         return cgImageToTexture(image)
     }
     
+    func loadTextureFromPixels(
+        _ pixelData: CGDataProvider,
+        _ palette: CGDataProvider,
+        _ width: Int64,
+        _ height: Int64,
+        _ pixelFormat: Int64) -> Texture {
+        
+        let format = PixelFormat(rawValue: pixelFormat)!
+        let rgbSpace = CGColorSpaceCreateDeviceRGB()
+        let space: CGColorSpace
+        let alphaInfo: CGImageAlphaInfo
+        
+        switch format {
+        case .rgb:
+            space = rgbSpace
+            alphaInfo = .none
+        case .rgba:
+            space = rgbSpace
+            alphaInfo = .last
+        case .indexed:
+            let colorTable = palette.data! as Data
+            space = colorTable.withUnsafeBytes { table in
+                CGColorSpace(
+                    indexedBaseSpace: rgbSpace,
+                    last: colorTable.count / 3 - 1,
+                    colorTable: table.bindMemory(to: UInt8.self).baseAddress!)!
+            }
+            alphaInfo = .none
+        }
+        
+        let image = CGImage(
+            width: Int(width),
+            height: Int(height),
+            bitsPerComponent: 8,
+            bitsPerPixel: 8 * format.bytesPerPixel,
+            bytesPerRow: Int(width) * format.bytesPerPixel,
+            space: space,
+            bitmapInfo: CGBitmapInfo(rawValue: alphaInfo.rawValue),
+            provider: pixelData,
+            decode: nil,
+            shouldInterpolate: false,
+            intent: .defaultIntent)!
+        
+        return cgImageToTexture(image)
+    }
+    
+    func loadText(_ resourceName: String) -> String {
+        guard let url = Bundle.main.url(forResource: resourceName, withExtension: nil),
+              let text = try? String(contentsOf: url, encoding: .utf8) else {
+            return ""
+        }
+        
+        return text
+    }
+    
+    func getTexturePath(_ resourceName: String) -> String {
+        return Bundle.main.url(forResource: resourceName, withExtension: nil)?.path ?? ""
+    }
+    
     private func cgImageToTexture(_ image: CGImage) -> Texture {
         let rawTexture = try! loader.newTexture(
             cgImage: image,
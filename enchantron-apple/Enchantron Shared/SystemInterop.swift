@@ -47,4 +47,11 @@ class SystemInterop {
     func getTransitionService() -> TransitionService {
         return self.transitionService
     }
+    
+    func getSaveDirectory() -> String {
+        let supportDir = FileManager.default.urls(
+            for: .applicationSupportDirectory,
+            in: .userDomainMask)[0]
+        return supportDir.appendingPathComponent("saves").path
+    }
 }
@@ -47,6 +47,16 @@ class GameViewController: UIViewController {
         
         
         mtkView.delegate = renderer
+        
+        NotificationCenter.default.addObserver(
+            self,
+            selector: #selector(didEnterBackground),
+            name: UIApplication.didEnterBackgroundNotification,
+            object: nil)
+    }
+    
+    @objc func didEnterBackground() {
+        renderer?.onBackground()
     }
     
     override var shouldAutorotate: Bool {
@@ -49,6 +49,16 @@ class GameViewController: NSViewController {
         renderer.mtkView(mtkView, drawableSizeWillChange: mtkView.drawableSize)
 
         mtkView.delegate = renderer
+        
+        NotificationCenter.default.addObserver(
+            self,
+            selector: #selector(didResignActive),
+            name: NSApplication.didResignActiveNotification,
+            object: nil)
+    }
+    
+    @objc func didResignActive() {
+        renderer?.onBackground()
     }
     
     override func magnify(with event: NSEvent) {
@@ -0,0 +1,19 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use enchantron::bench::SimplexRect;
+
+fn bench_simplex_rect(c: &mut Criterion) {
+    let simplex_rect = SimplexRect::new();
+    let mut group = c.benchmark_group("simplex_rect");
+
+    group.bench_function("points", |b| {
+        b.iter(|| simplex_rect.generate_points())
+    });
+    group.bench_function("batched", |b| {
+        b.iter(|| simplex_rect.generate_batched())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_simplex_rect);
+criterion_main!(benches);
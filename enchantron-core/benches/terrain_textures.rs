@@ -0,0 +1,15 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use enchantron::bench::TerrainUpload;
+
+fn bench_terrain_upload(c: &mut Criterion) {
+    let upload = TerrainUpload::new();
+    let mut group = c.benchmark_group("terrain_chunk_upload");
+
+    group.bench_function("png", |b| b.iter(|| upload.load_as_png()));
+    group.bench_function("raw_pixels", |b| b.iter(|| upload.load_as_pixels()));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_terrain_upload);
+criterion_main!(benches);
@@ -444,6 +444,7 @@ lazy_static! {
         fn transition_to_loading_view();
         fn transition_to_main_menu_view(view: swift_struct!(NativeView));
         fn transition_to_game_view(view: swift_struct!(NativeView));
+        fn on_background();
     }),
 
     // UI Components
@@ -646,6 +647,7 @@ lazy_static! {
             fn get_resource_loader() -> swift_struct!(Self::TL = ResourceLoader);
             fn create_native_view() -> swift_struct!(Self::NV = NativeView);
             fn get_transition_service() -> swift_struct!(Self::TS = TransitionService);
+            fn get_save_directory() -> STRING;
             fn create_loading_view() -> rust_struct!(Self::LV = crate::view::LoadingViewPublic<ViewTypes>) => {
                 crate::view::LoadingViewPublic::new(self.create_native_view())
             };
@@ -665,6 +667,14 @@ lazy_static! {
 
             fn load_texture(name: STRING) -> swift_struct!(Self::T = Texture);
             fn load_texture_from_png_data(png_data: TEXTURE_DATA) -> swift_struct!(Self::T = Texture);
+            fn load_texture_from_pixels(
+                pixel_data: TEXTURE_DATA,
+                palette: TEXTURE_DATA,
+                width: LONG,
+                height: LONG,
+                pixel_format: LONG) -> swift_struct!(Self::T = Texture);
+            fn load_text(name: STRING) -> STRING;
+            fn get_texture_path(name: STRING) -> STRING;
             fn create_animation() -> swift_struct!(Self::A = Animation);
         }
     }),
@@ -1,4 +1,4 @@
-use crate::event::EventBus;
+use crate::event::{ApplicationBackgrounded, EventBus};
 use crate::native::{RuntimeResources, SystemInterop};
 use crate::presenter::{GamePresenter, LoadingPresenter, MainMenuPresenter};
 use crate::view_types::ViewTypes;
@@ -162,6 +162,14 @@ impl<T: ViewTypes> ApplicationContext<T> {
     }
 }
 
+impl<T: ViewTypes> ApplicationContext<T> {
+    pub fn on_background(&self) {
+        debug!("Application moved to background");
+
+        self.event_bus.post(ApplicationBackgrounded::new());
+    }
+}
+
 impl<T: ViewTypes> ApplicationContextInner<T> {
     pub fn set_runtime_resources(
         &self,
@@ -0,0 +1,146 @@
+// Hooks into the crate internals for the criterion benchmarks in benches/.
+// Only built with the bench feature
+
+use crate::game::{BiomeTerrain, TerrainType};
+use crate::headless::HeadlessResourceLoader;
+use crate::img::PngGenerator;
+use crate::model::{IRect, ISize, Point};
+use crate::native::{PixelFormat, ResourceLoader};
+use crate::ui::{get_texture_data_for_rect, TerrainAutotiler};
+use crate::util::{ByteBuffer, SimplexGenerator, ValueRect};
+
+/// Number of chunks loaded on each iteration of the upload benchmarks
+const UPLOAD_CHUNK_COUNT: i64 = 16;
+
+/// Width and height of each chunk in tiles and in pixels
+const UPLOAD_CHUNK_LENGTH: usize = 128;
+
+/// Width and height in tiles of the rect filled by the simplex benchmarks
+const SIMPLEX_RECT_LENGTH: usize = 256;
+
+/// Number of tiles per unit of noise in the simplex benchmarks
+const SIMPLEX_SCALE: f64 = 40.;
+
+/// Indexed terrain chunks for comparing uploading them as encoded pngs with
+/// uploading the raw pixels
+pub struct TerrainUpload {
+    loader: HeadlessResourceLoader,
+    chunks: Vec<ByteBuffer>,
+    palette: Vec<u8>,
+    size: ISize,
+}
+
+impl TerrainUpload {
+    pub fn new() -> TerrainUpload {
+        let loader = HeadlessResourceLoader::default();
+        let autotiler = TerrainAutotiler::load(&loader);
+        let terrain_generator = BiomeTerrain::default();
+        let size = ISize::new(UPLOAD_CHUNK_LENGTH, UPLOAD_CHUNK_LENGTH);
+        let length = UPLOAD_CHUNK_LENGTH as i64;
+
+        let chunks = (0..UPLOAD_CHUNK_COUNT)
+            .map(|i| {
+                let rect = IRect::new(length * i, 0, size.width, size.height);
+
+                get_texture_data_for_rect(
+                    &rect,
+                    &size,
+                    &terrain_generator,
+                    &autotiler,
+                )
+            })
+            .collect();
+
+        TerrainUpload {
+            loader,
+            chunks,
+            palette: TerrainType::get_palette(),
+            size,
+        }
+    }
+
+    /// Encode each chunk as a png and load the textures from the png data
+    pub fn load_as_png(&self) {
+        for pixel_data in &self.chunks {
+            let mut png_data = ByteBuffer::new(Vec::with_capacity(4096));
+
+            PngGenerator::get_png_indexed(
+                pixel_data,
+                self.palette.clone(),
+                &self.size,
+                &mut png_data,
+            );
+
+            self.loader.load_texture_from_png_data(png_data);
+        }
+    }
+
+    /// Load the textures straight from each chunk's palette indices
+    pub fn load_as_pixels(&self) {
+        for pixel_data in &self.chunks {
+            self.loader.load_texture_from_pixels(
+                pixel_data.clone(),
+                ByteBuffer::new(self.palette.clone()),
+                self.size.width as i64,
+                self.size.height as i64,
+                PixelFormat::Indexed.code(),
+            );
+        }
+    }
+}
+
+impl Default for TerrainUpload {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rect of simplex noise for comparing generating each point on its own with
+/// generating the points in batches
+pub struct SimplexRect {
+    generator: SimplexGenerator,
+    rect: IRect,
+    offset: Point,
+}
+
+impl SimplexRect {
+    pub fn new() -> SimplexRect {
+        SimplexRect {
+            generator: SimplexGenerator {},
+            rect: IRect::new(0, 0, SIMPLEX_RECT_LENGTH, SIMPLEX_RECT_LENGTH),
+            offset: Point::new(0.5, 0.25),
+        }
+    }
+
+    /// Generate the noise for each point in the rect one at a time
+    pub fn generate_points(&self) -> ValueRect<f32> {
+        ValueRect::new_from_rect(self.rect, 1, 1, |point| {
+            let mut scaled_point = Point::new(point.x as f64, point.y as f64);
+
+            scaled_point *= 1. / SIMPLEX_SCALE;
+            scaled_point += &self.offset;
+
+            self.generator.generate(scaled_point)
+        })
+    }
+
+    /// Generate the noise for the whole rect in batches
+    pub fn generate_batched(&self) -> ValueRect<f32> {
+        let mut values =
+            ValueRect::new_from_rect_with_defaults(self.rect, 1, 1);
+
+        self.generator.generate_rect(
+            &mut values,
+            &self.offset,
+            Some((SIMPLEX_SCALE, 1.)),
+        );
+
+        values
+    }
+}
+
+impl Default for SimplexRect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,5 @@
+// Play the game in a terminal. Build with `--features terminal`
+
+fn main() {
+    enchantron::run_terminal();
+}
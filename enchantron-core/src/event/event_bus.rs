@@ -1,4 +1,5 @@
-use crate::model::{Point, Size};
+use crate::game::{Entity, EntityType, TerrainType};
+use crate::model::{IPoint, Point, Size};
 use crate::ui::{RawTouch, TouchEventType, ViewportInfo};
 
 define_event_bus!(
@@ -8,6 +9,14 @@ define_event_bus!(
     StartGame{ pub new: bool },
     StopGameRequested{},
     GameStopped{},
+    GameSaved{},
+    ApplicationBackgrounded{},
+    SpawnEntityRequested{ pub entity_type: EntityType, pub location: IPoint },
+    DespawnEntityRequested{ pub entity: Entity },
+    ModifyTerrainRequested{
+        pub position: IPoint,
+        pub terrain_type: Option<TerrainType>
+    },
     UI{ pub event: UIEvent },
     ViewportChange{ pub new_viewport: ViewportInfo }
 );
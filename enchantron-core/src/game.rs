@@ -1,3 +1,4 @@
+pub use self::biome_terrain::BiomeTerrain;
 pub use self::direction::Direction;
 pub use self::entity::Entity;
 pub use self::entity_data::EntityData;
@@ -9,19 +10,33 @@ pub use self::entity_type::EntityType;
 pub use self::gor::Gor;
 pub use self::location_service::{LocationService, SaveableLocation};
 pub use self::message_service::MessageService;
-pub use self::perlin_terrain_1::PerlinTerrain1;
+pub use self::modified_terrain::ModifiedTerrain;
+pub use self::move_error::MoveError;
+pub use self::pathfinding_service::PathfindingService;
 pub use self::player::Player;
 pub use self::presenter_service::{PresenterService, PresenterServiceLease};
+pub use self::save_error::SaveError;
+pub use self::save_slot_manager::SaveSlotManager;
+pub use self::save_slot_metadata::SaveSlotMetadata;
 pub use self::saved_game::SavedGame;
+pub use self::saved_game_record::CURRENT_SAVE_VERSION;
 pub use self::services::Services;
-pub use self::simplex_terrain_1::SimplexTerrain1;
+pub use self::terrain_config::{
+    BiomeRule, BiomeTerrainConfig, OctaveConfig, TerrainConfig,
+    TERRAIN_CONFIG_RESOURCE,
+};
+pub use self::terrain_modifications::TerrainModifications;
+pub use self::terrain_properties::TerrainProperties;
 pub use self::terrain_provider::TerrainProvider;
+pub use self::terrain_region::TerrainRegion;
+pub use self::terrain_service::TerrainService;
 pub use self::terrain_type::TerrainType;
 pub use self::time::Time;
 pub use self::view_service::ViewService;
 
 pub mod constants;
 
+mod biome_terrain;
 mod direction;
 mod entity;
 mod entity_data;
@@ -33,13 +48,24 @@ mod entity_type;
 mod gor;
 mod location_service;
 mod message_service;
-mod perlin_terrain_1;
+mod modified_terrain;
+mod move_error;
+mod pathfinding_service;
 mod player;
 mod presenter_service;
+mod save_error;
+mod save_migrations;
+mod save_slot_manager;
+mod save_slot_metadata;
 mod saved_game;
+mod saved_game_record;
 mod services;
-mod simplex_terrain_1;
+mod terrain_config;
+mod terrain_modifications;
+mod terrain_properties;
 mod terrain_provider;
+mod terrain_region;
+mod terrain_service;
 mod terrain_type;
 mod time;
 mod view_service;
@@ -0,0 +1,205 @@
+use crate::{
+    model::{IPoint, IRect, Point},
+    util::{SimplexGenerator, ValueRect},
+};
+
+use super::{BiomeTerrainConfig, OctaveConfig, TerrainProvider, TerrainType};
+
+/// Fractal noise built from a few octaves of simplex noise
+#[derive(Debug)]
+struct NoiseField {
+    offset: Point,
+    octaves: Vec<OctaveConfig>,
+}
+
+impl NoiseField {
+    fn new(
+        seed: u64,
+        field_index: u64,
+        octaves: &[OctaveConfig],
+    ) -> NoiseField {
+        NoiseField {
+            offset: SimplexGenerator::get_seed_offset(seed, field_index),
+            octaves: octaves.to_vec(),
+        }
+    }
+
+    fn get(&self, gen: &SimplexGenerator, position: &IPoint) -> f32 {
+        let mut result = 0.;
+
+        for octave in &self.octaves {
+            let mut scaled_point =
+                Point::new(position.x as f64, position.y as f64);
+
+            scaled_point *= 1. / octave.scale;
+            scaled_point += &self.offset;
+
+            result += octave.weight * gen.generate(scaled_point);
+        }
+
+        result
+    }
+
+    /// Get the noise for every value in a rect of the given stride. The
+    /// values match calling `get` for each point
+    fn get_rect(
+        &self,
+        gen: &SimplexGenerator,
+        rect: &IRect,
+        stride: usize,
+    ) -> ValueRect<f32> {
+        let mut result =
+            ValueRect::new_from_rect_with_defaults(*rect, stride, stride);
+
+        gen.generate_rect(
+            &mut result,
+            &self.offset,
+            self.octaves
+                .iter()
+                .map(|octave| (octave.scale, octave.weight)),
+        );
+
+        result
+    }
+}
+
+/// Terrain generated by classifying independent elevation, moisture and
+/// temperature fields into biomes
+#[derive(Debug)]
+pub struct BiomeTerrain {
+    gen: SimplexGenerator,
+    elevation: NoiseField,
+    moisture: NoiseField,
+    temperature: NoiseField,
+    config: BiomeTerrainConfig,
+}
+
+impl BiomeTerrain {
+    pub fn new(seed: u64) -> BiomeTerrain {
+        BiomeTerrain::from_config(&Default::default(), seed)
+    }
+
+    pub fn from_config(config: &BiomeTerrainConfig, seed: u64) -> BiomeTerrain {
+        BiomeTerrain {
+            gen: SimplexGenerator {},
+            elevation: NoiseField::new(seed, 0, &config.elevation),
+            moisture: NoiseField::new(seed, 1, &config.moisture),
+            temperature: NoiseField::new(seed, 2, &config.temperature),
+            config: config.clone(),
+        }
+    }
+
+    /// Get the elevation at the given position along with the terrain there
+    fn get_with_elevation(&self, position: &IPoint) -> (f64, TerrainType) {
+        let elevation = self.elevation.get(&self.gen, position);
+        let moisture = self.moisture.get(&self.gen, position);
+        let temperature = self.temperature.get(&self.gen, position);
+
+        (
+            elevation as f64,
+            self.config.classify(elevation, moisture, temperature),
+        )
+    }
+}
+
+impl Default for BiomeTerrain {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl TerrainProvider for BiomeTerrain {
+    fn get_for(&self, position: &IPoint) -> TerrainType {
+        self.get_with_elevation(position).1
+    }
+
+    fn get_for_rect(&self, rect: &IRect) -> ValueRect<(f64, TerrainType)> {
+        self.get_for_rect_with_stride(rect, 1)
+    }
+
+    fn get_for_rect_with_stride(
+        &self,
+        rect: &IRect,
+        stride: usize,
+    ) -> ValueRect<(f64, TerrainType)> {
+        let mut elevation = self.elevation.get_rect(&self.gen, rect, stride);
+        let mut moisture = self.moisture.get_rect(&self.gen, rect, stride);
+        let mut temperature =
+            self.temperature.get_rect(&self.gen, rect, stride);
+
+        // All the fields have the same layout as the terrain, so their values
+        // line up with the order the terrain is filled in
+        let mut fields = elevation
+            .get_raw_values_mut()
+            .iter()
+            .zip(moisture.get_raw_values_mut().iter())
+            .zip(temperature.get_raw_values_mut().iter());
+
+        ValueRect::new_from_rect(*rect, stride, stride, |_| {
+            let ((elevation, moisture), temperature) =
+                fields.next().expect("Noise fields smaller than terrain");
+
+            (
+                *elevation as f64,
+                self.config.classify(*elevation, *moisture, *temperature),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_biome_variety() {
+        let terrain = BiomeTerrain::new(3);
+        let mut seen = HashSet::new();
+
+        for y in 0..200 {
+            for x in 0..200 {
+                seen.insert(terrain.get_for(&IPoint::new(x * 40, y * 40)));
+            }
+        }
+
+        for terrain_type in &[
+            TerrainType::Water,
+            TerrainType::Sand,
+            TerrainType::Grass,
+            TerrainType::ForestFloor,
+            TerrainType::Rock,
+        ] {
+            assert!(seen.contains(terrain_type), "No {:?}", terrain_type);
+        }
+    }
+
+    #[test]
+    fn test_rect_matches_points() {
+        let terrain = BiomeTerrain::new(11);
+        let rect = IRect::new(-20, 35, 16, 16);
+
+        terrain.get_for_rect(&rect).for_each_value_coord(
+            |coord, (_, terrain_type)| {
+                let position = IPoint::new(
+                    rect.top_left.x + coord.x as i64,
+                    rect.top_left.y + coord.y as i64,
+                );
+                assert_eq!(&terrain.get_for(&position), terrain_type);
+            },
+        );
+
+        let stride = 8;
+        let coarse_rect = IRect::new(-512, 256, 256, 128);
+
+        terrain
+            .get_for_rect_with_stride(&coarse_rect, stride)
+            .for_each_value_coord(|coord, value| {
+                let position = IPoint::new(
+                    coarse_rect.top_left.x + (coord.x * stride) as i64,
+                    coarse_rect.top_left.y + (coord.y * stride) as i64,
+                );
+                assert_eq!(&terrain.get_with_elevation(&position), value);
+            });
+    }
+}
@@ -3,3 +3,12 @@ pub const ENTITY_Z_LEVEL: f64 = TERRAIN_Z_LEVEL + 10.0;
 pub const TILE_SCALE: f64 = 16.;
 pub const GRASS_GREEN_RGB: [u8; 3] = [0x90, 0xEE, 0x90];
 pub const DIRT_BROWN_RGB: [u8; 3] = [0x65, 0x43, 0x21];
+pub const WATER_BLUE_RGB: [u8; 3] = [0x3A, 0x6E, 0xC8];
+pub const SAND_YELLOW_RGB: [u8; 3] = [0xE8, 0xD7, 0x8E];
+pub const FOREST_GREEN_RGB: [u8; 3] = [0x2E, 0x6B, 0x30];
+pub const ROCK_GRAY_RGB: [u8; 3] = [0x80, 0x80, 0x80];
+pub const SNOW_WHITE_RGB: [u8; 3] = [0xF4, 0xF8, 0xFC];
+
+/// Whether entities can step diagonally between tiles as well as along the
+/// axes
+pub const DIAGONAL_MOVEMENT: bool = false;
@@ -1,27 +1,130 @@
 use crate::model::IPoint;
+use std::f64::consts::SQRT_2;
 
 const NORTH_POINT: IPoint = IPoint { x: 0, y: -1 };
+const NORTH_EAST_POINT: IPoint = IPoint { x: 1, y: -1 };
 const EAST_POINT: IPoint = IPoint { x: 1, y: 0 };
+const SOUTH_EAST_POINT: IPoint = IPoint { x: 1, y: 1 };
 const SOUTH_POINT: IPoint = IPoint { x: 0, y: 1 };
+const SOUTH_WEST_POINT: IPoint = IPoint { x: -1, y: 1 };
 const WEST_POINT: IPoint = IPoint { x: -1, y: 0 };
+const NORTH_WEST_POINT: IPoint = IPoint { x: -1, y: -1 };
 
-#[allow(dead_code)]
+#[allow(dead_code, non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Direction {
     NORTH,
+    NORTH_EAST,
     EAST,
+    SOUTH_EAST,
     SOUTH,
+    SOUTH_WEST,
     WEST,
+    NORTH_WEST,
 }
 
 impl Direction {
+    /// The directions along the axes in clockwise order starting from north
+    pub const CARDINAL: [Direction; 4] = [
+        Direction::NORTH,
+        Direction::EAST,
+        Direction::SOUTH,
+        Direction::WEST,
+    ];
+
+    /// All the directions including the diagonals in clockwise order starting
+    /// from north
+    pub const ALL: [Direction; 8] = [
+        Direction::NORTH,
+        Direction::NORTH_EAST,
+        Direction::EAST,
+        Direction::SOUTH_EAST,
+        Direction::SOUTH,
+        Direction::SOUTH_WEST,
+        Direction::WEST,
+        Direction::NORTH_WEST,
+    ];
+
+    /// Get the direction of a step by the given offset, or None if the offset
+    /// isn't a single step in one of the directions
+    pub fn from_point(point: &IPoint) -> Option<Direction> {
+        use Direction::*;
+
+        match *point {
+            NORTH_POINT => Some(NORTH),
+            NORTH_EAST_POINT => Some(NORTH_EAST),
+            EAST_POINT => Some(EAST),
+            SOUTH_EAST_POINT => Some(SOUTH_EAST),
+            SOUTH_POINT => Some(SOUTH),
+            SOUTH_WEST_POINT => Some(SOUTH_WEST),
+            WEST_POINT => Some(WEST),
+            NORTH_WEST_POINT => Some(NORTH_WEST),
+            _ => None,
+        }
+    }
+
     pub fn get_point(&self) -> &'static IPoint {
         use Direction::*;
 
         match self {
             NORTH => &NORTH_POINT,
+            NORTH_EAST => &NORTH_EAST_POINT,
             EAST => &EAST_POINT,
+            SOUTH_EAST => &SOUTH_EAST_POINT,
             SOUTH => &SOUTH_POINT,
+            SOUTH_WEST => &SOUTH_WEST_POINT,
             WEST => &WEST_POINT,
+            NORTH_WEST => &NORTH_WEST_POINT,
         }
     }
+
+    pub fn is_diagonal(&self) -> bool {
+        let point = self.get_point();
+        point.x != 0 && point.y != 0
+    }
+
+    /// Get the distance in tiles covered by a step in this direction
+    pub fn get_length(&self) -> f64 {
+        if self.is_diagonal() {
+            SQRT_2
+        } else {
+            1.
+        }
+    }
+
+    /// Get the cardinal direction a character faces while moving in this
+    /// direction. Characters moving diagonally face sideways
+    pub fn get_facing(&self) -> Direction {
+        use Direction::*;
+
+        match self {
+            NORTH_EAST | SOUTH_EAST => EAST,
+            SOUTH_WEST | NORTH_WEST => WEST,
+            cardinal => *cardinal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_directions() {
+        for direction in &Direction::ALL {
+            assert_eq!(
+                Some(*direction),
+                Direction::from_point(direction.get_point())
+            );
+        }
+
+        assert_eq!(None, Direction::from_point(&IPoint::new(2, 0)));
+        assert_eq!(None, Direction::from_point(&IPoint::new(0, 0)));
+
+        assert_eq!(1., Direction::WEST.get_length());
+        assert_eq!(SQRT_2, Direction::SOUTH_EAST.get_length());
+        assert_eq!(Direction::EAST, Direction::NORTH_EAST.get_facing());
+        assert_eq!(Direction::WEST, Direction::SOUTH_WEST.get_facing());
+        assert_eq!(Direction::SOUTH, Direction::SOUTH.get_facing());
+    }
 }
@@ -7,6 +7,14 @@ pub enum Entity {
     Player(SlotMapKeyData),
 }
 
+impl Entity {
+    pub fn entity_type(&self) -> EntityType {
+        match self {
+            Self::Player(_) => EntityType::Player,
+        }
+    }
+}
+
 impl SlotMapKey<EntityType> for Entity {}
 
 impl From<(EntityType, SlotMapKeyData)> for Entity {
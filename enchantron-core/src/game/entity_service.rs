@@ -1,6 +1,6 @@
 use super::{Entity, EntityData, EntityType, Gor, Player};
 use crate::util::ConcurrentSlotmap;
-use one_way_slot_map::SlotMap;
+use one_way_slot_map::{SlotMap, SlotMapKeyData};
 
 /// This is effectively the main service. It controls entity creation, storage,
 /// and messaging.
@@ -26,6 +26,41 @@ impl EntityService {
     pub fn get_player(&self) -> Player {
         self.inner.player
     }
+
+    /// Get a copy of the data for the given entity
+    pub fn get(&self, entity: &Entity) -> Option<EntityData> {
+        self.inner.entities.get(entity).map(|data| *data)
+    }
+
+    /// Add a new entity of the given type. Its data starts out empty, so it
+    /// should be updated once the entity's other parts are created
+    pub async fn insert(&self, entity_type: EntityType) -> Entity {
+        self.inner
+            .entities
+            .insert(entity_type, EntityData::default_for_type(entity_type))
+            .await
+    }
+
+    pub async fn update(&self, entity: Entity, entity_data: EntityData) {
+        self.inner.entities.update(entity, entity_data).await
+    }
+
+    pub async fn remove(&self, entity: &Entity) {
+        self.inner.entities.remove(entity).await
+    }
+
+    /// Get a copy of the data for all entities along with the raw key data
+    /// for each entity
+    pub fn to_raw_entity_data(&self) -> Vec<(SlotMapKeyData, EntityData)> {
+        self.inner
+            .entities
+            .reader()
+            .iter_raw()
+            .filter_map(|(key_data, entity_data)| {
+                entity_data.map(|entity_data| (key_data, entity_data))
+            })
+            .collect()
+    }
 }
 
 #[derive(derive_new::new, Debug)]
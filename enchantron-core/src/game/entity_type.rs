@@ -1,5 +1,17 @@
 /// Enumeration of type of entities
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum EntityType {
     Player,
 }
+
+impl EntityType {
+    /// Check if entities of this type are kept from moving onto tiles
+    /// occupied by entities of the other type
+    pub fn is_blocked_by(&self, other: &EntityType) -> bool {
+        use EntityType::*;
+
+        match (self, other) {
+            (Player, Player) => true,
+        }
+    }
+}
@@ -1,10 +1,17 @@
-use super::{Entity, Gor, LocationKey};
+use super::{
+    BiomeTerrain, Entity, EntityType, Gor, LocationKey, ModifiedTerrain,
+    MoveError, TerrainProvider,
+};
 use crate::model::{IPoint, IRect, ISize};
 use one_way_slot_map::SlotMap;
 use rstar::{PointDistance, RTree, RTreeObject};
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::ptr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{
+    unbounded_channel, UnboundedReceiver, UnboundedSender,
+};
 use tokio::sync::RwLock;
 
 #[derive(Debug, Copy, Clone, derive_new::new)]
@@ -28,6 +35,27 @@ fn get_windowed_offset_for(entity: &Entity) -> usize {
     }
 }
 
+/// Get the squared distance between the given tile and the closest tile in the
+/// given location. Unlike `IRect::distance_squared`, this measures between
+/// tiles rather than to the edges of the rect, so a neighbouring tile is 1
+/// away whichever side it's on
+fn get_tile_distance_squared(location: &IRect, point: &IPoint) -> i64 {
+    let bottom_right = location.bottom_right_inclusive();
+    let closest = IPoint::new(
+        point.x.clamp(location.top_left.x, bottom_right.x),
+        point.y.clamp(location.top_left.y, bottom_right.y),
+    );
+
+    closest.distance_2(point)
+}
+
+fn matches_type(entity: &Entity, entity_type: Option<EntityType>) -> bool {
+    match entity_type {
+        Some(entity_type) => entity.entity_type() == entity_type,
+        None => true,
+    }
+}
+
 impl PartialEq for WindowedPointer {
     fn eq(&self, other: &WindowedPointer) -> bool {
         self.read().entity == other.read().entity
@@ -43,9 +71,11 @@ impl RTreeObject for WindowedPointer {
 }
 
 impl PointDistance for WindowedPointer {
-    /// Returns the squared euclidean distance of an object to a point.
+    /// Returns the squared euclidean distance of an object to a point. This is
+    /// never less than the distance to the window, so nearest neighbor queries
+    /// through the windows are still ordered by the actual locations
     fn distance_2(&self, point: &IPoint) -> i64 {
-        self.read().location.distance_squared(point)
+        get_tile_distance_squared(&self.read().location, point)
     }
 
     /// Returns true if a point is contained within this object.
@@ -158,6 +188,8 @@ impl Clone for WindowedPointer {
 #[derive(Clone, Debug)]
 pub struct LocationService {
     inner: Gor<RwLock<Inner>>,
+    terrain: Arc<ModifiedTerrain<BiomeTerrain>>,
+    listeners: Arc<Mutex<Vec<UnboundedSender<IRect>>>>,
 }
 
 #[derive(Debug)]
@@ -169,20 +201,41 @@ struct Inner {
 
 #[allow(dead_code)]
 impl LocationService {
-    pub fn new() -> (LocationService, impl FnOnce() + Send) {
+    pub fn new(
+        terrain: Arc<ModifiedTerrain<BiomeTerrain>>,
+    ) -> (LocationService, impl FnOnce() + Send) {
         let boxed_inner = Box::new(RwLock::new(Inner::new()));
         let inner = Gor::new(&boxed_inner);
 
-        (LocationService { inner }, move || drop(boxed_inner))
+        let listeners = Default::default();
+
+        (
+            LocationService {
+                inner,
+                terrain,
+                listeners,
+            },
+            move || drop(boxed_inner),
+        )
     }
 
     pub fn new_from_data(
         data: &SlotMap<LocationKey, Entity, SaveableLocation>,
+        terrain: Arc<ModifiedTerrain<BiomeTerrain>>,
     ) -> (LocationService, impl FnOnce() + Send) {
         let boxed_inner = Box::new(RwLock::new(Inner::new_from_data(data)));
         let inner = Gor::new(&boxed_inner);
 
-        (LocationService { inner }, move || drop(boxed_inner))
+        let listeners = Default::default();
+
+        (
+            LocationService {
+                inner,
+                terrain,
+                listeners,
+            },
+            move || drop(boxed_inner),
+        )
     }
 
     async fn with_inner<T>(&self, action: impl FnOnce(&Inner) -> T) -> T {
@@ -200,8 +253,41 @@ impl LocationService {
         action(&mut *inner)
     }
 
+    /// Send the areas a location was in before and after it changed to the
+    /// subscribers
+    fn notify(&self, changed: impl IntoIterator<Item = Option<IRect>>) {
+        let mut listeners =
+            self.listeners.lock().expect("Location listeners poisoned");
+
+        for rect in changed.into_iter().flatten() {
+            listeners.retain(|listener| listener.send(rect).is_ok());
+        }
+    }
+
+    /// Get a receiver for the areas locations move out of or into. A moved
+    /// location is sent both where it was and where it went
+    pub fn subscribe(&self) -> UnboundedReceiver<IRect> {
+        let (sender, receiver) = unbounded_channel();
+
+        self.listeners
+            .lock()
+            .expect("Location listeners poisoned")
+            .push(sender);
+
+        receiver
+    }
+
     pub async fn insert(&self, e: Entity, location: IPoint) -> LocationKey {
-        self.with_inner_mut(|inner| inner.insert(e, location)).await
+        let (key, inserted) = self
+            .with_inner_mut(|inner| {
+                let key = inner.insert(e, location);
+                (key, inner.get_by_key(&key))
+            })
+            .await;
+
+        self.notify(vec![inserted]);
+
+        key
     }
 
     /// Get the current position for the given key
@@ -209,19 +295,145 @@ impl LocationService {
         self.with_inner(|inner| inner.get_by_key(key)).await
     }
 
+    /// Move the location with the given key unless the destination is
+    /// occupied by an entity that blocks it, in which case the blocking entity
+    /// is returned. The check and the move happen atomically, so two entities
+    /// can't move onto the same tile at once
+    pub async fn try_move_by_key(
+        &self,
+        key: &LocationKey,
+        new_location: IPoint,
+    ) -> Result<(), MoveError> {
+        let (result, before, after) = self
+            .with_inner_mut(|inner| {
+                let before = inner.get_by_key(key);
+                let result = inner.try_move_by_key(key, new_location);
+                (result, before, inner.get_by_key(key))
+            })
+            .await;
+
+        if result.is_ok() {
+            self.notify(vec![before, after]);
+        }
+
+        result
+    }
+
     pub async fn move_by_key(&self, key: &LocationKey, new_location: IPoint) {
-        self.with_inner_mut(|inner| inner.move_by_key(key, new_location))
-            .await
+        let (before, after) = self
+            .with_inner_mut(|inner| {
+                let before = inner.get_by_key(key);
+                inner.move_by_key(key, new_location);
+                (before, inner.get_by_key(key))
+            })
+            .await;
+
+        self.notify(vec![before, after]);
     }
 
-    pub async fn move_by_key_delta(&self, key: &LocationKey, shift: &IPoint) {
-        self.with_inner_mut(|inner| inner.move_by_key_delta(key, shift))
-            .await
+    /// Remove the location with the given key, returning where it was
+    pub async fn remove_by_key(&self, key: &LocationKey) -> Option<IRect> {
+        let removed =
+            self.with_inner_mut(|inner| inner.remove_by_key(key)).await;
+
+        self.notify(vec![removed]);
+
+        removed
+    }
+
+    /// Check if entities can walk onto the given tile
+    pub fn is_walkable(&self, position: &IPoint) -> bool {
+        self.terrain.get_for(position).properties().walkable
+    }
+
+    /// Step the location with the given key by the given shift. Returns false
+    /// without moving if the terrain at the destination can't be walked on or
+    /// the destination is occupied by an entity that blocks it
+    pub async fn move_by_key_delta(
+        &self,
+        key: &LocationKey,
+        shift: &IPoint,
+    ) -> bool {
+        let terrain = &self.terrain;
+
+        let moved = self
+            .with_inner_mut(|inner| {
+                let before = inner.get_by_key(key)?;
+                let destination = &before.top_left + shift;
+
+                if !terrain.get_for(&destination).properties().walkable {
+                    return None;
+                }
+
+                inner
+                    .try_move_by_key(key, destination)
+                    .ok()
+                    .map(|_| (before, inner.get_by_key(key)))
+            })
+            .await;
+
+        match moved {
+            Some((before, after)) => {
+                self.notify(vec![Some(before), after]);
+                true
+            }
+            None => false,
+        }
     }
 
     pub async fn get_entities_at(&self, point: &IPoint) -> Vec<Entity> {
         self.with_inner(|inner| inner.get_entities_at(point)).await
     }
+
+    /// Get the entities located in the given rect along with their locations
+    pub async fn get_locations_in(&self, rect: &IRect) -> Vec<(Entity, IRect)> {
+        self.with_inner(|inner| inner.get_locations_in(rect)).await
+    }
+
+    /// Get the entities located in the given rect, optionally only those of
+    /// the given type
+    pub async fn get_entities_in_rect(
+        &self,
+        rect: &IRect,
+        entity_type: Option<EntityType>,
+    ) -> Vec<Entity> {
+        self.with_inner(|inner| inner.get_entities_in_rect(rect, entity_type))
+            .await
+    }
+
+    /// Get the entities within the given number of tiles from the given point
+    /// as the crow flies, optionally only those of the given type
+    pub async fn get_entities_within(
+        &self,
+        point: &IPoint,
+        radius: usize,
+        entity_type: Option<EntityType>,
+    ) -> Vec<Entity> {
+        self.with_inner(|inner| {
+            inner.get_entities_within(point, radius, entity_type)
+        })
+        .await
+    }
+
+    /// Get up to `n` of the entities accepted by the filter that are closest
+    /// to the given point, closest first
+    pub async fn nearest_n(
+        &self,
+        point: &IPoint,
+        n: usize,
+        filter: impl Fn(&Entity) -> bool,
+    ) -> Vec<Entity> {
+        self.with_inner(|inner| inner.nearest_n(point, n, filter))
+            .await
+    }
+
+    /// Get a copy of all the locations keyed by the same keys used by this
+    /// service
+    pub async fn to_saveable_locations(
+        &self,
+    ) -> SlotMap<LocationKey, Entity, SaveableLocation> {
+        self.with_inner(Inner::to_saveable_locations).await
+    }
 }
 
 #[allow(dead_code)]
@@ -305,16 +517,34 @@ impl Inner {
         }
     }
 
-    fn move_by_key_delta(&mut self, key: &LocationKey, shift: &IPoint) {
-        if let Some(wp) = self.slot_map.get_mut(key) {
-            wp.write().location.top_left += shift;
-            if !wp.check_window_contains_location() {
-                let mut owned_wp = self
-                    .rtree
-                    .remove(wp)
-                    .expect("Entity pointer should still be in rtree");
-                owned_wp.recenter_window();
-                self.rtree.insert(owned_wp);
+    /// Get an entity at the given tile that keeps the given entity from moving
+    /// onto it
+    fn get_blocker(&self, mover: &Entity, tile: &IPoint) -> Option<Entity> {
+        let mover_type = mover.entity_type();
+
+        self.rtree
+            .locate_all_at_point(tile)
+            .map(|wp| wp.read().entity)
+            .find(|other| {
+                other != mover && mover_type.is_blocked_by(&other.entity_type())
+            })
+    }
+
+    fn try_move_by_key(
+        &mut self,
+        key: &LocationKey,
+        new_location: IPoint,
+    ) -> Result<(), MoveError> {
+        let mover = match self.slot_map.get(key) {
+            Some(wp) => wp.read().entity,
+            None => return Err(MoveError::Missing),
+        };
+
+        match self.get_blocker(&mover, &new_location) {
+            Some(blocker) => Err(MoveError::Blocked(blocker)),
+            None => {
+                self.move_by_key(key, new_location);
+                Ok(())
             }
         }
     }
@@ -326,6 +556,78 @@ impl Inner {
             .collect()
     }
 
+    fn get_locations_in(&self, rect: &IRect) -> Vec<(Entity, IRect)> {
+        // The rtree holds the windows around the locations, so entities found
+        // near the edges of the rect may be outside of it
+        self.rtree
+            .locate_in_envelope_intersecting(rect)
+            .map(WindowedPointer::read)
+            .filter(|wp| rect.intersection(&wp.location).is_some())
+            .map(|wp| (wp.entity, wp.location))
+            .collect()
+    }
+
+    fn get_entities_in_rect(
+        &self,
+        rect: &IRect,
+        entity_type: Option<EntityType>,
+    ) -> Vec<Entity> {
+        self.get_locations_in(rect)
+            .into_iter()
+            .map(|(entity, _)| entity)
+            .filter(|entity| matches_type(entity, entity_type))
+            .collect()
+    }
+
+    fn get_entities_within(
+        &self,
+        point: &IPoint,
+        radius: usize,
+        entity_type: Option<EntityType>,
+    ) -> Vec<Entity> {
+        let radius_2 = (radius * radius) as i64;
+        let bounds = IRect {
+            top_left: *point,
+            size: ISize::new(1, 1),
+        }
+        .expanded_by(radius);
+
+        // Every location within the radius is inside the bounds, so its window
+        // must intersect them too
+        self.rtree
+            .locate_in_envelope_intersecting(&bounds)
+            .map(WindowedPointer::read)
+            .filter(|wp| matches_type(&wp.entity, entity_type))
+            .filter(|wp| {
+                get_tile_distance_squared(&wp.location, point) <= radius_2
+            })
+            .map(|wp| wp.entity)
+            .collect()
+    }
+
+    fn nearest_n(
+        &self,
+        point: &IPoint,
+        n: usize,
+        filter: impl Fn(&Entity) -> bool,
+    ) -> Vec<Entity> {
+        self.rtree
+            .nearest_neighbor_iter(point)
+            .map(|wp| wp.read().entity)
+            .filter(|entity| filter(entity))
+            .take(n)
+            .collect()
+    }
+
+    fn to_saveable_locations(
+        &self,
+    ) -> SlotMap<LocationKey, Entity, SaveableLocation> {
+        self.slot_map.map(|wp| {
+            let inner = wp.read();
+            SaveableLocation::new(inner.location, inner.entity)
+        })
+    }
+
     fn remove_by_key(&mut self, key: &LocationKey) -> Option<IRect> {
         if let Some(wp_ref) = self.slot_map.remove(key) {
             let _ = self
@@ -409,4 +711,216 @@ mod test {
 
         assert_eq!(vec![player()], s.get_entities_at(&IPoint::new(1000, 1000)));
     }
+
+    #[test]
+    fn test_get_locations_in() {
+        let mut s = create_service();
+
+        s.insert(player(), IPoint::new(5, 5));
+
+        assert_eq!(
+            vec![(player(), IRect::new(5, 5, 1, 1))],
+            s.get_locations_in(&IRect::new(0, 0, 6, 6))
+        );
+
+        // The window around the location overlaps this rect but the location
+        // itself doesn't
+        assert!(s.get_locations_in(&IRect::new(0, 0, 5, 5)).is_empty());
+    }
+
+    /// Insert players at the given tiles, returning them in the same order
+    fn insert_players(s: &mut Inner, tiles: &[(i64, i64)]) -> Vec<Entity> {
+        let mut players = SlotMap::<Entity, EntityType, ()>::new();
+
+        tiles
+            .iter()
+            .map(|(x, y)| {
+                let entity = players.insert(EntityType::Player, ());
+                s.insert(entity, IPoint::new(*x, *y));
+                entity
+            })
+            .collect()
+    }
+
+    fn sorted(mut entities: Vec<Entity>) -> Vec<Entity> {
+        entities.sort_by_key(|entity| format!("{:?}", entity));
+        entities
+    }
+
+    #[test]
+    fn test_get_entities_in_rect() {
+        let mut s = create_service();
+        let players = insert_players(&mut s, &[(0, 0), (3, 3), (20, 20)]);
+
+        assert_eq!(
+            sorted(players[..2].to_vec()),
+            sorted(s.get_entities_in_rect(&IRect::new(0, 0, 4, 4), None))
+        );
+        assert_eq!(
+            vec![players[1]],
+            s.get_entities_in_rect(
+                &IRect::new(1, 1, 10, 10),
+                Some(EntityType::Player)
+            )
+        );
+
+        // Both windows overlap this rect, but neither location is in it
+        assert!(s
+            .get_entities_in_rect(&IRect::new(4, 4, 10, 10), None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_get_entities_within() {
+        let mut s = create_service();
+        let players =
+            insert_players(&mut s, &[(0, 0), (3, 0), (3, 4), (-2, -2)]);
+        let origin = IPoint::new(0, 0);
+
+        assert_eq!(vec![players[0]], s.get_entities_within(&origin, 0, None));
+        // The diagonal neighbour two tiles away is further than two tiles
+        assert_eq!(vec![players[0]], s.get_entities_within(&origin, 2, None));
+        assert_eq!(
+            sorted(vec![players[0], players[1], players[3]]),
+            sorted(s.get_entities_within(&origin, 3, None))
+        );
+
+        // Within the rect around the radius, but only on the circle at 5
+        assert_eq!(
+            4,
+            s.get_entities_within(&origin, 5, Some(EntityType::Player))
+                .len()
+        );
+        assert!(!s
+            .get_entities_within(&origin, 4, None)
+            .contains(&players[2]));
+
+        // The windows reach the point, but none of the locations do
+        assert!(s
+            .get_entities_within(&IPoint::new(9, 0), 5, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_nearest_n() {
+        let mut s = create_service();
+        let players =
+            insert_players(&mut s, &[(10, 0), (0, 4), (-3, 0), (100, 100)]);
+        let origin = IPoint::new(0, 0);
+
+        assert_eq!(
+            vec![players[2], players[1], players[0]],
+            s.nearest_n(&origin, 3, |_| true)
+        );
+        assert_eq!(
+            vec![players[2], players[1], players[0], players[3]],
+            s.nearest_n(&IPoint::new(-5, -5), 10, |_| true)
+        );
+        assert_eq!(
+            vec![players[1], players[0]],
+            s.nearest_n(&origin, 2, |entity| *entity != players[2])
+        );
+        assert!(s.nearest_n(&origin, 0, |_| true).is_empty());
+    }
+
+    #[test]
+    fn test_try_move_by_key() {
+        let mut s = create_service();
+        let players = insert_players(&mut s, &[(0, 0), (1, 0)]);
+        let key = s.get_by_entity(&players[0]).unwrap().0;
+
+        assert_eq!(
+            Err(MoveError::Blocked(players[1])),
+            s.try_move_by_key(&key, IPoint::new(1, 0))
+        );
+        assert_eq!(Some(IRect::new(0, 0, 1, 1)), s.get_by_key(&key));
+
+        // Entities don't block themselves
+        assert_eq!(Ok(()), s.try_move_by_key(&key, IPoint::new(0, 0)));
+
+        assert_eq!(Ok(()), s.try_move_by_key(&key, IPoint::new(0, 1)));
+        assert_eq!(Some(IRect::new(0, 1, 1, 1)), s.get_by_key(&key));
+
+        s.remove_by_key(&key);
+
+        assert_eq!(
+            Err(MoveError::Missing),
+            s.try_move_by_key(&key, IPoint::new(0, 2))
+        );
+    }
+
+    #[test]
+    fn test_move_delta_respects_walkability() {
+        use crate::game::{TerrainService, TerrainType};
+        use futures::executor::block_on;
+
+        let terrain_service = TerrainService::default();
+        let terrain = Arc::new(ModifiedTerrain::new(
+            BiomeTerrain::default(),
+            terrain_service.clone(),
+        ));
+
+        terrain_service.modify(IPoint::new(0, 1), Some(TerrainType::Grass));
+        terrain_service.modify(IPoint::new(0, 2), Some(TerrainType::Water));
+
+        let (service, dropper) = LocationService::new(terrain);
+
+        block_on(async {
+            let key = service.insert(player(), IPoint::new(0, 0)).await;
+
+            assert!(service.move_by_key_delta(&key, &IPoint::new(0, 1)).await);
+            assert!(!service.is_walkable(&IPoint::new(0, 2)));
+
+            // Occupied tiles can't be stepped onto either
+            let mut players = SlotMap::<Entity, EntityType, ()>::new();
+            let _ = players.insert(EntityType::Player, ());
+            let other = players.insert(EntityType::Player, ());
+            service.insert(other, IPoint::new(1, 1)).await;
+            assert!(!service.move_by_key_delta(&key, &IPoint::new(1, 0)).await);
+
+            assert!(!service.move_by_key_delta(&key, &IPoint::new(0, 1)).await);
+            assert_eq!(
+                Some(IRect::new(0, 1, 1, 1)),
+                service.get_by_key(&key).await
+            );
+        });
+
+        dropper();
+    }
+
+    #[test]
+    fn test_subscribe() {
+        use futures::executor::block_on;
+        use futures::FutureExt;
+
+        let terrain = Arc::new(ModifiedTerrain::new(
+            BiomeTerrain::default(),
+            Default::default(),
+        ));
+        let (service, dropper) = LocationService::new(terrain);
+        let mut changes = service.subscribe();
+
+        block_on(async {
+            let key = service.insert(player(), IPoint::new(0, 0)).await;
+            assert_eq!(Some(IRect::new(0, 0, 1, 1)), changes.recv().await);
+
+            // Moves are sent from both ends
+            assert!(service.move_by_key_delta(&key, &IPoint::new(1, 0)).await);
+            assert_eq!(Some(IRect::new(0, 0, 1, 1)), changes.recv().await);
+            assert_eq!(Some(IRect::new(1, 0, 1, 1)), changes.recv().await);
+
+            service.move_by_key(&key, IPoint::new(5, 5)).await;
+            assert_eq!(Some(IRect::new(1, 0, 1, 1)), changes.recv().await);
+            assert_eq!(Some(IRect::new(5, 5, 1, 1)), changes.recv().await);
+
+            assert!(service.remove_by_key(&key).await.is_some());
+            assert_eq!(Some(IRect::new(5, 5, 1, 1)), changes.recv().await);
+
+            // Nothing is sent when nothing changes
+            assert!(!service.move_by_key_delta(&key, &IPoint::new(1, 0)).await);
+            assert!(changes.recv().now_or_never().is_none());
+        });
+
+        dropper();
+    }
 }
@@ -28,6 +28,33 @@ impl MessageService {
         (MessageService { inner }, move || drop(boxed_inner))
     }
 
+    /// Add the sender for messages to the given entity. The message senders
+    /// are stored with the same keys as the entities, so this must be called
+    /// in the same order that entities are added and removed
+    pub async fn insert(&self, entity: &Entity, sender: Sender<EntityMessage>) {
+        let key = self
+            .inner
+            .entity_messagers
+            .insert(entity.entity_type(), Box::new(sender))
+            .await;
+
+        assert_eq!(
+            *entity, key,
+            "Message senders out of step with the entities"
+        );
+    }
+
+    /// Drop the sender for messages to the given entity, which closes its
+    /// message channel. The entity keeps its place until it's removed
+    pub async fn close(&self, entity: &Entity) {
+        self.inner.entity_messagers.empty(entity).await
+    }
+
+    /// Remove the sender for messages to the given entity along with its place
+    pub async fn remove(&self, entity: &Entity) {
+        self.inner.entity_messagers.remove(entity).await
+    }
+
     pub async fn send_message(&self, entity: &Entity, message: EntityMessage) {
         if let Some(sender) = self.inner.entity_messagers.get(entity) {
             let ok = sender.send(message).await.is_ok();
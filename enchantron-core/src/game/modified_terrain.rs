@@ -0,0 +1,96 @@
+use super::{TerrainProvider, TerrainService, TerrainType};
+use crate::model::{IPoint, IRect};
+use crate::util::ValueRect;
+
+/// Terrain provider that layers the player's modifications over the terrain
+/// from another provider
+#[derive(Debug)]
+pub struct ModifiedTerrain<P: TerrainProvider> {
+    base: P,
+    terrain_service: TerrainService,
+}
+
+impl<P: TerrainProvider> ModifiedTerrain<P> {
+    pub fn new(base: P, terrain_service: TerrainService) -> ModifiedTerrain<P> {
+        ModifiedTerrain {
+            base,
+            terrain_service,
+        }
+    }
+
+    /// Get the revision of the latest modification
+    pub fn revision(&self) -> u64 {
+        self.terrain_service.revision()
+    }
+
+    /// Check if any tile in the given rect may have been modified after the
+    /// given revision
+    pub fn modified_since(&self, rect: &IRect, revision: u64) -> bool {
+        self.terrain_service.modified_since(rect, revision)
+    }
+}
+
+impl<P: TerrainProvider> TerrainProvider for ModifiedTerrain<P> {
+    fn get_for(&self, position: &IPoint) -> TerrainType {
+        self.terrain_service
+            .get_modification(position)
+            .unwrap_or_else(|| self.base.get_for(position))
+    }
+
+    fn get_for_rect(&self, rect: &IRect) -> ValueRect<(f64, TerrainType)> {
+        let mut result = self.base.get_for_rect(rect);
+        self.terrain_service.apply_to(&mut result);
+        result
+    }
+
+    fn get_for_rect_with_stride(
+        &self,
+        rect: &IRect,
+        stride: usize,
+    ) -> ValueRect<(f64, TerrainType)> {
+        let mut result = self.base.get_for_rect_with_stride(rect, stride);
+        self.terrain_service.apply_to(&mut result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::BiomeTerrain;
+
+    #[test]
+    fn test_modifications_override_base() {
+        let terrain_service = TerrainService::default();
+        let terrain =
+            ModifiedTerrain::new(BiomeTerrain::new(5), terrain_service.clone());
+        let base = BiomeTerrain::new(5);
+        let rect = IRect::new(-8, -8, 16, 16);
+        let position = IPoint::new(-3, 2);
+
+        let modified_type = if base.get_for(&position) == TerrainType::Dirt {
+            TerrainType::Rock
+        } else {
+            TerrainType::Dirt
+        };
+
+        terrain_service.modify(position, Some(modified_type));
+
+        assert_eq!(modified_type, terrain.get_for(&position));
+
+        terrain.get_for_rect(&rect).for_each_value_coord(
+            |coord, (_, terrain_type)| {
+                let point = IPoint::new(
+                    rect.top_left.x + coord.x as i64,
+                    rect.top_left.y + coord.y as i64,
+                );
+
+                assert_eq!(terrain.get_for(&point), *terrain_type);
+            },
+        );
+
+        terrain_service.modify(position, None);
+
+        assert_eq!(base.get_for(&position), terrain.get_for(&position));
+    }
+}
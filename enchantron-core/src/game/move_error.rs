@@ -0,0 +1,26 @@
+use super::Entity;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Reasons an entity's location couldn't be moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    /// The destination is occupied by the given entity, which the mover
+    /// can't share a tile with
+    Blocked(Entity),
+    /// There is no location with the given key
+    Missing,
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            MoveError::Blocked(blocker) => {
+                write!(f, "Destination is blocked by {:?}", blocker)
+            }
+            MoveError::Missing => write!(f, "No location to move"),
+        }
+    }
+}
+
+impl Error for MoveError {}
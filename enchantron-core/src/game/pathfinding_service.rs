@@ -0,0 +1,418 @@
+use super::{
+    BiomeTerrain, Direction, Entity, LocationService, ModifiedTerrain,
+    TerrainProvider,
+};
+use crate::model::{IPoint, IRect, ISize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::f64::consts::SQRT_2;
+use std::sync::Arc;
+
+/// Number of tiles around the start and goal that paths can wander through to
+/// get around obstacles
+const SEARCH_MARGIN: usize = 16;
+
+/// Most tiles from the start that a path can reach. Goals further away than
+/// this are approached as closely as possible
+const MAX_SEARCH_DISTANCE: i64 = 128;
+
+/// Tile waiting to be expanded by the search, ordered so the tile with the
+/// lowest estimated total cost is popped from the heap first
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OpenTile {
+    estimate: f64,
+    cost: f64,
+    tile: IPoint,
+}
+
+impl Eq for OpenTile {}
+
+impl Ord for OpenTile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Prefer tiles further along when the estimates tie, they're more
+        // likely to be on the final path
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
+                self.cost
+                    .partial_cmp(&other.cost)
+                    .unwrap_or(Ordering::Equal)
+            })
+    }
+}
+
+impl PartialOrd for OpenTile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Get the least number of tiles walked between the given tiles when stepping
+/// in the given directions. Diagonal steps cover the square root of two tiles
+fn get_distance(from: &IPoint, to: &IPoint, diagonal: bool) -> f64 {
+    let dx = (from.x - to.x).abs() as f64;
+    let dy = (from.y - to.y).abs() as f64;
+
+    if diagonal {
+        dx.max(dy) + (SQRT_2 - 1.) * dx.min(dy)
+    } else {
+        dx + dy
+    }
+}
+
+/// Find the cheapest path from the start to the goal that stays within the
+/// given bounds with A*, stepping in the given directions. `step_cost` gives
+/// the cost of stepping onto a tile or None if the tile can't be entered.
+/// Costs must be at least 1 so that the distance to the goal never
+/// overestimates the remaining cost. Diagonal steps cost the square root of
+/// two times the cost of the tile they step onto, and can't cut the corners of
+/// tiles that can't be entered. If the goal can't be reached, the path leads to the
+/// reachable tile closest to it. The returned path doesn't include the start
+fn find_path(
+    start: &IPoint,
+    goal: &IPoint,
+    bounds: &IRect,
+    directions: &[Direction],
+    step_cost: impl Fn(&IPoint) -> Option<f64>,
+) -> Vec<IPoint> {
+    let diagonal = directions.iter().any(Direction::is_diagonal);
+    let can_enter = |tile: &IPoint| {
+        bounds.contains_point(tile) && step_cost(tile).is_some()
+    };
+
+    let mut open = BinaryHeap::new();
+    let mut came_from = HashMap::<IPoint, IPoint>::new();
+    let mut best_costs = HashMap::new();
+    let mut closed = HashSet::new();
+
+    // Closest tile to the goal found so far along with its distance and cost
+    let mut closest = (get_distance(start, goal, diagonal), 0., *start);
+
+    best_costs.insert(*start, 0.);
+    open.push(OpenTile {
+        estimate: closest.0,
+        cost: 0.,
+        tile: *start,
+    });
+
+    while let Some(OpenTile { cost, tile, .. }) = open.pop() {
+        if tile == *goal {
+            closest = (0., cost, tile);
+            break;
+        }
+
+        if !closed.insert(tile) {
+            continue;
+        }
+
+        let distance = get_distance(&tile, goal, diagonal);
+
+        if distance < closest.0 || (distance == closest.0 && cost < closest.1) {
+            closest = (distance, cost, tile);
+        }
+
+        for direction in directions {
+            let offset = direction.get_point();
+            let neighbor = &tile + offset;
+
+            if !bounds.contains_point(&neighbor) || closed.contains(&neighbor) {
+                continue;
+            }
+
+            if direction.is_diagonal()
+                && !(can_enter(&IPoint::new(tile.x + offset.x, tile.y))
+                    && can_enter(&IPoint::new(tile.x, tile.y + offset.y)))
+            {
+                continue;
+            }
+
+            let neighbor_cost = match step_cost(&neighbor) {
+                Some(step) => cost + step * direction.get_length(),
+                None => continue,
+            };
+
+            let improved = best_costs
+                .get(&neighbor)
+                .map(|best| neighbor_cost < *best)
+                .unwrap_or(true);
+
+            if improved {
+                best_costs.insert(neighbor, neighbor_cost);
+                came_from.insert(neighbor, tile);
+                open.push(OpenTile {
+                    estimate: neighbor_cost
+                        + get_distance(&neighbor, goal, diagonal),
+                    cost: neighbor_cost,
+                    tile: neighbor,
+                });
+            }
+        }
+    }
+
+    let mut path = vec![];
+    let mut tile = closest.2;
+
+    while tile != *start {
+        path.push(tile);
+        tile = came_from[&tile];
+    }
+
+    path.reverse();
+    path
+}
+
+/// Plans paths for entities over the walkable terrain around the tiles other
+/// entities are standing on
+#[derive(Clone, Debug)]
+pub struct PathfindingService {
+    terrain: Arc<ModifiedTerrain<BiomeTerrain>>,
+    location_service: LocationService,
+    diagonal_movement: bool,
+}
+
+impl PathfindingService {
+    /// Create a pathfinding service that plans paths over the given terrain,
+    /// optionally including diagonal steps
+    pub fn new(
+        terrain: Arc<ModifiedTerrain<BiomeTerrain>>,
+        location_service: LocationService,
+        diagonal_movement: bool,
+    ) -> PathfindingService {
+        PathfindingService {
+            terrain,
+            location_service,
+            diagonal_movement,
+        }
+    }
+
+    /// Find a path for the given entity from the start tile towards the goal.
+    /// The path avoids tiles that can't be walked on or are occupied by other
+    /// entities that block the given one. If the goal can't be reached the
+    /// path leads as close to it as possible. The returned path doesn't
+    /// include the start
+    pub async fn find_path(
+        &self,
+        entity: &Entity,
+        start: &IPoint,
+        goal: &IPoint,
+    ) -> Vec<IPoint> {
+        let bounds = get_search_bounds(start, goal);
+        let entity_type = entity.entity_type();
+
+        let occupied = self
+            .location_service
+            .get_locations_in(&bounds)
+            .await
+            .into_iter()
+            .filter(|(other, _)| {
+                other != entity
+                    && entity_type.is_blocked_by(&other.entity_type())
+            })
+            .map(|(_, location)| location.top_left)
+            .collect::<HashSet<_>>();
+
+        // The terrain for the whole search area is generated at once, which
+        // is much faster than generating it one tile at a time
+        let terrain = self.terrain.get_for_rect(&bounds);
+
+        let directions: &[Direction] = if self.diagonal_movement {
+            &Direction::ALL
+        } else {
+            &Direction::CARDINAL
+        };
+
+        find_path(start, goal, &bounds, directions, |tile| {
+            if occupied.contains(tile) {
+                return None;
+            }
+
+            let offset = tile - &bounds.top_left;
+            let (_, terrain_type) =
+                terrain.get(offset.x as usize, offset.y as usize)?;
+            let properties = terrain_type.properties();
+
+            if properties.walkable {
+                Some(properties.movement_cost)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Get the area to search for a path between the given tiles. Goals too far
+/// from the start are clamped so the search area stays bounded
+fn get_search_bounds(start: &IPoint, goal: &IPoint) -> IRect {
+    let clamped_goal = IPoint::new(
+        goal.x.clamp(
+            start.x - MAX_SEARCH_DISTANCE,
+            start.x + MAX_SEARCH_DISTANCE,
+        ),
+        goal.y.clamp(
+            start.y - MAX_SEARCH_DISTANCE,
+            start.y + MAX_SEARCH_DISTANCE,
+        ),
+    );
+
+    let top_left = start.component_min(&clamped_goal);
+    let bottom_right = start.component_max(&clamped_goal);
+
+    IRect {
+        top_left,
+        size: ISize::new(
+            (bottom_right.x - top_left.x + 1) as usize,
+            (bottom_right.y - top_left.y + 1) as usize,
+        ),
+    }
+    .expanded_by(SEARCH_MARGIN)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Get the step costs from a map of characters where `#` can't be entered
+    /// and digits cost that much to enter
+    fn map_costs<'a>(map: &'a [&str]) -> impl Fn(&IPoint) -> Option<f64> + 'a {
+        move |tile| {
+            let c = map.get(tile.y as usize)?.as_bytes()[tile.x as usize];
+
+            match c {
+                b'#' => None,
+                b'0'..=b'9' => Some((c - b'0') as f64),
+                _ => Some(1.),
+            }
+        }
+    }
+
+    fn bounds_of(map: &[&str]) -> IRect {
+        IRect::new(0, 0, map[0].len(), map.len())
+    }
+
+    fn path_cost(start: &IPoint, path: &[IPoint], map: &[&str]) -> f64 {
+        let cost = map_costs(map);
+        let mut previous = *start;
+
+        path.iter()
+            .map(|tile| {
+                let direction =
+                    Direction::from_point(&(tile - &previous)).unwrap();
+                previous = *tile;
+                cost(tile).unwrap() * direction.get_length()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_path_around_wall() {
+        let map = [".....", ".###.", "...#.", "####."];
+
+        let path = find_path(
+            &IPoint::new(0, 2),
+            &IPoint::new(4, 3),
+            &bounds_of(&map),
+            &Direction::CARDINAL,
+            map_costs(&map),
+        );
+
+        assert_eq!(Some(&IPoint::new(4, 3)), path.last());
+        assert_eq!(9, path.len());
+
+        // Every step moves to a tile sharing an edge
+        let mut previous = IPoint::new(0, 2);
+        for tile in &path {
+            let direction = Direction::from_point(&(tile - &previous));
+            assert_eq!(Some(false), direction.map(|d| d.is_diagonal()));
+            previous = *tile;
+        }
+    }
+
+    #[test]
+    fn test_path_avoids_costly_tiles() {
+        let map = [".....", ".999.", "....."];
+
+        let path = find_path(
+            &IPoint::new(0, 1),
+            &IPoint::new(4, 1),
+            &bounds_of(&map),
+            &Direction::CARDINAL,
+            map_costs(&map),
+        );
+
+        // Going around the costly tiles is cheaper than going through them
+        assert_eq!(6, path.len());
+        assert_eq!(6., path_cost(&IPoint::new(0, 1), &path, &map));
+    }
+
+    #[test]
+    fn test_unreachable_goal_gets_close() {
+        let map = ["..#..", "..#..", "..#.."];
+
+        let path = find_path(
+            &IPoint::new(0, 0),
+            &IPoint::new(4, 1),
+            &bounds_of(&map),
+            &Direction::CARDINAL,
+            map_costs(&map),
+        );
+
+        assert_eq!(Some(&IPoint::new(1, 1)), path.last());
+
+        // Already being as close as possible means there's nowhere to go
+        let path = find_path(
+            &IPoint::new(1, 1),
+            &IPoint::new(4, 1),
+            &bounds_of(&map),
+            &Direction::CARDINAL,
+            map_costs(&map),
+        );
+
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn test_diagonal_path() {
+        let map = [".....", ".#...", ".....", "....."];
+
+        let path = find_path(
+            &IPoint::new(0, 0),
+            &IPoint::new(3, 3),
+            &bounds_of(&map),
+            &Direction::ALL,
+            map_costs(&map),
+        );
+
+        // The rock blocks the straight diagonal, and cutting past its corners
+        // isn't allowed, so the path only steps diagonally once
+        assert_eq!(Some(&IPoint::new(3, 3)), path.last());
+        assert_eq!(5, path.len());
+        assert!(
+            (path_cost(&IPoint::new(0, 0), &path, &map) - (4. + SQRT_2)).abs()
+                < 1e-9
+        );
+
+        let direct = find_path(
+            &IPoint::new(2, 0),
+            &IPoint::new(4, 2),
+            &bounds_of(&map),
+            &Direction::ALL,
+            map_costs(&map),
+        );
+
+        assert_eq!(vec![IPoint::new(3, 1), IPoint::new(4, 2)], direct);
+    }
+
+    #[test]
+    fn test_search_bounds() {
+        assert_eq!(
+            IRect::new(-16, -21, 38, 39),
+            get_search_bounds(&IPoint::new(5, -5), &IPoint::new(0, 1))
+        );
+
+        let far = get_search_bounds(&IPoint::new(0, 0), &IPoint::new(1000, 3));
+
+        assert_eq!(IRect::new(-16, -16, 161, 36), far);
+    }
+}
@@ -3,7 +3,7 @@ use crate::presenter::*;
 use std::collections::HashMap;
 use std::iter::FromIterator;
 use std::ops::{Deref, DerefMut};
-use tokio::sync::RwLock;
+use tokio::sync::{oneshot, Mutex, RwLock};
 
 /// This is how the presenter service shares the state of each presenter with
 /// the actual presenters. The presenters each get a loan of the state that they
@@ -13,6 +13,9 @@ use tokio::sync::RwLock;
 /// while time is paused, but that doesn't seem smart
 pub struct PresenterServiceLease<T> {
     loaned: *const T,
+    /// Dropped along with the lease to tell the presenter service that the
+    /// state is no longer in use
+    _returned: oneshot::Sender<()>,
 }
 
 /// SAFETY: This type is only shared once to the presenter it belongs to.
@@ -22,8 +25,14 @@ unsafe impl<T> Send for PresenterServiceLease<T> where T: Send + Sync {}
 unsafe impl<T> Sync for PresenterServiceLease<T> where T: Send + Sync {}
 
 impl<T> PresenterServiceLease<T> {
-    fn new(original: &T) -> PresenterServiceLease<T> {
-        PresenterServiceLease { loaned: original }
+    fn new(
+        original: &T,
+        returned: oneshot::Sender<()>,
+    ) -> PresenterServiceLease<T> {
+        PresenterServiceLease {
+            loaned: original,
+            _returned: returned,
+        }
     }
 }
 
@@ -53,6 +62,8 @@ pub struct PresenterService {
 #[derive(Debug)]
 struct Inner {
     player_presenter_states: RwLock<HashMap<Entity, Box<PlayerPresenterState>>>,
+    /// Signals for when the leases of each rented state are returned
+    lease_returns: Mutex<HashMap<Entity, oneshot::Receiver<()>>>,
 }
 
 impl PresenterService {
@@ -67,6 +78,7 @@ impl PresenterService {
                     .map(|(k, v)| (k, Box::new(v)))
                     .collect::<HashMap<_, _>>(),
             ),
+            lease_returns: Default::default(),
         });
         let inner = Gor::new(&boxed_inner);
 
@@ -86,16 +98,80 @@ impl PresenterService {
             .copied()
     }
 
+    /// Get a copy of the state of all the player presenters. This should
+    /// only be called while time is paused
+    pub async fn get_player_presenter_states(
+        &self,
+    ) -> Vec<(Entity, PlayerPresenterState)> {
+        self.inner
+            .player_presenter_states
+            .read()
+            .await
+            .iter()
+            .map(|(entity, state)| (*entity, **state))
+            .collect()
+    }
+
     pub async fn rent_player_presenter_state(
         &self,
         player_entity: &Entity,
     ) -> Option<PresenterServiceLease<PlayerPresenterState>> {
+        let states = self.inner.player_presenter_states.read().await;
+        let state = states.get(player_entity)?;
+        let (returned, lease_return) = oneshot::channel();
+
+        self.inner
+            .lease_returns
+            .lock()
+            .await
+            .insert(*player_entity, lease_return);
+
+        Some(PresenterServiceLease::new(state.as_ref(), returned))
+    }
+
+    /// Add the state for the presenter of a new player entity
+    pub async fn insert_player_presenter_state(
+        &self,
+        player_entity: Entity,
+        state: PlayerPresenterState,
+    ) {
         self.inner
             .player_presenter_states
-            .read()
+            .write()
             .await
-            .get(player_entity)
-            .map(Box::as_ref)
-            .map(PresenterServiceLease::new)
+            .insert(player_entity, Box::new(state));
+    }
+
+    /// Wait for the lease on the state of the given player entity's presenter
+    /// to be returned. This finishes right away if the state isn't rented
+    pub async fn wait_for_player_presenter_lease(
+        &self,
+        player_entity: &Entity,
+    ) {
+        let lease_return =
+            self.inner.lease_returns.lock().await.remove(player_entity);
+
+        if let Some(lease_return) = lease_return {
+            // The sender is never used, so this only finishes when it's
+            // dropped along with the lease
+            let _ = lease_return.await;
+        }
+    }
+
+    /// Remove the state for the presenter of the given player entity. If the
+    /// state is rented, this waits for the lease to be returned so the
+    /// presenter never sees the state freed
+    pub async fn remove_player_presenter_state(
+        &self,
+        player_entity: &Entity,
+    ) -> Option<PlayerPresenterState> {
+        self.wait_for_player_presenter_lease(player_entity).await;
+
+        self.inner
+            .player_presenter_states
+            .write()
+            .await
+            .remove(player_entity)
+            .map(|state| *state)
     }
 }
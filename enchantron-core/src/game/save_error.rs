@@ -0,0 +1,59 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io;
+
+/// Errors that can happen while writing or reading a saved game
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u64),
+    MigrationFailed(u64, String),
+    InvalidReference(String),
+    InvalidSlotName(String),
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            SaveError::Io(e) => write!(f, "Failed to access saved game, {}", e),
+            SaveError::Format(e) => write!(f, "Malformed saved game, {}", e),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported saved game version {}", version)
+            }
+            SaveError::MigrationFailed(from_version, reason) => write!(
+                f,
+                "Failed to migrate saved game from version {}, {}",
+                from_version, reason
+            ),
+            SaveError::InvalidReference(description) => {
+                write!(f, "Invalid reference in saved game, {}", description)
+            }
+            SaveError::InvalidSlotName(slot_name) => {
+                write!(f, "Invalid save slot name {:?}", slot_name)
+            }
+        }
+    }
+}
+
+impl Error for SaveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SaveError::Io(e) => Some(e),
+            SaveError::Format(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Format(e)
+    }
+}
@@ -0,0 +1,209 @@
+use super::{SaveError, CURRENT_SAVE_VERSION};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Upgrade the raw encoding of a saved game by one version. Migrations only
+/// need to change the layout, the version field is updated by the registry
+pub type SaveMigration = fn(Value) -> Result<Value, SaveError>;
+
+lazy_static! {
+    pub static ref SAVE_MIGRATIONS: SaveMigrations = SaveMigrations::new();
+}
+
+/// Registry of the migrations that upgrade each historical save version to the
+/// next one. When the save format changes, bump `CURRENT_SAVE_VERSION`,
+/// register a migration from the previous version here and check in a fixture
+/// of the previous version under `test_data/saves`
+pub struct SaveMigrations {
+    migrations: BTreeMap<u64, SaveMigration>,
+    current_version: u64,
+}
+
+impl SaveMigrations {
+    fn new() -> SaveMigrations {
+        let mut result = SaveMigrations::empty(CURRENT_SAVE_VERSION);
+
+        result.register(1, add_terrain_modifications);
+
+        result
+    }
+
+    fn empty(current_version: u64) -> SaveMigrations {
+        SaveMigrations {
+            migrations: BTreeMap::new(),
+            current_version,
+        }
+    }
+
+    /// Register the migration that upgrades saves of the given version to the
+    /// next version
+    fn register(&mut self, from_version: u64, migration: SaveMigration) {
+        debug_assert!(from_version < self.current_version);
+
+        if self.migrations.insert(from_version, migration).is_some() {
+            panic!("Multiple migrations registered from {}", from_version);
+        }
+    }
+
+    /// Upgrade the given raw save of the given version to the current version
+    /// one version at a time
+    pub fn migrate(
+        &self,
+        mut raw: Value,
+        version: u64,
+    ) -> Result<Value, SaveError> {
+        if version > self.current_version {
+            return Err(SaveError::UnsupportedVersion(version));
+        }
+
+        for from_version in version..self.current_version {
+            let migration = self
+                .migrations
+                .get(&from_version)
+                .ok_or(SaveError::UnsupportedVersion(version))?;
+
+            raw = migration(raw)?;
+
+            match raw.as_object_mut() {
+                Some(object) => {
+                    object.insert("version".into(), (from_version + 1).into());
+                }
+                None => {
+                    return Err(SaveError::MigrationFailed(
+                        from_version,
+                        "Migration didn't produce an object".into(),
+                    ))
+                }
+            }
+        }
+
+        Ok(raw)
+    }
+}
+
+/// Version 2 added the player's modifications to the terrain. Older games
+/// have none
+fn add_terrain_modifications(mut raw: Value) -> Result<Value, SaveError> {
+    match raw.as_object_mut() {
+        Some(object) => {
+            object.insert("terrain_modifications".into(), Value::Array(vec![]));
+            Ok(raw)
+        }
+        None => Err(SaveError::MigrationFailed(
+            1,
+            "Saved game isn't an object".into(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::SavedGame;
+    use serde_json::json;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    const FIXTURE_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/saves");
+
+    fn fixture_path(version: u64) -> PathBuf {
+        PathBuf::from(FIXTURE_DIR).join(format!("v{}.json", version))
+    }
+
+    fn rename_a_to_b(mut raw: Value) -> Result<Value, SaveError> {
+        let value = raw["a"].take();
+        raw["b"] = value;
+        Ok(raw)
+    }
+
+    fn rename_b_to_c(mut raw: Value) -> Result<Value, SaveError> {
+        let value = raw["b"].take();
+        raw["c"] = value;
+        Ok(raw)
+    }
+
+    #[test]
+    fn test_migrations_chain() {
+        let mut migrations = SaveMigrations::empty(3);
+        migrations.register(2, rename_b_to_c);
+        migrations.register(1, rename_a_to_b);
+
+        let migrated = migrations
+            .migrate(json!({ "version": 1, "a": 5 }), 1)
+            .unwrap();
+
+        assert_eq!(
+            json!({ "version": 3, "a": null, "b": null, "c": 5 }),
+            migrated
+        );
+
+        let migrated = migrations
+            .migrate(json!({ "version": 2, "b": 7 }), 2)
+            .unwrap();
+
+        assert_eq!(json!({ "version": 3, "b": null, "c": 7 }), migrated);
+
+        let current = json!({ "version": 3, "c": 1 });
+        assert_eq!(current, migrations.migrate(current.clone(), 3).unwrap());
+    }
+
+    #[test]
+    fn test_missing_and_future_versions() {
+        let mut migrations = SaveMigrations::empty(3);
+        migrations.register(2, rename_b_to_c);
+
+        assert!(matches!(
+            migrations.migrate(json!({ "version": 1 }), 1),
+            Err(SaveError::UnsupportedVersion(1))
+        ));
+        assert!(matches!(
+            migrations.migrate(json!({ "version": 4 }), 4),
+            Err(SaveError::UnsupportedVersion(4))
+        ));
+    }
+
+    #[test]
+    fn test_historical_fixtures_load() {
+        for version in 1..=CURRENT_SAVE_VERSION {
+            let path = fixture_path(version);
+            let file = File::open(&path).unwrap_or_else(|e| {
+                panic!("Missing save fixture {:?}, {}", path, e)
+            });
+
+            let saved_game = SavedGame::read_from(file).unwrap_or_else(|e| {
+                panic!("Failed to load save fixture {:?}, {}", path, e)
+            });
+
+            // Every fixture is the same game written in its version's format,
+            // with whatever that version was able to record
+            assert_eq!(7, saved_game.seed);
+            assert_eq!(2500, saved_game.elapsed_millis);
+            assert_eq!(2, saved_game.entities.len());
+            assert_eq!(
+                Some(crate::model::IRect::new(3, -2, 1, 1)),
+                saved_game
+                    .locations
+                    .get(&saved_game.player.location_key)
+                    .map(|l| l.location)
+            );
+
+            let player_state = saved_game
+                .player_presenter_states
+                .iter()
+                .find(|(entity, _)| *entity == saved_game.player.entity)
+                .map(|(_, state)| format!("{:?}", state))
+                .expect("Missing player presenter state");
+
+            assert!(player_state.contains("WalkingIn(2.25)"));
+
+            // Terrain modifications were only saved from version 2
+            let expected_modifications = if version >= 2 { 2 } else { 0 };
+
+            assert_eq!(
+                expected_modifications,
+                saved_game.terrain_modifications.len()
+            );
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use super::{
+    BiomeTerrain, BiomeTerrainConfig, SaveError, SaveSlotMetadata, SavedGame,
+    TerrainModifications, TerrainProvider,
+};
+use crate::img::PngGenerator;
+use crate::model::{IPoint, IRect, ISize};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+const GAME_FILE_NAME: &str = "game.json";
+const METADATA_FILE_NAME: &str = "metadata.json";
+const THUMBNAIL_FILE_NAME: &str = "thumbnail.png";
+const NEW_SLOT_PREFIX: &str = "slot-";
+
+/// Width and height in tiles (and pixels) of the slot thumbnails
+const THUMBNAIL_TILES: usize = 64;
+const PLAYER_MARKER_RGB: [u8; 3] = [0xE0, 0x20, 0x20];
+
+/// Manages the saved games in a directory. Each slot is a sub-directory
+/// holding the game, its metadata and a thumbnail of the terrain around the
+/// player
+#[derive(Debug, Clone)]
+pub struct SaveSlotManager {
+    directory: PathBuf,
+    terrain_config: BiomeTerrainConfig,
+}
+
+/// Slot names become directory names, so they are kept simple
+fn check_slot_name(slot_name: &str) -> Result<(), SaveError> {
+    let valid = !slot_name.is_empty()
+        && slot_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(SaveError::InvalidSlotName(slot_name.to_owned()))
+    }
+}
+
+/// Create an overview image of the terrain around the player with the player
+/// marked in the middle
+fn create_thumbnail(
+    terrain_config: &BiomeTerrainConfig,
+    seed: u64,
+    terrain_modifications: &TerrainModifications,
+    player_tile: IPoint,
+    target: &mut Vec<u8>,
+) {
+    let half = (THUMBNAIL_TILES / 2) as i64;
+    let rect = IRect::new(
+        player_tile.x - half,
+        player_tile.y - half,
+        THUMBNAIL_TILES,
+        THUMBNAIL_TILES,
+    );
+
+    let mut terrain =
+        BiomeTerrain::from_config(terrain_config, seed).get_for_rect(&rect);
+
+    terrain_modifications.apply_to(&mut terrain);
+    let mut data = vec![0u8; THUMBNAIL_TILES * THUMBNAIL_TILES * 3];
+
+    terrain.for_each_value_coord(|coord, (_, terrain_type)| {
+        let offset = 3 * (coord.x + coord.y * THUMBNAIL_TILES);
+        data[offset..offset + 3].copy_from_slice(terrain_type.get_rgb());
+    });
+
+    for y in (half - 1)..=half {
+        for x in (half - 1)..=half {
+            let offset = 3 * (x as usize + y as usize * THUMBNAIL_TILES);
+            data[offset..offset + 3].copy_from_slice(&PLAYER_MARKER_RGB);
+        }
+    }
+
+    PngGenerator::get_png(
+        &data,
+        &ISize::new(THUMBNAIL_TILES, THUMBNAIL_TILES),
+        target,
+    );
+}
+
+/// Write the given data to the target path through a temporary file so a
+/// failed write can't clobber the existing file
+fn write_file_atomic(path: &Path, data: &[u8]) -> Result<(), SaveError> {
+    let temp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+
+    writer.write_all(data)?;
+    writer.flush()?;
+    drop(writer);
+
+    fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+impl SaveSlotManager {
+    pub fn new(directory: impl Into<PathBuf>) -> SaveSlotManager {
+        SaveSlotManager {
+            directory: directory.into(),
+            terrain_config: Default::default(),
+        }
+    }
+
+    /// Draw thumbnails with the given terrain configuration instead of the
+    /// default one
+    pub fn with_terrain_config(
+        self,
+        terrain_config: BiomeTerrainConfig,
+    ) -> SaveSlotManager {
+        SaveSlotManager {
+            terrain_config,
+            ..self
+        }
+    }
+
+    fn slot_directory(&self, slot_name: &str) -> Result<PathBuf, SaveError> {
+        check_slot_name(slot_name)?;
+        Ok(self.directory.join(slot_name))
+    }
+
+    /// Get the metadata for all the slots in the directory with the most
+    /// recently saved first. Slots that can't be read are skipped
+    pub fn list(&self) -> Result<Vec<SaveSlotMetadata>, SaveError> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut result = Vec::new();
+
+        for entry in entries {
+            let entry = entry?;
+
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let slot_name = entry.file_name().to_string_lossy().into_owned();
+
+            match self.get_metadata(&slot_name) {
+                Ok(metadata) => result.push(metadata),
+                Err(e) => warn!("Skipping save slot {}, {}", slot_name, e),
+            }
+        }
+
+        result.sort_by(|a, b| {
+            b.saved_at
+                .cmp(&a.saved_at)
+                .then_with(|| a.slot_name.cmp(&b.slot_name))
+        });
+
+        Ok(result)
+    }
+
+    /// Get the most recently saved slot if there are any
+    pub fn most_recent(&self) -> Result<Option<SaveSlotMetadata>, SaveError> {
+        Ok(self.list()?.into_iter().next())
+    }
+
+    /// Load the most recently saved game, or start a new game if there isn't
+    /// one that can be loaded. Failures to load are logged rather than
+    /// returned so there's always a game to play
+    pub fn load_most_recent_or_new(&self) -> SavedGame {
+        let loaded = self.most_recent().and_then(|metadata| {
+            metadata.map(|m| self.load(&m.slot_name)).transpose()
+        });
+
+        match loaded {
+            Ok(Some(saved_game)) => return saved_game,
+            Ok(None) => info!("No saved game to continue"),
+            Err(e) => error!("Failed to load saved game, {}", e),
+        }
+
+        SavedGame::new(Default::default())
+    }
+
+    pub fn get_metadata(
+        &self,
+        slot_name: &str,
+    ) -> Result<SaveSlotMetadata, SaveError> {
+        let path = self.slot_directory(slot_name)?.join(METADATA_FILE_NAME);
+        let metadata: SaveSlotMetadata =
+            serde_json::from_reader(BufReader::new(File::open(path)?))?;
+
+        Ok(metadata)
+    }
+
+    /// Save the given game into a new slot
+    pub fn create(
+        &self,
+        saved_game: &SavedGame,
+    ) -> Result<SaveSlotMetadata, SaveError> {
+        let next_index = self
+            .list()?
+            .iter()
+            .filter_map(|m| m.slot_name.strip_prefix(NEW_SLOT_PREFIX))
+            .filter_map(|index| index.parse::<u64>().ok())
+            .max()
+            .map(|max| max + 1)
+            .unwrap_or(1);
+
+        self.save(&format!("{}{}", NEW_SLOT_PREFIX, next_index), saved_game)
+    }
+
+    /// Save the given game into the given slot, overwriting the slot if it
+    /// already exists
+    pub fn save(
+        &self,
+        slot_name: &str,
+        saved_game: &SavedGame,
+    ) -> Result<SaveSlotMetadata, SaveError> {
+        let slot_directory = self.slot_directory(slot_name)?;
+        fs::create_dir_all(&slot_directory)?;
+
+        let metadata = SaveSlotMetadata::new(slot_name, saved_game);
+
+        let mut thumbnail = Vec::new();
+        create_thumbnail(
+            &self.terrain_config,
+            metadata.seed,
+            &saved_game.terrain_modifications,
+            metadata.player_tile,
+            &mut thumbnail,
+        );
+
+        saved_game.write_to_file(&slot_directory.join(GAME_FILE_NAME))?;
+        write_file_atomic(
+            &slot_directory.join(THUMBNAIL_FILE_NAME),
+            &thumbnail,
+        )?;
+
+        // The metadata is written last so that a slot only shows up in the
+        // listing once it's complete
+        write_file_atomic(
+            &slot_directory.join(METADATA_FILE_NAME),
+            &serde_json::to_vec(&metadata)?,
+        )?;
+
+        Ok(metadata)
+    }
+
+    pub fn load(&self, slot_name: &str) -> Result<SavedGame, SaveError> {
+        SavedGame::read_from_file(
+            &self.slot_directory(slot_name)?.join(GAME_FILE_NAME),
+        )
+    }
+
+    /// Get the png data of the thumbnail for the given slot
+    pub fn get_thumbnail(&self, slot_name: &str) -> Result<Vec<u8>, SaveError> {
+        Ok(fs::read(
+            self.slot_directory(slot_name)?.join(THUMBNAIL_FILE_NAME),
+        )?)
+    }
+
+    pub fn delete(&self, slot_name: &str) -> Result<(), SaveError> {
+        fs::remove_dir_all(self.slot_directory(slot_name)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::img::PngDecoder;
+    use std::process;
+
+    fn temp_manager(name: &str) -> SaveSlotManager {
+        let directory = std::env::temp_dir().join(format!(
+            "enchantron-slots-{}-{}",
+            name,
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&directory);
+
+        SaveSlotManager::new(directory)
+    }
+
+    #[test]
+    fn test_create_overwrite_delete() {
+        let manager = temp_manager("crud");
+
+        assert!(manager.list().unwrap().is_empty());
+        assert!(manager.most_recent().unwrap().is_none());
+
+        let mut saved_game = SavedGame::new(3);
+        saved_game.elapsed_millis = 1234;
+
+        let first = manager.create(&saved_game).unwrap();
+        let second = manager.create(&SavedGame::new(4)).unwrap();
+
+        assert_eq!("slot-1", first.slot_name);
+        assert_eq!("slot-2", second.slot_name);
+        assert_eq!(3, first.seed);
+        assert_eq!(1234, first.play_time_millis);
+        assert_eq!(IPoint::new(0, 0), first.player_tile);
+
+        let mut slot_names = manager
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|m| m.slot_name)
+            .collect::<Vec<_>>();
+        slot_names.sort();
+        assert_eq!(vec!["slot-1", "slot-2"], slot_names);
+
+        saved_game.elapsed_millis = 5678;
+        let overwritten = manager.save("slot-1", &saved_game).unwrap();
+        assert_eq!(5678, overwritten.play_time_millis);
+        assert_eq!(overwritten, manager.get_metadata("slot-1").unwrap());
+        assert_eq!(5678, manager.load("slot-1").unwrap().elapsed_millis);
+        assert_eq!(2, manager.list().unwrap().len());
+
+        manager.delete("slot-2").unwrap();
+        assert_eq!(1, manager.list().unwrap().len());
+        assert!(manager.load("slot-2").is_err());
+
+        let _ = fs::remove_dir_all(&manager.directory);
+    }
+
+    #[test]
+    fn test_load_most_recent_or_new() {
+        let manager = temp_manager("most-recent");
+
+        // Nothing has been saved yet, so a new game is started
+        assert_eq!(0, manager.load_most_recent_or_new().seed);
+
+        manager.create(&SavedGame::new(7)).unwrap();
+
+        assert_eq!(7, manager.load_most_recent_or_new().seed);
+
+        let _ = fs::remove_dir_all(&manager.directory);
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let manager = temp_manager("thumbnail");
+
+        manager.save("autosave", &SavedGame::new(1)).unwrap();
+
+        let (size, rgba) =
+            PngDecoder::get_rgba(&manager.get_thumbnail("autosave").unwrap());
+
+        assert_eq!(ISize::new(THUMBNAIL_TILES, THUMBNAIL_TILES), size);
+
+        let center = 4 * (THUMBNAIL_TILES / 2 * (THUMBNAIL_TILES + 1));
+        assert_eq!(PLAYER_MARKER_RGB, rgba[center..center + 3]);
+
+        let _ = fs::remove_dir_all(&manager.directory);
+    }
+
+    #[test]
+    fn test_invalid_slot_names() {
+        let manager = temp_manager("names");
+
+        for name in &["", "../escape", "a/b", "with space"] {
+            assert!(matches!(
+                manager.save(name, &SavedGame::new(1)),
+                Err(SaveError::InvalidSlotName(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use super::SavedGame;
+use crate::model::IPoint;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Summary of a saved game that can be shown without loading the whole game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveSlotMetadata {
+    pub slot_name: String,
+    pub seed: u64,
+    pub play_time_millis: u64,
+    pub player_tile: IPoint,
+    /// Seconds since the unix epoch when the slot was written
+    pub saved_at: u64,
+}
+
+impl SaveSlotMetadata {
+    pub fn new(slot_name: &str, saved_game: &SavedGame) -> SaveSlotMetadata {
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        SaveSlotMetadata {
+            slot_name: slot_name.to_owned(),
+            seed: saved_game.seed,
+            play_time_millis: saved_game.elapsed_millis,
+            player_tile: saved_game.player_tile(),
+            saved_at,
+        }
+    }
+}
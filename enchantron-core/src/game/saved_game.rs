@@ -1,9 +1,15 @@
+use super::save_migrations::SAVE_MIGRATIONS;
+use super::saved_game_record::SavedGameRecord;
 use super::{
-    Entity, EntityData, EntityType, LocationKey, Player, SaveableLocation,
+    Entity, EntityData, EntityType, LocationKey, Player, SaveError,
+    SaveableLocation, TerrainModifications,
 };
-use crate::model::IRect;
+use crate::model::{IPoint, IRect};
 use crate::presenter::PlayerPresenterState;
 use one_way_slot_map::SlotMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
 
 pub struct SavedGame {
     pub(crate) seed: u64,
@@ -12,6 +18,7 @@ pub struct SavedGame {
     pub(crate) entities: SlotMap<Entity, EntityType, EntityData>,
     pub(crate) locations: SlotMap<LocationKey, Entity, SaveableLocation>,
     pub(crate) player_presenter_states: Vec<(Entity, PlayerPresenterState)>,
+    pub(crate) terrain_modifications: TerrainModifications,
 }
 
 impl SavedGame {
@@ -49,6 +56,248 @@ impl SavedGame {
             entities,
             locations,
             player_presenter_states,
+            terrain_modifications: Default::default(),
         }
     }
+
+    /// Get the tile the player is standing on
+    pub fn player_tile(&self) -> IPoint {
+        self.locations
+            .get(&self.player.location_key)
+            .map(|saveable_location| saveable_location.location.top_left)
+            .unwrap_or_default()
+    }
+
+    /// Write this saved game in the current on-disk format
+    pub fn write_to(&self, writer: impl Write) -> Result<(), SaveError> {
+        serde_json::to_writer(writer, &SavedGameRecord::from(self))?;
+        Ok(())
+    }
+
+    /// Read a saved game that was written with `write_to` by this or any
+    /// earlier version. The entity and location keys of the loaded game will
+    /// not match the keys of the game that was written, but all the references
+    /// between them are preserved
+    pub fn read_from(reader: impl Read) -> Result<SavedGame, SaveError> {
+        let raw: serde_json::Value = serde_json::from_reader(reader)?;
+
+        // The version is checked before decoding because the rest of the
+        // record may not match the current layout
+        let version = SavedGameRecord::version_of(&raw)?;
+        let raw = SAVE_MIGRATIONS.migrate(raw, version)?;
+
+        serde_json::from_value::<SavedGameRecord>(raw)?.into_saved_game()
+    }
+
+    /// Write this saved game to the given file. The game is written to a
+    /// temporary file first so an interrupted save can't clobber the previous
+    /// save
+    pub fn write_to_file(&self, path: &Path) -> Result<(), SaveError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(&temp_path, path)?;
+
+        Ok(())
+    }
+
+    pub fn read_from_file(path: &Path) -> Result<SavedGame, SaveError> {
+        SavedGame::read_from(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::{TerrainType, CURRENT_SAVE_VERSION};
+
+    /// Flatten a saved game into a form that doesn't depend on the slot map
+    /// keys, following every reference so broken references fail the test
+    fn describe(saved_game: &SavedGame) -> Vec<String> {
+        let mut result = vec![
+            format!("seed {}", saved_game.seed),
+            format!("elapsed {}", saved_game.elapsed_millis),
+        ];
+
+        let player_location = saved_game
+            .locations
+            .get(&saved_game.player.location_key)
+            .expect("Player location missing");
+
+        assert_eq!(saved_game.player.entity, player_location.entity);
+        result.push(format!("player at {:?}", player_location.location));
+
+        for entity_data in saved_game.entities.values() {
+            let entity = entity_data.entity.expect("Entity missing self key");
+            let location = entity_data.location_key.map(|key| {
+                let saveable_location = saved_game
+                    .locations
+                    .get(&key)
+                    .expect("Entity location missing");
+                assert_eq!(entity, saveable_location.entity);
+                saveable_location.location
+            });
+
+            assert_eq!(
+                Some(entity_data),
+                saved_game.entities.get(&entity),
+                "Entity key doesn't point to its own data"
+            );
+
+            result.push(format!(
+                "{:?} at {:?}",
+                entity.entity_type(),
+                location
+            ));
+        }
+
+        for (entity, state) in &saved_game.player_presenter_states {
+            assert!(saved_game.entities.get(entity).is_some());
+            result.push(format!("{:?} has {:?}", entity.entity_type(), state));
+        }
+
+        let mut terrain_modifications =
+            saved_game.terrain_modifications.iter().collect::<Vec<_>>();
+
+        terrain_modifications
+            .sort_by_key(|(position, _)| (position.y, position.x));
+
+        for (position, terrain_type) in terrain_modifications {
+            result.push(format!("{:?} at {:?}", terrain_type, position));
+        }
+
+        result
+    }
+
+    fn round_trip(saved_game: &SavedGame) -> SavedGame {
+        let mut buffer = Vec::new();
+        saved_game.write_to(&mut buffer).unwrap();
+        SavedGame::read_from(buffer.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_new_game() {
+        let mut saved_game = SavedGame::new(12345);
+        saved_game.elapsed_millis = 67890;
+
+        let loaded = round_trip(&saved_game);
+
+        assert_eq!(describe(&saved_game), describe(&loaded));
+        assert_eq!(12345, loaded.seed);
+        assert_eq!(67890, loaded.elapsed_millis);
+    }
+
+    #[test]
+    fn test_round_trip_remaps_keys() {
+        let mut saved_game = SavedGame::new(1);
+
+        // Leave holes and reused slots in both maps so the keys written don't
+        // line up with the keys a fresh slot map would hand out
+        for i in 0..5 {
+            let entity: Entity = saved_game.entities.insert(
+                EntityType::Player,
+                EntityData::default_for_type(EntityType::Player),
+            );
+            let location_key: LocationKey = saved_game.locations.insert(
+                entity,
+                SaveableLocation::new(IRect::new(i, -i, 1, 1), entity),
+            );
+
+            if i % 2 == 0 {
+                saved_game.entities.remove(&entity);
+                saved_game.locations.remove(&location_key);
+            } else {
+                let entity_data = saved_game.entities.get_mut(&entity).unwrap();
+                entity_data.entity = Some(entity);
+                entity_data.location_key = Some(location_key);
+                saved_game
+                    .player_presenter_states
+                    .push((entity, Default::default()));
+            }
+        }
+
+        let player_location = saved_game
+            .locations
+            .get_mut(&saved_game.player.location_key)
+            .unwrap();
+        player_location.location = IRect::new(-7, 3, 1, 1);
+
+        let loaded = round_trip(&saved_game);
+
+        assert_eq!(3, loaded.entities.len());
+        assert_eq!(3, loaded.locations.len());
+        assert_eq!(describe(&saved_game), describe(&loaded));
+
+        // Saving the loaded game again must not change anything
+        assert_eq!(describe(&loaded), describe(&round_trip(&loaded)));
+    }
+
+    #[test]
+    fn test_round_trip_terrain_modifications() {
+        let mut saved_game = SavedGame::new(5);
+        let modifications = &mut saved_game.terrain_modifications;
+
+        modifications.set(IPoint::new(0, 0), Some(TerrainType::Dirt));
+        modifications.set(IPoint::new(-40, 17), Some(TerrainType::Water));
+        modifications.set(IPoint::new(9, -300), Some(TerrainType::Sand));
+        modifications.set(IPoint::new(9, -300), None);
+
+        let loaded = round_trip(&saved_game);
+
+        assert_eq!(2, loaded.terrain_modifications.len());
+        assert_eq!(
+            Some(TerrainType::Water),
+            loaded.terrain_modifications.get(&IPoint::new(-40, 17))
+        );
+        assert_eq!(describe(&saved_game), describe(&loaded));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut buffer = Vec::new();
+        SavedGame::new(1).write_to(&mut buffer).unwrap();
+
+        let mut raw: serde_json::Value =
+            serde_json::from_slice(&buffer).unwrap();
+        raw["version"] = (CURRENT_SAVE_VERSION + 1).into();
+
+        match SavedGame::read_from(raw.to_string().as_bytes()) {
+            Err(SaveError::UnsupportedVersion(v)) => {
+                assert_eq!(CURRENT_SAVE_VERSION + 1, v)
+            }
+            other => {
+                panic!("Expected unsupported version, got {:?}", other.err())
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_reference() {
+        let mut buffer = Vec::new();
+        SavedGame::new(1).write_to(&mut buffer).unwrap();
+
+        let mut raw: serde_json::Value =
+            serde_json::from_slice(&buffer).unwrap();
+        raw["player"]["location_key"] = 999_999u64.into();
+
+        match SavedGame::read_from(raw.to_string().as_bytes()) {
+            Err(SaveError::InvalidReference(_)) => {}
+            other => {
+                panic!("Expected invalid reference, got {:?}", other.err())
+            }
+        }
+
+        assert!(matches!(
+            SavedGame::read_from(&b"{\"version\": 1}"[..]),
+            Err(SaveError::Format(_))
+        ));
+    }
 }
@@ -0,0 +1,297 @@
+use super::{
+    Entity, EntityData, EntityType, LocationKey, Player, SaveError,
+    SaveableLocation, SavedGame, TerrainModifications, TerrainType,
+};
+use crate::model::{IPoint, IRect};
+use crate::presenter::PlayerPresenterState;
+use one_way_slot_map::{SlotMap, SlotMapKeyData};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+
+/// Version of the on-disk format written by this build
+pub const CURRENT_SAVE_VERSION: u64 = 2;
+
+/// On-disk representation of a saved game. Slot map keys can't be restored
+/// as-is, so they are written as raw key data and remapped to new keys when
+/// the slot maps are rebuilt on load
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct SavedGameRecord {
+    version: u64,
+    seed: u64,
+    elapsed_millis: u64,
+    player: PlayerRecord,
+    entities: Vec<EntityDataRecord>,
+    locations: Vec<LocationRecord>,
+    player_presenter_states: Vec<PresenterStateRecord<PlayerPresenterState>>,
+    terrain_modifications: Vec<TerrainModificationRecord>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct EntityRecord {
+    entity_type: EntityType,
+    key: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlayerRecord {
+    entity: EntityRecord,
+    location_key: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EntityDataRecord {
+    key: EntityRecord,
+    entity: Option<EntityRecord>,
+    location_key: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LocationRecord {
+    key: u64,
+    location: IRect,
+    entity: EntityRecord,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PresenterStateRecord<S> {
+    entity: EntityRecord,
+    state: S,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TerrainModificationRecord {
+    position: IPoint,
+    terrain_type: TerrainType,
+}
+
+fn raw_key(key: &impl Borrow<SlotMapKeyData>) -> u64 {
+    (*key.borrow()).into()
+}
+
+impl From<&Entity> for EntityRecord {
+    fn from(entity: &Entity) -> Self {
+        EntityRecord {
+            entity_type: entity.entity_type(),
+            key: raw_key(entity),
+        }
+    }
+}
+
+/// Lookup from the keys stored in a record to the keys of the rebuilt slot
+/// maps
+struct KeyRemapping {
+    entities: HashMap<u64, Entity>,
+    locations: HashMap<u64, LocationKey>,
+}
+
+impl KeyRemapping {
+    fn entity(&self, record: &EntityRecord) -> Result<Entity, SaveError> {
+        self.entities
+            .get(&record.key)
+            .filter(|entity| entity.entity_type() == record.entity_type)
+            .copied()
+            .ok_or_else(|| {
+                SaveError::InvalidReference(format!(
+                    "No {:?} entity with key {}",
+                    record.entity_type, record.key
+                ))
+            })
+    }
+
+    fn location_key(&self, key: u64) -> Result<LocationKey, SaveError> {
+        self.locations.get(&key).copied().ok_or_else(|| {
+            SaveError::InvalidReference(format!("No location with key {}", key))
+        })
+    }
+}
+
+impl SavedGameRecord {
+    /// Create a record from the raw contents of the entity and location slot
+    /// maps. The key data given must be the key data the slot maps use for
+    /// the entities and location keys referenced by the values
+    pub fn new<'a>(
+        seed: u64,
+        elapsed_millis: u64,
+        player: &Player,
+        entities: impl Iterator<Item = (SlotMapKeyData, &'a EntityData)>,
+        locations: impl Iterator<Item = (SlotMapKeyData, &'a SaveableLocation)>,
+        player_presenter_states: impl Iterator<
+            Item = &'a (Entity, PlayerPresenterState),
+        >,
+        terrain_modifications: &TerrainModifications,
+    ) -> SavedGameRecord {
+        let entities = entities
+            .map(|(key_data, entity_data)| EntityDataRecord {
+                key: EntityRecord {
+                    entity_type: entity_data.entity_type,
+                    key: key_data.into(),
+                },
+                entity: entity_data.entity.as_ref().map(EntityRecord::from),
+                location_key: entity_data.location_key.as_ref().map(raw_key),
+            })
+            .collect();
+
+        let locations = locations
+            .map(|(key_data, saveable_location)| LocationRecord {
+                key: key_data.into(),
+                location: saveable_location.location,
+                entity: EntityRecord::from(&saveable_location.entity),
+            })
+            .collect();
+
+        let player_presenter_states = player_presenter_states
+            .map(|(entity, state)| PresenterStateRecord {
+                entity: entity.into(),
+                state: *state,
+            })
+            .collect();
+
+        // Modifications are sorted so saving the same game twice writes the
+        // same file
+        let mut terrain_modifications = terrain_modifications
+            .iter()
+            .map(|(position, terrain_type)| TerrainModificationRecord {
+                position,
+                terrain_type,
+            })
+            .collect::<Vec<_>>();
+
+        terrain_modifications
+            .sort_by_key(|record| (record.position.y, record.position.x));
+
+        SavedGameRecord {
+            version: CURRENT_SAVE_VERSION,
+            seed,
+            elapsed_millis,
+            player: PlayerRecord {
+                entity: EntityRecord::from(&player.entity),
+                location_key: raw_key(&player.location_key),
+            },
+            entities,
+            locations,
+            player_presenter_states,
+            terrain_modifications,
+        }
+    }
+
+    /// Get the format version of a raw saved game without decoding the rest
+    pub fn version_of(raw: &serde_json::Value) -> Result<u64, SaveError> {
+        #[derive(Deserialize)]
+        struct VersionOnly {
+            version: u64,
+        }
+
+        Ok(<VersionOnly as serde::Deserialize>::deserialize(raw)?.version)
+    }
+
+    pub fn into_saved_game(self) -> Result<SavedGame, SaveError> {
+        let mut remapping = KeyRemapping {
+            entities: HashMap::new(),
+            locations: HashMap::new(),
+        };
+
+        // Entities are inserted first without their references so that the
+        // locations can be inserted with the new entity keys
+        let mut entities = SlotMap::new();
+
+        for entity_record in &self.entities {
+            let new_entity: Entity = entities.insert(
+                entity_record.key.entity_type,
+                EntityData::default_for_type(entity_record.key.entity_type),
+            );
+
+            if remapping
+                .entities
+                .insert(entity_record.key.key, new_entity)
+                .is_some()
+            {
+                return Err(SaveError::InvalidReference(format!(
+                    "Duplicate entity key {}",
+                    entity_record.key.key
+                )));
+            }
+        }
+
+        let mut locations = SlotMap::new();
+
+        for location_record in &self.locations {
+            let entity = remapping.entity(&location_record.entity)?;
+            let location_key: LocationKey = locations.insert(
+                entity,
+                SaveableLocation::new(location_record.location, entity),
+            );
+
+            if remapping
+                .locations
+                .insert(location_record.key, location_key)
+                .is_some()
+            {
+                return Err(SaveError::InvalidReference(format!(
+                    "Duplicate location key {}",
+                    location_record.key
+                )));
+            }
+        }
+
+        for entity_record in &self.entities {
+            let entity = remapping.entity(&entity_record.key)?;
+            let referenced_entity = entity_record
+                .entity
+                .as_ref()
+                .map(|e| remapping.entity(e))
+                .transpose()?;
+            let location_key = entity_record
+                .location_key
+                .map(|key| remapping.location_key(key))
+                .transpose()?;
+
+            let entity_data = entities
+                .get_mut(&entity)
+                .expect("Remapped entity missing from slot map");
+
+            entity_data.entity = referenced_entity;
+            entity_data.location_key = location_key;
+        }
+
+        let player = Player {
+            entity: remapping.entity(&self.player.entity)?,
+            location_key: remapping.location_key(self.player.location_key)?,
+        };
+
+        let player_presenter_states = self
+            .player_presenter_states
+            .iter()
+            .map(|record| Ok((remapping.entity(&record.entity)?, record.state)))
+            .collect::<Result<Vec<_>, SaveError>>()?;
+
+        let terrain_modifications = self
+            .terrain_modifications
+            .iter()
+            .map(|record| (record.position, record.terrain_type))
+            .collect();
+
+        Ok(SavedGame {
+            seed: self.seed,
+            elapsed_millis: self.elapsed_millis,
+            player,
+            entities,
+            locations,
+            player_presenter_states,
+            terrain_modifications,
+        })
+    }
+}
+
+impl From<&SavedGame> for SavedGameRecord {
+    fn from(saved_game: &SavedGame) -> Self {
+        SavedGameRecord::new(
+            saved_game.seed,
+            saved_game.elapsed_millis,
+            &saved_game.player,
+            saved_game.entities.iter_raw(),
+            saved_game.locations.iter_raw(),
+            saved_game.player_presenter_states.iter(),
+            &saved_game.terrain_modifications,
+        )
+    }
+}
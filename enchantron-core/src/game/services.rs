@@ -1,15 +1,22 @@
+use super::saved_game_record::SavedGameRecord;
 use super::{
-    EntityData, EntityMessage, EntityRunBundle, EntityService, EntityType, Gor,
-    LocationService, MessageService, PresenterService, SavedGame, Time,
+    constants, BiomeTerrain, BiomeTerrainConfig, Entity, EntityData,
+    EntityMessage, EntityRunBundle, EntityService, EntityType, Gor,
+    LocationService, MessageService, ModifiedTerrain, PathfindingService,
+    PresenterService, SaveError, SavedGame, TerrainService, Time,
 };
 use crate::application_context::Ao;
+use crate::model::IPoint;
 use crate::native::RuntimeResources;
 use crate::presenter::*;
 use crate::ui::SpriteSource;
 use crate::view::*;
 use crate::view_types::ViewTypes;
+use std::sync::Arc;
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::Mutex;
+use tokio::task::spawn_blocking;
 
 const ENTITY_MESSAGE_CHANNEL_SIZE: usize = 8;
 
@@ -34,18 +41,26 @@ impl TemporaryChannel {
 #[derive(Clone, Debug)]
 pub struct Services {
     runtime: Gor<Runtime>,
+    seed: u64,
 
     time: Time,
     location_service: LocationService,
     entity_service: EntityService,
     message_service: MessageService,
     presenter_service: PresenterService,
+    terrain_service: TerrainService,
+    terrain: Arc<ModifiedTerrain<BiomeTerrain>>,
+    pathfinding_service: PathfindingService,
+    /// Held while entities are spawned, despawned or saved so each of those
+    /// sees the services in a consistent state
+    lifecycle_lock: Arc<Mutex<()>>,
 }
 
 impl Services {
     pub fn new(
         boxed_runtime: Box<Runtime>,
         saved_game: SavedGame,
+        terrain_config: &BiomeTerrainConfig,
     ) -> (
         Services,
         Vec<EntityRunBundle>,
@@ -58,13 +73,27 @@ impl Services {
             locations,
             player_presenter_states,
             player,
+            terrain_modifications,
         } = saved_game;
 
         let runtime = Gor::new(&boxed_runtime);
         let runtime_dropper = move || drop(boxed_runtime);
         let time = Time::new(runtime.clone());
+
+        let terrain_service = TerrainService::new(terrain_modifications);
+        let terrain = Arc::new(ModifiedTerrain::new(
+            BiomeTerrain::from_config(terrain_config, seed),
+            terrain_service.clone(),
+        ));
+
         let (location_service, location_service_dropper) =
-            LocationService::new_from_data(&locations);
+            LocationService::new_from_data(&locations, terrain.clone());
+
+        let pathfinding_service = PathfindingService::new(
+            terrain.clone(),
+            location_service.clone(),
+            constants::DIAGONAL_MOVEMENT,
+        );
 
         let mut entity_channels = entities.map(|data| {
             let (send, recv) = channel(ENTITY_MESSAGE_CHANNEL_SIZE);
@@ -88,11 +117,16 @@ impl Services {
 
         let services = Services {
             runtime,
+            seed,
             time,
             location_service,
             entity_service,
             message_service,
             presenter_service,
+            terrain_service,
+            terrain,
+            pathfinding_service,
+            lifecycle_lock: Default::default(),
         };
 
         let run_bundles = entity_channels
@@ -156,6 +190,140 @@ impl Services {
         }
     }
 
+    /// Create a new entity of the given type at the given location with the
+    /// given initial presenter state. The entity starts acting once the
+    /// returned run bundle is passed to `run`
+    pub async fn spawn(
+        &self,
+        entity_type: EntityType,
+        location: IPoint,
+        initial_state: PlayerPresenterState,
+    ) -> EntityRunBundle {
+        let _lifecycle = self.lifecycle_lock.lock().await;
+
+        let entity = self.entity_service.insert(entity_type).await;
+        let location_key = self.location_service.insert(entity, location).await;
+
+        let entity_data = EntityData {
+            entity_type,
+            entity: Some(entity),
+            location_key: Some(location_key),
+        };
+
+        self.entity_service.update(entity, entity_data).await;
+
+        let (sender, receiver) = channel(ENTITY_MESSAGE_CHANNEL_SIZE);
+
+        self.message_service.insert(&entity, sender).await;
+
+        match entity_type {
+            EntityType::Player => {
+                self.presenter_service
+                    .insert_player_presenter_state(entity, initial_state)
+                    .await
+            }
+        }
+
+        EntityRunBundle::new(entity, entity_data, receiver, self.clone())
+    }
+
+    /// Remove the given entity from the game. Its message channel is closed
+    /// first, and the rest of it is only removed once its presenter stops.
+    /// Returns false if the entity doesn't exist or is the player
+    pub async fn despawn(&self, entity: &Entity) -> bool {
+        if self.entity_service.get_player().entity == *entity {
+            return false;
+        }
+
+        let entity_type = {
+            let _lifecycle = self.lifecycle_lock.lock().await;
+
+            let entity_data = match self.entity_service.get(entity) {
+                Some(entity_data) => entity_data,
+                None => return false,
+            };
+
+            self.message_service.close(entity).await;
+
+            entity_data.entity_type
+        };
+
+        // The lifecycle lock isn't held while the presenter stops, since it
+        // may need to finish a step first
+        match entity_type {
+            EntityType::Player => {
+                self.presenter_service
+                    .wait_for_player_presenter_lease(entity)
+                    .await
+            }
+        }
+
+        let _lifecycle = self.lifecycle_lock.lock().await;
+
+        // Another despawn may have finished while this one waited
+        let entity_data = match self.entity_service.get(entity) {
+            Some(entity_data) => entity_data,
+            None => return false,
+        };
+
+        match entity_data.entity_type {
+            EntityType::Player => {
+                let _ = self
+                    .presenter_service
+                    .remove_player_presenter_state(entity)
+                    .await;
+            }
+        }
+
+        if let Some(location_key) = &entity_data.location_key {
+            let _ = self.location_service.remove_by_key(location_key).await;
+        }
+
+        self.message_service.remove(entity).await;
+        self.entity_service.remove(entity).await;
+
+        true
+    }
+
+    /// Capture the current state of the game. Time is paused while the state
+    /// is read so that the presenters can't change it part way through, and
+    /// resumed once the snapshot is taken. Fails if the state read doesn't
+    /// form a consistent saved game
+    pub async fn snapshot(&self) -> Result<SavedGame, SaveError> {
+        let _lifecycle = self.lifecycle_lock.lock().await;
+        let runtime = self.runtime();
+
+        let _ = spawn_blocking(move || runtime.pause()).await;
+
+        let elapsed_millis = self.runtime.elapsed_millis();
+        let entities = self.entity_service.to_raw_entity_data();
+        let locations = self.location_service.to_saveable_locations().await;
+        let player_presenter_states =
+            self.presenter_service.get_player_presenter_states().await;
+        let terrain_modifications =
+            self.terrain_service.to_terrain_modifications();
+
+        let runtime = self.runtime();
+
+        let _ = spawn_blocking(move || runtime.resume()).await;
+
+        SavedGameRecord::new(
+            self.seed,
+            elapsed_millis,
+            &self.entity_service.get_player(),
+            entities.iter().map(|(key_data, data)| (*key_data, data)),
+            locations.iter_raw(),
+            player_presenter_states.iter(),
+            &terrain_modifications,
+        )
+        .into_saved_game()
+    }
+
+    /// Get the seed of the world being played
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn location_service(&self) -> LocationService {
         self.location_service.clone()
     }
@@ -179,4 +347,184 @@ impl Services {
     pub fn message_service(&self) -> MessageService {
         self.message_service.clone()
     }
+
+    pub fn terrain_service(&self) -> TerrainService {
+        self.terrain_service.clone()
+    }
+
+    /// Get the terrain of the world being played including the player's
+    /// modifications
+    pub fn terrain(&self) -> Arc<ModifiedTerrain<BiomeTerrain>> {
+        self.terrain.clone()
+    }
+
+    pub fn pathfinding_service(&self) -> PathfindingService {
+        self.pathfinding_service.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::TerrainType;
+    use crate::model::IRect;
+    use std::time::Duration;
+    use tokio::runtime::Builder;
+
+    fn create_game_runtime() -> Box<Runtime> {
+        Box::new(
+            Builder::new_multi_thread()
+                .worker_threads(1)
+                .enable_time()
+                .pausable_time(true, Duration::from_millis(1500))
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let app_runtime = Builder::new_current_thread().build().unwrap();
+
+        let (services, _, droppers) = Services::new(
+            create_game_runtime(),
+            SavedGame::new(42),
+            &Default::default(),
+        );
+
+        let saved_game = app_runtime.block_on(async {
+            let player = services.entity_service().get_player();
+
+            services
+                .location_service()
+                .move_by_key(&player.location_key, IPoint::new(3, -4))
+                .await;
+
+            services
+                .terrain_service()
+                .modify(IPoint::new(1, 1), Some(TerrainType::Dirt));
+
+            services.snapshot().await.unwrap()
+        });
+
+        assert_eq!(42, saved_game.seed);
+        assert!(saved_game.elapsed_millis >= 1500);
+        assert_eq!(1, saved_game.entities.len());
+        assert_eq!(1, saved_game.player_presenter_states.len());
+        assert_eq!(
+            Some(TerrainType::Dirt),
+            saved_game.terrain_modifications.get(&IPoint::new(1, 1))
+        );
+        assert_eq!(
+            Some(IRect::new(3, -4, 1, 1)),
+            saved_game
+                .locations
+                .get(&saved_game.player.location_key)
+                .map(|l| l.location)
+        );
+
+        for dropper in droppers {
+            dropper();
+        }
+    }
+
+    #[test]
+    fn test_spawn_and_despawn() {
+        let app_runtime = Builder::new_current_thread().build().unwrap();
+
+        let (services, _, droppers) = Services::new(
+            create_game_runtime(),
+            SavedGame::new(42),
+            &Default::default(),
+        );
+
+        app_runtime.block_on(async {
+            let mut run_bundle = services
+                .spawn(
+                    EntityType::Player,
+                    IPoint::new(5, 6),
+                    PlayerPresenterState::default(),
+                )
+                .await;
+            let entity = run_bundle.entity;
+            let location_key = run_bundle.entity_data.location_key.unwrap();
+
+            assert_eq!(
+                Some(run_bundle.entity_data),
+                services.entity_service().get(&entity)
+            );
+            assert_eq!(
+                vec![entity],
+                services
+                    .location_service()
+                    .get_entities_at(&IPoint::new(5, 6))
+                    .await
+            );
+
+            services
+                .message_service()
+                .send_message(
+                    &entity,
+                    EntityMessage::GoalSet(IPoint::new(1, 1)),
+                )
+                .await;
+
+            assert!(run_bundle.entity_message_source.recv().await.is_some());
+
+            let saved_game = services.snapshot().await.unwrap();
+
+            assert_eq!(2, saved_game.entities.len());
+            assert_eq!(2, saved_game.locations.len());
+            assert_eq!(2, saved_game.player_presenter_states.len());
+
+            // A rented state is only removed once its lease is returned
+            let lease = services
+                .presenter_service()
+                .rent_player_presenter_state(&entity)
+                .await
+                .unwrap();
+
+            let despawn = services.despawn(&entity);
+            let return_lease = async {
+                // Despawning closes the channel the presenter listens to
+                assert!(run_bundle
+                    .entity_message_source
+                    .recv()
+                    .await
+                    .is_none());
+
+                // Waiting for the lease doesn't hold up saving
+                let saved_game = services.snapshot().await.unwrap();
+
+                assert_eq!(2, saved_game.entities.len());
+
+                drop(lease);
+            };
+
+            let (despawned, _) = tokio::join!(despawn, return_lease);
+
+            assert!(despawned);
+            assert!(!services.despawn(&entity).await);
+            assert!(
+                !services
+                    .despawn(&services.entity_service().get_player().entity)
+                    .await
+            );
+            assert_eq!(None, services.entity_service().get(&entity));
+            assert_eq!(
+                None,
+                services.location_service().get_by_key(&location_key).await
+            );
+
+            let saved_game = services.snapshot().await.unwrap();
+
+            assert_eq!(1, saved_game.entities.len());
+            assert_eq!(1, saved_game.locations.len());
+            assert_eq!(1, saved_game.player_presenter_states.len());
+        });
+
+        for dropper in droppers {
+            dropper();
+        }
+    }
 }
@@ -0,0 +1,251 @@
+use super::TerrainType;
+use crate::native::ResourceLoader;
+
+/// Name of the resource the terrain configuration is loaded from
+pub const TERRAIN_CONFIG_RESOURCE: &str = "terrain.json";
+
+/// Description of how the terrain generator builds the world. Any section or
+/// field missing from a configuration file takes its default value
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainConfig {
+    pub biome: BiomeTerrainConfig,
+}
+
+/// One octave of a fractal noise field
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OctaveConfig {
+    /// Tiles per unit of noise
+    pub scale: f64,
+    pub weight: f32,
+}
+
+/// Rule mapping ranges of the biome fields to a terrain type. Bounds are
+/// exclusive and a missing bound is unbounded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BiomeRule {
+    pub terrain_type: TerrainType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_elevation: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_elevation: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_moisture: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_moisture: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_temperature: Option<f32>,
+}
+
+/// Fields and biome mapping used by `BiomeTerrain`. The octave weights of each
+/// field should sum to one so the fields stay within -1 and 1
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BiomeTerrainConfig {
+    pub elevation: Vec<OctaveConfig>,
+    pub moisture: Vec<OctaveConfig>,
+    pub temperature: Vec<OctaveConfig>,
+    /// Elevation above which the temperature starts dropping
+    pub lapse_base_elevation: f32,
+    /// How much colder the temperature gets per unit of elevation above the
+    /// lapse base elevation
+    pub lapse_rate: f32,
+    /// Rules checked in order, the first matching rule picks the terrain
+    pub rules: Vec<BiomeRule>,
+    /// Terrain used where no rule matches
+    pub default_terrain_type: TerrainType,
+}
+
+fn within(value: f32, min: Option<f32>, max: Option<f32>) -> bool {
+    let above_min = match min {
+        Some(min) => value > min,
+        None => true,
+    };
+    let below_max = match max {
+        Some(max) => value < max,
+        None => true,
+    };
+
+    above_min && below_max
+}
+
+fn octave(scale: f64, weight: f32) -> OctaveConfig {
+    OctaveConfig { scale, weight }
+}
+
+impl BiomeRule {
+    fn new(terrain_type: TerrainType) -> BiomeRule {
+        BiomeRule {
+            terrain_type,
+            min_elevation: None,
+            max_elevation: None,
+            min_moisture: None,
+            max_moisture: None,
+            min_temperature: None,
+            max_temperature: None,
+        }
+    }
+
+    pub fn matches(
+        &self,
+        elevation: f32,
+        moisture: f32,
+        temperature: f32,
+    ) -> bool {
+        within(elevation, self.min_elevation, self.max_elevation)
+            && within(moisture, self.min_moisture, self.max_moisture)
+            && within(temperature, self.min_temperature, self.max_temperature)
+    }
+}
+
+impl BiomeTerrainConfig {
+    /// Pick the terrain type for the given field values. The temperature given
+    /// is the temperature before adjusting for elevation
+    pub fn classify(
+        &self,
+        elevation: f32,
+        moisture: f32,
+        temperature: f32,
+    ) -> TerrainType {
+        let temperature = temperature
+            - self.lapse_rate * (elevation - self.lapse_base_elevation).max(0.);
+
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(elevation, moisture, temperature))
+            .map(|rule| rule.terrain_type)
+            .unwrap_or(self.default_terrain_type)
+    }
+}
+
+impl TerrainConfig {
+    /// Load the terrain configuration from the given loader's resources,
+    /// falling back to the defaults if it's missing or malformed
+    pub fn load(resource_loader: &impl ResourceLoader) -> TerrainConfig {
+        let raw = resource_loader.load_text(TERRAIN_CONFIG_RESOURCE.to_owned());
+
+        if raw.is_empty() {
+            info!("No terrain config found, using the defaults");
+            return Default::default();
+        }
+
+        serde_json::from_str(&raw).unwrap_or_else(|e| {
+            error!("Malformed terrain config, using the defaults: {}", e);
+            Default::default()
+        })
+    }
+}
+
+impl Default for BiomeTerrainConfig {
+    fn default() -> Self {
+        BiomeTerrainConfig {
+            elevation: vec![
+                octave(647., 0.58),
+                octave(211., 0.28),
+                octave(67., 0.14),
+            ],
+            moisture: vec![octave(911., 0.7), octave(293., 0.3)],
+            temperature: vec![octave(1597., 0.8), octave(523., 0.2)],
+            lapse_base_elevation: -0.18,
+            lapse_rate: 0.6,
+            rules: vec![
+                BiomeRule {
+                    max_elevation: Some(-0.25),
+                    ..BiomeRule::new(TerrainType::Water)
+                },
+                BiomeRule {
+                    max_elevation: Some(-0.18),
+                    ..BiomeRule::new(TerrainType::Sand)
+                },
+                BiomeRule {
+                    min_elevation: Some(0.6),
+                    ..BiomeRule::new(TerrainType::Snow)
+                },
+                BiomeRule {
+                    max_temperature: Some(-0.45),
+                    ..BiomeRule::new(TerrainType::Snow)
+                },
+                BiomeRule {
+                    min_elevation: Some(0.45),
+                    ..BiomeRule::new(TerrainType::Rock)
+                },
+                BiomeRule {
+                    max_moisture: Some(-0.3),
+                    min_temperature: Some(0.2),
+                    ..BiomeRule::new(TerrainType::Sand)
+                },
+                BiomeRule {
+                    max_moisture: Some(-0.3),
+                    ..BiomeRule::new(TerrainType::Dirt)
+                },
+                BiomeRule {
+                    min_moisture: Some(0.25),
+                    ..BiomeRule::new(TerrainType::ForestFloor)
+                },
+            ],
+            default_terrain_type: TerrainType::Grass,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::headless::HeadlessResourceLoader;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_classify() {
+        let config = BiomeTerrainConfig::default();
+
+        assert_eq!(TerrainType::Water, config.classify(-0.5, 0., 0.));
+        assert_eq!(TerrainType::Sand, config.classify(-0.2, 0., 0.));
+        assert_eq!(TerrainType::Grass, config.classify(0., 0., 0.));
+        assert_eq!(TerrainType::ForestFloor, config.classify(0., 0.5, 0.));
+        assert_eq!(TerrainType::Dirt, config.classify(0., -0.5, 0.));
+        assert_eq!(TerrainType::Sand, config.classify(0., -0.5, 0.5));
+        assert_eq!(TerrainType::Rock, config.classify(0.5, 0., 0.5));
+        assert_eq!(TerrainType::Snow, config.classify(0.7, 0., 0.5));
+        assert_eq!(TerrainType::Snow, config.classify(0., 0., -0.6));
+    }
+
+    #[test]
+    fn test_partial_config() {
+        let config: TerrainConfig = serde_json::from_str(
+            r#"{
+                "biome": {
+                    "rules": [
+                        { "terrain_type": "Water", "max_elevation": 0.0 }
+                    ],
+                    "default_terrain_type": "Rock"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            BiomeTerrainConfig::default().elevation,
+            config.biome.elevation
+        );
+        assert_eq!(TerrainType::Water, config.biome.classify(-0.1, 0., 0.));
+        assert_eq!(TerrainType::Rock, config.biome.classify(0.1, 0., 0.));
+    }
+
+    #[test]
+    fn test_load_bundled_config() {
+        let loader = HeadlessResourceLoader::default();
+
+        // The bundled configuration describes the default world
+        assert_eq!(TerrainConfig::default(), TerrainConfig::load(&loader));
+    }
+
+    #[test]
+    fn test_load_missing_config() {
+        let loader = HeadlessResourceLoader::default()
+            .with_config_dir(PathBuf::from("/nonexistent"));
+
+        assert_eq!(TerrainConfig::default(), TerrainConfig::load(&loader));
+    }
+}
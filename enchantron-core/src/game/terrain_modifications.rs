@@ -0,0 +1,307 @@
+use super::TerrainType;
+use crate::model::{IPoint, IRect, ISize};
+use crate::util::ValueRect;
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+/// Length in tiles of the sides of the chunks modifications are grouped into
+const MODIFICATION_CHUNK_LENGTH: i64 = 16;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ModificationChunk {
+    tiles: HashMap<IPoint, TerrainType>,
+    /// Revision of the last change to the chunk
+    revision: u64,
+}
+
+/// Sparse changes made to the generated terrain, like tilled soil or paths
+/// placed by the player. Changes are grouped into square chunks of tiles so
+/// the changes within an area can be found without checking all of them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TerrainModifications {
+    chunks: HashMap<IPoint, ModificationChunk>,
+    /// Incremented with every change
+    revision: u64,
+    /// Revisions of the changes that emptied out chunks, keyed by the
+    /// position of the removed chunk, so the chunk's area still counts as
+    /// changed then
+    removed_chunks: HashMap<IPoint, u64>,
+}
+
+fn chunk_of(position: &IPoint) -> IPoint {
+    IPoint::new(
+        position.x.div_euclid(MODIFICATION_CHUNK_LENGTH),
+        position.y.div_euclid(MODIFICATION_CHUNK_LENGTH),
+    )
+}
+
+/// Iterate over the values of the chunks that overlap the given rect
+fn overlapping<'a, V>(
+    chunks: &'a HashMap<IPoint, V>,
+    rect: &IRect,
+) -> Box<dyn Iterator<Item = &'a V> + 'a> {
+    let top_left = chunk_of(&rect.top_left);
+    let bottom_right = chunk_of(&rect.bottom_right_inclusive());
+    let chunk_rect = IRect {
+        top_left,
+        size: ISize::new(
+            (bottom_right.x - top_left.x + 1) as usize,
+            (bottom_right.y - top_left.y + 1) as usize,
+        ),
+    };
+
+    // Look up each chunk in the rect unless there are fewer chunks in total
+    // than that
+    if chunk_rect.area() <= chunks.len() {
+        Box::new(
+            (top_left.y..=bottom_right.y)
+                .flat_map(move |y| {
+                    (top_left.x..=bottom_right.x)
+                        .map(move |x| IPoint::new(x, y))
+                })
+                .filter_map(move |chunk| chunks.get(&chunk)),
+        )
+    } else {
+        Box::new(
+            chunks
+                .iter()
+                .filter(move |(chunk, _)| chunk_rect.contains_point(chunk))
+                .map(|(_, value)| value),
+        )
+    }
+}
+
+impl TerrainModifications {
+    /// Get the terrain the tile at the given position was changed to if it
+    /// was changed
+    pub fn get(&self, position: &IPoint) -> Option<TerrainType> {
+        self.chunks
+            .get(&chunk_of(position))
+            .and_then(|chunk| chunk.tiles.get(position))
+            .copied()
+    }
+
+    /// Change the terrain at the given position, or restore the generated
+    /// terrain if no terrain type is given. Returns true if the modification
+    /// at the position changed
+    pub fn set(
+        &mut self,
+        position: IPoint,
+        terrain_type: Option<TerrainType>,
+    ) -> bool {
+        if self.get(&position) == terrain_type {
+            return false;
+        }
+
+        self.revision += 1;
+
+        let chunk_position = chunk_of(&position);
+        let chunk = self.chunks.entry(chunk_position).or_default();
+
+        chunk.revision = self.revision;
+
+        match terrain_type {
+            Some(terrain_type) => chunk.tiles.insert(position, terrain_type),
+            None => chunk.tiles.remove(&position),
+        };
+
+        if chunk.tiles.is_empty() {
+            self.chunks.remove(&chunk_position);
+            self.removed_chunks.insert(chunk_position, self.revision);
+        } else {
+            // The chunk's own revision covers the removal from now on
+            self.removed_chunks.remove(&chunk_position);
+        }
+
+        true
+    }
+
+    /// Get the revision of the latest change
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Get the number of modified tiles
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(|chunk| chunk.tiles.len()).sum()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over all the modified tiles in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (IPoint, TerrainType)> + '_ {
+        self.chunks.values().flat_map(|chunk| {
+            chunk
+                .tiles
+                .iter()
+                .map(|(position, terrain)| (*position, *terrain))
+        })
+    }
+
+    /// Check if any tile in the given rect may have changed after the given
+    /// revision
+    pub fn modified_since(&self, rect: &IRect, revision: u64) -> bool {
+        overlapping(&self.chunks, rect).any(|chunk| chunk.revision > revision)
+            || overlapping(&self.removed_chunks, rect)
+                .any(|removed_revision| *removed_revision > revision)
+    }
+
+    /// Replace the terrain types in the given terrain values with the
+    /// modifications to the tiles they were sampled from
+    pub fn apply_to(&self, terrain: &mut ValueRect<(f64, TerrainType)>) {
+        let rect = *terrain.rect();
+        let x_stride = *terrain.x_stride() as i64;
+        let y_stride = *terrain.y_stride() as i64;
+        let values_width = *terrain.values_width();
+
+        let sampled_modifications = overlapping(&self.chunks, &rect)
+            .flat_map(|chunk| chunk.tiles.iter())
+            .filter(|(position, _)| rect.contains_point(position))
+            .filter_map(|(position, terrain_type)| {
+                let offset = position - &rect.top_left;
+
+                if offset.x % x_stride != 0 || offset.y % y_stride != 0 {
+                    return None;
+                }
+
+                let index = (offset.y / y_stride) as usize * values_width
+                    + (offset.x / x_stride) as usize;
+
+                Some((index, *terrain_type))
+            })
+            .collect::<Vec<_>>();
+
+        let values = terrain.get_raw_values_mut();
+
+        for (index, terrain_type) in sampled_modifications {
+            values[index].1 = terrain_type;
+        }
+    }
+}
+
+impl FromIterator<(IPoint, TerrainType)> for TerrainModifications {
+    fn from_iter<I: IntoIterator<Item = (IPoint, TerrainType)>>(
+        iter: I,
+    ) -> Self {
+        let mut result = TerrainModifications::default();
+
+        for (position, terrain_type) in iter {
+            result.set(position, Some(terrain_type));
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_and_get() {
+        let mut modifications = TerrainModifications::default();
+        let position = IPoint::new(-17, 40);
+
+        assert!(modifications.is_empty());
+        assert!(modifications.set(position, Some(TerrainType::Dirt)));
+        assert!(!modifications.set(position, Some(TerrainType::Dirt)));
+        assert_eq!(Some(TerrainType::Dirt), modifications.get(&position));
+        assert_eq!(None, modifications.get(&IPoint::new(-16, 40)));
+        assert_eq!(1, modifications.len());
+
+        assert!(modifications.set(position, None));
+        assert!(!modifications.set(position, None));
+        assert_eq!(None, modifications.get(&position));
+        assert!(modifications.is_empty());
+        assert_eq!(2, modifications.revision());
+
+        // The chunk is dropped along with its last modification
+        assert!(modifications.chunks.is_empty());
+    }
+
+    #[test]
+    fn test_modified_since() {
+        let mut modifications = TerrainModifications::default();
+
+        modifications.set(IPoint::new(5, 5), Some(TerrainType::Sand));
+
+        let revision = modifications.revision();
+
+        modifications.set(IPoint::new(-1, 100), Some(TerrainType::Dirt));
+
+        // Only the chunk of the later change is newer than the revision
+        assert!(modifications.modified_since(&IRect::new(0, 0, 16, 16), 0));
+        assert!(
+            !modifications.modified_since(&IRect::new(0, 0, 16, 16), revision)
+        );
+        assert!(
+            modifications.modified_since(&IRect::new(-4, 96, 4, 4), revision)
+        );
+        assert!(modifications
+            .modified_since(&IRect::new(-1024, -1024, 2048, 2048), revision));
+
+        // Removing a modification still counts as a change, even when it
+        // removes the chunk
+        let revision = modifications.revision();
+        modifications.set(IPoint::new(5, 5), None);
+
+        assert!(modifications.modified_since(&IRect::new(0, 0, 8, 8), revision));
+        assert!(modifications
+            .modified_since(&IRect::new(-1024, -1024, 2048, 2048), revision));
+
+        // but only for the removed chunk
+        assert!(
+            !modifications.modified_since(&IRect::new(-4, 96, 4, 4), revision)
+        );
+        assert!(
+            !modifications.modified_since(&IRect::new(16, 0, 8, 8), revision)
+        );
+
+        let revision = modifications.revision();
+        modifications.set(IPoint::new(-1, 101), Some(TerrainType::Rock));
+
+        assert!(
+            !modifications.modified_since(&IRect::new(0, 0, 8, 8), revision)
+        );
+
+        // Filling the chunk again replaces its removal
+        modifications.set(IPoint::new(6, 6), Some(TerrainType::Dirt));
+
+        assert!(modifications.removed_chunks.is_empty());
+        assert!(modifications.modified_since(&IRect::new(0, 0, 8, 8), revision));
+    }
+
+    #[test]
+    fn test_apply_to_sampled_tiles() {
+        let modifications = vec![
+            (IPoint::new(2, 4), TerrainType::Dirt),
+            (IPoint::new(3, 4), TerrainType::Rock),
+            (IPoint::new(40, 0), TerrainType::Snow),
+        ]
+        .into_iter()
+        .collect::<TerrainModifications>();
+
+        let mut terrain =
+            ValueRect::new_from_rect(IRect::new(0, 0, 8, 8), 2, 2, |_| {
+                (0.5, TerrainType::Grass)
+            });
+
+        modifications.apply_to(&mut terrain);
+
+        // Only the tile the values are sampled from changes them
+        terrain.for_each_value_coord(|coord, (elevation, terrain_type)| {
+            let expected = if coord.x == 1 && coord.y == 2 {
+                TerrainType::Dirt
+            } else {
+                TerrainType::Grass
+            };
+
+            assert_eq!(0.5, *elevation);
+            assert_eq!(expected, *terrain_type, "{:?}", coord);
+        });
+    }
+}
@@ -0,0 +1,84 @@
+use super::TerrainType;
+
+/// Gameplay rules for a type of terrain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainProperties {
+    /// Whether entities can walk onto the terrain
+    pub walkable: bool,
+    /// How many times longer it takes to walk onto the terrain than onto grass
+    pub movement_cost: f64,
+    /// Whether the terrain blocks the line of sight across it
+    pub blocks_sight: bool,
+}
+
+impl TerrainProperties {
+    /// Get the properties of the given type of terrain
+    pub fn of(terrain_type: TerrainType) -> &'static TerrainProperties {
+        match terrain_type {
+            TerrainType::Water => &TerrainProperties {
+                walkable: false,
+                movement_cost: f64::INFINITY,
+                blocks_sight: false,
+            },
+            TerrainType::Sand => &TerrainProperties {
+                walkable: true,
+                movement_cost: 1.5,
+                blocks_sight: false,
+            },
+            TerrainType::Grass => &TerrainProperties {
+                walkable: true,
+                movement_cost: 1.0,
+                blocks_sight: false,
+            },
+            TerrainType::Dirt => &TerrainProperties {
+                walkable: true,
+                movement_cost: 1.25,
+                blocks_sight: false,
+            },
+            TerrainType::ForestFloor => &TerrainProperties {
+                walkable: true,
+                movement_cost: 1.5,
+                blocks_sight: true,
+            },
+            TerrainType::Rock => &TerrainProperties {
+                walkable: false,
+                movement_cost: f64::INFINITY,
+                blocks_sight: true,
+            },
+            TerrainType::Snow => &TerrainProperties {
+                walkable: true,
+                movement_cost: 2.0,
+                blocks_sight: false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_terrain_properties() {
+        assert!(!TerrainType::Water.properties().walkable);
+        assert!(!TerrainType::Rock.properties().walkable);
+        assert!(TerrainType::Rock.properties().blocks_sight);
+        assert!(!TerrainType::Grass.properties().blocks_sight);
+
+        // Grass is the baseline every other cost is relative to
+        assert_eq!(1.0, TerrainType::Grass.properties().movement_cost);
+
+        for terrain_type in &TerrainType::ALL {
+            let properties = terrain_type.properties();
+
+            if properties.walkable {
+                assert!(properties.movement_cost >= 1.0, "{:?}", terrain_type);
+            }
+        }
+
+        assert!(
+            TerrainType::Dirt.properties().movement_cost
+                > TerrainType::Grass.properties().movement_cost
+        );
+    }
+}
@@ -1,8 +1,402 @@
-use super::TerrainType;
-use crate::model::{IPoint, IRect};
+use super::{TerrainRegion, TerrainType};
+use crate::model::{IPoint, IRect, ISize};
 use crate::util::ValueRect;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Half the width of the first square searched for the nearest tile of a type.
+/// The square doubles in size until the nearest tile is known
+const INITIAL_SEARCH_HALF_WIDTH: usize = 8;
+
+/// Length in tiles of the sides of the blocks of terrain generated at once
+/// while flood filling
+const FLOOD_FILL_BLOCK_LENGTH: i64 = 32;
 
 pub trait TerrainProvider {
     fn get_for(&self, position: &IPoint) -> TerrainType;
     fn get_for_rect(&self, rect: &IRect) -> ValueRect<(f64, TerrainType)>;
+
+    /// Sample the terrain in the given rect once every `stride` tiles in each
+    /// direction. This is used to draw the terrain at coarser levels of detail
+    fn get_for_rect_with_stride(
+        &self,
+        rect: &IRect,
+        stride: usize,
+    ) -> ValueRect<(f64, TerrainType)> {
+        ValueRect::new_from_rect(*rect, stride, stride, |point| {
+            (0.0, self.get_for(point))
+        })
+    }
+
+    /// Find the closest tile of the given terrain type within the given
+    /// radius of the origin. Ties are broken by the topmost then leftmost
+    /// tile. Terrain is generated in squares around the origin that double in
+    /// size until a match is found, so nearby matches are found quickly
+    fn find_nearest(
+        &self,
+        origin: &IPoint,
+        terrain_type: TerrainType,
+        radius: usize,
+    ) -> Option<IPoint> {
+        let max_distance_squared = (radius * radius) as i64;
+        let mut half_width = INITIAL_SEARCH_HALF_WIDTH.min(radius);
+
+        loop {
+            let rect = IRect {
+                top_left: IPoint::new(
+                    origin.x - half_width as i64,
+                    origin.y - half_width as i64,
+                ),
+                size: ISize::new(2 * half_width + 1, 2 * half_width + 1),
+            };
+
+            let mut nearest: Option<(i64, IPoint)> = None;
+
+            self.get_for_rect(&rect).for_each_value_coord(
+                |coord, (_, tile_type)| {
+                    if *tile_type != terrain_type {
+                        return;
+                    }
+
+                    let position = IPoint::new(
+                        rect.top_left.x + coord.x as i64,
+                        rect.top_left.y + coord.y as i64,
+                    );
+                    let distance_squared = position.distance_squared(origin);
+
+                    // Values are visited row by row, so the first of equally
+                    // distant tiles is the topmost, leftmost one
+                    let closer = nearest
+                        .map(|(nearest_distance, _)| {
+                            distance_squared < nearest_distance
+                        })
+                        .unwrap_or(true);
+
+                    if distance_squared <= max_distance_squared && closer {
+                        nearest = Some((distance_squared, position));
+                    }
+                },
+            );
+
+            // Every tile outside the square is further away than half its
+            // width, so matches within that distance can't be beaten
+            let settled_distance_squared = (half_width * half_width) as i64;
+
+            match nearest {
+                Some((distance_squared, position))
+                    if distance_squared <= settled_distance_squared =>
+                {
+                    return Some(position)
+                }
+                _ if half_width >= radius => {
+                    return nearest.map(|(_, position)| position)
+                }
+                _ => half_width = (half_width * 2).min(radius),
+            }
+        }
+    }
+
+    /// Collect the tiles connected to the start tile through their edges that
+    /// have the same type of terrain as the start tile. At most `max_tiles`
+    /// tiles are collected
+    fn flood_fill(&self, start: &IPoint, max_tiles: usize) -> TerrainRegion {
+        let mut blocks = HashMap::new();
+        let mut get_type = |position: &IPoint| {
+            let block = IPoint::new(
+                position.x.div_euclid(FLOOD_FILL_BLOCK_LENGTH),
+                position.y.div_euclid(FLOOD_FILL_BLOCK_LENGTH),
+            );
+
+            let terrain = blocks.entry(block).or_insert_with(|| {
+                self.get_for_rect(&IRect::new(
+                    block.x * FLOOD_FILL_BLOCK_LENGTH,
+                    block.y * FLOOD_FILL_BLOCK_LENGTH,
+                    FLOOD_FILL_BLOCK_LENGTH as usize,
+                    FLOOD_FILL_BLOCK_LENGTH as usize,
+                ))
+            });
+
+            let offset = position - &terrain.rect().top_left;
+
+            terrain
+                .get(offset.x as usize, offset.y as usize)
+                .map(|(_, terrain_type)| *terrain_type)
+                .expect("Position outside of its flood fill block")
+        };
+
+        let terrain_type = get_type(start);
+        let mut tiles = HashSet::new();
+        let mut frontier = VecDeque::new();
+        let mut complete = true;
+
+        if max_tiles > 0 {
+            tiles.insert(*start);
+            frontier.push_back(*start);
+        } else {
+            complete = false;
+        }
+
+        'fill: while let Some(position) = frontier.pop_front() {
+            let neighbors = [
+                IPoint::new(position.x, position.y - 1),
+                IPoint::new(position.x + 1, position.y),
+                IPoint::new(position.x, position.y + 1),
+                IPoint::new(position.x - 1, position.y),
+            ];
+
+            for neighbor in &neighbors {
+                if tiles.contains(neighbor)
+                    || get_type(neighbor) != terrain_type
+                {
+                    continue;
+                }
+
+                if tiles.len() >= max_tiles {
+                    complete = false;
+                    break 'fill;
+                }
+
+                tiles.insert(*neighbor);
+                frontier.push_back(*neighbor);
+            }
+        }
+
+        TerrainRegion {
+            terrain_type,
+            tiles,
+            complete,
+        }
+    }
+
+    /// Check that none of the tiles on the straight line between the given
+    /// tiles have terrain that blocks sight. The end tiles themselves are not
+    /// checked. The line is generated in runs of tiles that share a row or
+    /// column
+    fn has_line_of_sight(&self, from: &IPoint, to: &IPoint) -> bool {
+        let tiles = get_line_between(from, to);
+        let between = match tiles.len() {
+            0..=2 => return true,
+            len => &tiles[1..len - 1],
+        };
+
+        let horizontal = (to.x - from.x).abs() >= (to.y - from.y).abs();
+
+        let mut run_start = 0;
+
+        while run_start < between.len() {
+            let first = between[run_start];
+
+            let run_length = between[run_start..]
+                .iter()
+                .take_while(|tile| {
+                    if horizontal {
+                        tile.y == first.y
+                    } else {
+                        tile.x == first.x
+                    }
+                })
+                .count();
+
+            let last = between[run_start + run_length - 1];
+            let top_left = first.component_min(&last);
+            let run_rect = if horizontal {
+                IRect {
+                    top_left,
+                    size: ISize::new(run_length, 1),
+                }
+            } else {
+                IRect {
+                    top_left,
+                    size: ISize::new(1, run_length),
+                }
+            };
+
+            let mut blocked = false;
+
+            self.get_for_rect(&run_rect).for_each_value_coord(
+                |_, (_, terrain_type)| {
+                    blocked = blocked || terrain_type.properties().blocks_sight
+                },
+            );
+
+            if blocked {
+                return false;
+            }
+
+            run_start += run_length;
+        }
+
+        true
+    }
+}
+
+/// Get the tiles on the line between the given tiles including both ends,
+/// using Bresenham's algorithm
+fn get_line_between(from: &IPoint, to: &IPoint) -> Vec<IPoint> {
+    let dx = (to.x - from.x).abs();
+    let dy = -(to.y - from.y).abs();
+    let step_x = if from.x < to.x { 1 } else { -1 };
+    let step_y = if from.y < to.y { 1 } else { -1 };
+
+    let mut result = Vec::with_capacity((dx - dy + 1) as usize);
+    let mut position = *from;
+    let mut error = dx + dy;
+
+    loop {
+        result.push(position);
+
+        if position == *to {
+            return result;
+        }
+
+        let doubled_error = 2 * error;
+
+        if doubled_error >= dy {
+            error += dy;
+            position.x += step_x;
+        }
+
+        if doubled_error <= dx {
+            error += dx;
+            position.y += step_y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::TerrainType::{Grass, Rock, Water};
+
+    /// Terrain provider that draws terrain from a map of characters with its
+    /// top left at the origin, and grass everywhere else
+    struct MapTerrain(Vec<&'static str>);
+
+    impl TerrainProvider for MapTerrain {
+        fn get_for(&self, position: &IPoint) -> TerrainType {
+            if position.x < 0 || position.y < 0 {
+                return Grass;
+            }
+
+            let tile = self
+                .0
+                .get(position.y as usize)
+                .and_then(|row| row.as_bytes().get(position.x as usize));
+
+            match tile {
+                Some(b'~') => Water,
+                Some(b'#') => Rock,
+                _ => Grass,
+            }
+        }
+
+        fn get_for_rect(&self, rect: &IRect) -> ValueRect<(f64, TerrainType)> {
+            self.get_for_rect_with_stride(rect, 1)
+        }
+    }
+
+    #[test]
+    fn test_line_between() {
+        assert_eq!(
+            vec![IPoint::new(0, 0)],
+            get_line_between(&IPoint::new(0, 0), &IPoint::new(0, 0))
+        );
+        assert_eq!(
+            vec![
+                IPoint::new(0, 0),
+                IPoint::new(1, 0),
+                IPoint::new(2, -1),
+                IPoint::new(3, -1),
+            ],
+            get_line_between(&IPoint::new(0, 0), &IPoint::new(3, -1))
+        );
+
+        // Lines are symmetric in length and end where they should
+        let line = get_line_between(&IPoint::new(5, 9), &IPoint::new(-2, -4));
+        assert_eq!(14, line.len());
+        assert_eq!(IPoint::new(-2, -4), line[13]);
+    }
+
+    #[test]
+    fn test_find_nearest() {
+        let terrain = MapTerrain(vec![
+            "..........",
+            "..~.......",
+            "..........",
+            "........~.",
+        ]);
+
+        let origin = IPoint::new(5, 2);
+
+        // Both water tiles are sqrt(10) away, so the upper one wins
+        assert_eq!(
+            Some(IPoint::new(2, 1)),
+            terrain.find_nearest(&origin, Water, 10)
+        );
+        assert_eq!(None, terrain.find_nearest(&origin, Water, 3));
+        assert_eq!(
+            Some(IPoint::new(8, 3)),
+            terrain.find_nearest(&IPoint::new(8, 2), Water, 2)
+        );
+        assert_eq!(Some(origin), terrain.find_nearest(&origin, Grass, 0));
+
+        // Matches far outside the first search square are still found
+        assert_eq!(
+            Some(IPoint::new(8, 3)),
+            terrain.find_nearest(&IPoint::new(40, 1), Water, 40)
+        );
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let terrain =
+            MapTerrain(vec!["~~~.....", "~.~.....", "~~~..~~.", "~...~~.."]);
+
+        let lake = terrain.flood_fill(&IPoint::new(0, 0), 100);
+
+        assert_eq!(Water, lake.terrain_type);
+        assert_eq!(9, lake.len());
+        assert!(lake.complete);
+        assert!(!lake.contains(&IPoint::new(1, 1)));
+        assert!(!lake.contains(&IPoint::new(5, 2)));
+
+        let pond = terrain.flood_fill(&IPoint::new(6, 2), 100);
+        assert_eq!(4, pond.len());
+
+        // The grass is unbounded, so the fill stops at the limit
+        let field = terrain.flood_fill(&IPoint::new(3, 0), 500);
+        assert_eq!(500, field.len());
+        assert!(!field.complete);
+        assert!(field
+            .tiles
+            .iter()
+            .all(|tile| terrain.get_for(tile) == Grass));
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let terrain =
+            MapTerrain(vec!["........", "...#....", "..~~~...", "#......."]);
+
+        assert!(
+            !terrain.has_line_of_sight(&IPoint::new(0, 1), &IPoint::new(7, 1))
+        );
+
+        // Water can't be walked on but can be seen across
+        assert!(
+            terrain.has_line_of_sight(&IPoint::new(0, 2), &IPoint::new(7, 2))
+        );
+
+        // The end tiles don't block sight
+        assert!(
+            terrain.has_line_of_sight(&IPoint::new(3, 1), &IPoint::new(0, 3))
+        );
+
+        // Steep lines are checked by column
+        assert!(
+            !terrain.has_line_of_sight(&IPoint::new(3, 0), &IPoint::new(3, 3))
+        );
+        assert!(
+            terrain.has_line_of_sight(&IPoint::new(5, 0), &IPoint::new(6, 3))
+        );
+    }
 }
@@ -0,0 +1,27 @@
+use super::TerrainType;
+use crate::model::IPoint;
+use std::collections::HashSet;
+
+/// Connected area of tiles that all have the same type of terrain
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainRegion {
+    pub terrain_type: TerrainType,
+    pub tiles: HashSet<IPoint>,
+    /// False if the region was cut off because it reached the limit on the
+    /// number of tiles to collect
+    pub complete: bool,
+}
+
+impl TerrainRegion {
+    pub fn contains(&self, position: &IPoint) -> bool {
+        self.tiles.contains(position)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+}
@@ -0,0 +1,122 @@
+use super::{TerrainModifications, TerrainType};
+use crate::model::{IPoint, IRect};
+use crate::util::ValueRect;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use tokio::sync::mpsc::{
+    unbounded_channel, UnboundedReceiver, UnboundedSender,
+};
+
+/// Shares the player's changes to the terrain between the game and the
+/// terrain workers. Unlike the other services, the modifications are read
+/// from plain threads, so they are kept behind blocking locks that are only
+/// held for single lookups
+#[derive(Clone, Debug, Default)]
+pub struct TerrainService {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    modifications: RwLock<TerrainModifications>,
+    listeners: Mutex<Vec<UnboundedSender<IPoint>>>,
+}
+
+impl TerrainService {
+    pub fn new(modifications: TerrainModifications) -> TerrainService {
+        TerrainService {
+            inner: Arc::new(Inner {
+                modifications: RwLock::new(modifications),
+                listeners: Default::default(),
+            }),
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, TerrainModifications> {
+        self.inner
+            .modifications
+            .read()
+            .expect("Terrain modifications poisoned")
+    }
+
+    /// Get the terrain the player changed the given tile to, if any
+    pub fn get_modification(&self, position: &IPoint) -> Option<TerrainType> {
+        self.read().get(position)
+    }
+
+    /// Change the terrain of the given tile, or restore the generated terrain
+    /// if no terrain type is given. Subscribers are sent the position of the
+    /// tile if it changed
+    pub fn modify(&self, position: IPoint, terrain_type: Option<TerrainType>) {
+        let changed = self
+            .inner
+            .modifications
+            .write()
+            .expect("Terrain modifications poisoned")
+            .set(position, terrain_type);
+
+        if changed {
+            self.inner
+                .listeners
+                .lock()
+                .expect("Terrain listeners poisoned")
+                .retain(|listener| listener.send(position).is_ok());
+        }
+    }
+
+    /// Get a receiver for the positions of the tiles that get modified
+    pub fn subscribe(&self) -> UnboundedReceiver<IPoint> {
+        let (sender, receiver) = unbounded_channel();
+
+        self.inner
+            .listeners
+            .lock()
+            .expect("Terrain listeners poisoned")
+            .push(sender);
+
+        receiver
+    }
+
+    /// Get the revision of the latest modification
+    pub fn revision(&self) -> u64 {
+        self.read().revision()
+    }
+
+    /// Check if any tile in the given rect may have been modified after the
+    /// given revision
+    pub fn modified_since(&self, rect: &IRect, revision: u64) -> bool {
+        self.read().modified_since(rect, revision)
+    }
+
+    /// Apply the modifications to the given generated terrain
+    pub fn apply_to(&self, terrain: &mut ValueRect<(f64, TerrainType)>) {
+        self.read().apply_to(terrain)
+    }
+
+    /// Copy the modifications to be saved
+    pub fn to_terrain_modifications(&self) -> TerrainModifications {
+        self.read().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_modify_notifies_subscribers() {
+        let terrain_service = TerrainService::default();
+        let mut receiver = terrain_service.subscribe();
+        let position = IPoint::new(4, -9);
+
+        terrain_service.modify(position, Some(TerrainType::Sand));
+        terrain_service.modify(position, Some(TerrainType::Sand));
+        terrain_service.modify(position, None);
+
+        // Repeating a modification doesn't change anything
+        assert_eq!(Some(position), receiver.try_recv().ok());
+        assert_eq!(Some(position), receiver.try_recv().ok());
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(None, terrain_service.get_modification(&position));
+        assert_eq!(2, terrain_service.revision());
+    }
+}
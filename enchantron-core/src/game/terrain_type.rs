@@ -1,6 +1,57 @@
+use super::{constants, TerrainProperties};
+
 /// Enumeration of the types of terrain
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum TerrainType {
+    Water,
+    Sand,
     Grass,
     Dirt,
+    ForestFloor,
+    Rock,
+    Snow,
+}
+
+impl TerrainType {
+    /// All the terrain types in the order of their palette indices
+    pub const ALL: [TerrainType; 7] = [
+        TerrainType::Water,
+        TerrainType::Sand,
+        TerrainType::Grass,
+        TerrainType::Dirt,
+        TerrainType::ForestFloor,
+        TerrainType::Rock,
+        TerrainType::Snow,
+    ];
+
+    /// Get the index of this terrain type's color in the terrain palette
+    pub fn palette_index(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Get the gameplay rules for this type of terrain
+    pub fn properties(&self) -> &'static TerrainProperties {
+        TerrainProperties::of(*self)
+    }
+
+    /// Get the RGB colors of all the terrain types in palette index order
+    pub fn get_palette() -> Vec<u8> {
+        TerrainType::ALL
+            .iter()
+            .flat_map(|terrain| terrain.get_rgb().iter().copied())
+            .collect()
+    }
+
+    /// Get the color used to draw this type of terrain
+    pub fn get_rgb(&self) -> &'static [u8; 3] {
+        match self {
+            TerrainType::Water => &constants::WATER_BLUE_RGB,
+            TerrainType::Sand => &constants::SAND_YELLOW_RGB,
+            TerrainType::Grass => &constants::GRASS_GREEN_RGB,
+            TerrainType::Dirt => &constants::DIRT_BROWN_RGB,
+            TerrainType::ForestFloor => &constants::FOREST_GREEN_RGB,
+            TerrainType::Rock => &constants::ROCK_GRAY_RGB,
+            TerrainType::Snow => &constants::SNOW_WHITE_RGB,
+        }
+    }
 }
@@ -1,53 +1,273 @@
-use super::{Entity, Gor, LocationService, Services};
+use super::{Entity, EntityMessage, LocationService, MessageService, Services};
 use crate::event::EventBus;
 use crate::event::*;
-use crate::presenter::{EntityPresenter, PlayerPresenter};
-use crate::view::EntityView;
-use crate::view::PlayerView;
+use crate::model::{IRect, ISize, Rect};
 use futures::pin_mut;
-use std::collections::HashMap;
 use std::collections::HashSet;
+use std::future::Future;
+use tokio::select;
 use tokio::stream::StreamExt;
 
+/// Number of tiles around the viewport where entities keep their views, so
+/// entities at the edges are drawn before they come into sight
+const VIEWPORT_MARGIN: usize = 2;
+
+/// Tells entities when they enter or leave the viewport so that only the
+/// entities near the viewport hold views
 pub struct ViewService {
-    presenters:
-        HashMap<Entity, Box<dyn EntityPresenter<View = dyn EntityView>>>,
     event_bus: EventBus,
     location_service: LocationService,
+    message_service: MessageService,
+    entities_in_viewport: HashSet<Entity>,
 }
 
 impl ViewService {
-    pub fn new(
-        services: Services,
-        event_bus: EventBus,
-        presenters: HashMap<
-            Entity,
-            Box<dyn EntityPresenter<View = dyn EntityView>>,
-        >,
-    ) -> ViewService {
+    pub fn new(services: Services, event_bus: EventBus) -> ViewService {
         ViewService {
-            presenters,
             event_bus,
             location_service: services.location_service(),
+            message_service: services.message_service(),
+            entities_in_viewport: HashSet::new(),
+        }
+    }
+
+    /// Run until the game stops. The entities in the viewport are checked
+    /// again whenever the viewport changes or a location changes inside it.
+    /// Both are subscribed to when this is called rather than when the
+    /// returned future is first polled, so the first layout can't be missed
+    pub fn run(mut self) -> impl Future<Output = ()> {
+        let end_event = self.event_bus.register_for_one::<StopGameRequested>();
+        let (registration, update_stream) =
+            self.event_bus.register_to_watch::<ViewportChange>();
+        let mut location_changes = self.location_service.subscribe();
+
+        async move {
+            let _registration = registration;
+
+            pin_mut!(update_stream);
+            pin_mut!(end_event);
+
+            let mut tile_rect = None;
+
+            loop {
+                select! {
+                    event_opt = update_stream.next() => match event_opt {
+                        Some(ViewportChange { new_viewport }) => {
+                            tile_rect =
+                                Some(get_tile_rect(&new_viewport.viewport_rect));
+                        }
+                        None => break,
+                    },
+                    Some(changed) = location_changes.recv() => {
+                        let in_view = tile_rect
+                            .as_ref()
+                            .and_then(|rect| rect.intersection(&changed))
+                            .is_some();
+
+                        if !in_view {
+                            continue;
+                        }
+                    },
+                    _ = &mut end_event => break
+                }
+
+                if let Some(tile_rect) = &tile_rect {
+                    self.update_entities_in_viewport(tile_rect).await;
+                }
+            }
+
+            info!("View service stopped");
         }
     }
 
-    pub async fn run(mut self) {
-        let (_registration, update_stream) =
-            self.event_bus.register::<ViewportChange>();
+    /// Send the entities that came into or went out of the viewport messages
+    /// telling them so. Entities that were despawned are dropped without a
+    /// message
+    async fn update_entities_in_viewport(&mut self, tile_rect: &IRect) {
+        let entities_in_viewport = self
+            .location_service
+            .get_entities_in_rect(tile_rect, None)
+            .await
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        for entity in
+            self.entities_in_viewport.difference(&entities_in_viewport)
+        {
+            self.message_service
+                .send_message(entity, EntityMessage::ExitedViewport)
+                .await;
+        }
+
+        for entity in
+            entities_in_viewport.difference(&self.entities_in_viewport)
+        {
+            self.message_service
+                .send_message(entity, EntityMessage::EnteredViewport)
+                .await;
+        }
+
+        self.entities_in_viewport = entities_in_viewport;
+    }
+}
+
+/// Get the tiles covered by the given viewport rect along with the margin
+/// around it
+fn get_tile_rect(viewport_rect: &Rect) -> IRect {
+    let top_left = viewport_rect.top_left.floor();
+    let bottom_right = viewport_rect.bottom_right().floor();
+
+    IRect {
+        top_left,
+        size: ISize::new(
+            (bottom_right.x - top_left.x + 1) as usize,
+            (bottom_right.y - top_left.y + 1) as usize,
+        ),
+    }
+    .expanded_by(VIEWPORT_MARGIN)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::application_context::Ao;
+    use crate::game::{EntityType, SavedGame};
+    use crate::model::IPoint;
+    use crate::presenter::PlayerPresenterState;
+    use crate::ui::ViewportInfo;
+    use std::time::Duration;
+    use tokio::runtime::{Builder, Runtime};
+    use tokio::sync::mpsc::Receiver;
+    use tokio::time::timeout;
+
+    /// Get the next message sent to an entity, if one arrives in time
+    async fn next_message(
+        receiver: &mut Receiver<EntityMessage>,
+    ) -> Option<EntityMessage> {
+        timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    fn create_runtime() -> Box<Runtime> {
+        Box::new(
+            Builder::new_multi_thread()
+                .worker_threads(1)
+                .enable_time()
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_refreshes_entities_in_viewport() {
+        let app_runtime = create_runtime();
+        let (event_bus, event_bus_dropper) =
+            EventBus::new(Ao::new(&app_runtime));
+
+        let (services, run_bundles, droppers) = Services::new(
+            create_runtime(),
+            SavedGame::new(42),
+            &Default::default(),
+        );
+
+        app_runtime.block_on(async {
+            let mut player_bundle = run_bundles.into_iter().next().unwrap();
+
+            let view_service_handle = event_bus.spawn(
+                ViewService::new(services.clone(), event_bus.clone()).run(),
+            );
+
+            let mut viewport = ViewportInfo::default();
+            viewport.viewport_rect = Rect::new(0., 0., 10., 10.);
+
+            event_bus.post(ViewportChange {
+                new_viewport: viewport,
+            });
 
-        pin_mut!(update_stream);
+            assert!(matches!(
+                next_message(&mut player_bundle.entity_message_source).await,
+                Some(EntityMessage::EnteredViewport)
+            ));
 
-        let mut viewport = if let Some(viewport) = update_stream.next().await {
-            viewport
-        } else {
-            return;
-        };
+            // Entities spawned in the viewport are noticed without the
+            // viewport changing
+            let mut spawned_bundle = services
+                .spawn(
+                    EntityType::Player,
+                    IPoint::new(5, 5),
+                    PlayerPresenterState::default(),
+                )
+                .await;
 
-        //let mut entities_in_viewport = HashSet::new();
+            assert!(matches!(
+                next_message(&mut spawned_bundle.entity_message_source).await,
+                Some(EntityMessage::EnteredViewport)
+            ));
 
-        while let Some(event) = update_stream.next().await {
-            let viewport = event.new_viewport;
+            // And so are entities that move out of it
+            services
+                .location_service()
+                .move_by_key(
+                    &spawned_bundle.entity_data.location_key.unwrap(),
+                    IPoint::new(50, 50),
+                )
+                .await;
+
+            assert!(matches!(
+                next_message(&mut spawned_bundle.entity_message_source).await,
+                Some(EntityMessage::ExitedViewport)
+            ));
+
+            // Despawned entities are dropped without being sent anything,
+            // and the service keeps going
+            let mut despawned_bundle = services
+                .spawn(
+                    EntityType::Player,
+                    IPoint::new(6, 6),
+                    PlayerPresenterState::default(),
+                )
+                .await;
+
+            assert!(matches!(
+                next_message(&mut despawned_bundle.entity_message_source).await,
+                Some(EntityMessage::EnteredViewport)
+            ));
+            assert!(services.despawn(&despawned_bundle.entity).await);
+            assert!(next_message(&mut despawned_bundle.entity_message_source)
+                .await
+                .is_none());
+
+            let player = services.entity_service().get_player();
+
+            services
+                .location_service()
+                .move_by_key(&player.location_key, IPoint::new(-50, -50))
+                .await;
+
+            assert!(matches!(
+                next_message(&mut player_bundle.entity_message_source).await,
+                Some(EntityMessage::ExitedViewport)
+            ));
+
+            // The service has to stop before the services are dropped
+            event_bus.post(StopGameRequested {});
+            assert!(view_service_handle.await.is_ok());
+        });
+
+        for dropper in droppers {
+            dropper();
         }
+
+        event_bus_dropper();
+    }
+
+    #[test]
+    fn test_get_tile_rect() {
+        assert_eq!(
+            IRect::new(-4, -2, 8, 7),
+            get_tile_rect(&Rect::new(-1.5, 0.25, 3., 2.5))
+        );
     }
 }
@@ -0,0 +1,28 @@
+// In-memory implementation of the view types for running the presenters
+// without a native ui
+
+pub use self::headless_animation::HeadlessAnimation;
+pub use self::headless_native_view::HeadlessNativeView;
+pub use self::headless_renderer::HeadlessRenderer;
+pub use self::headless_resource_loader::HeadlessResourceLoader;
+pub use self::headless_scene::*;
+pub use self::headless_sprite::HeadlessSprite;
+pub use self::headless_sprite_group::HeadlessSpriteGroup;
+pub use self::headless_system_interop::HeadlessSystemInterop;
+pub use self::headless_texture::HeadlessTexture;
+pub use self::headless_transition_service::HeadlessTransitionService;
+pub use self::headless_view_types::HeadlessViewTypes;
+pub use self::headless_viewport::HeadlessViewport;
+
+mod headless_animation;
+mod headless_native_view;
+mod headless_renderer;
+mod headless_resource_loader;
+mod headless_scene;
+mod headless_sprite;
+mod headless_sprite_group;
+mod headless_system_interop;
+mod headless_texture;
+mod headless_transition_service;
+mod headless_view_types;
+mod headless_viewport;
@@ -0,0 +1,24 @@
+use super::HeadlessTexture;
+use crate::native::Animation;
+
+/// Animation that ignores the frames and settings it's given. Headless sprites
+/// show the final state of every animated change right away, so there's
+/// nothing to play
+#[derive(Debug, Clone, Default)]
+pub struct HeadlessAnimation;
+
+impl HeadlessAnimation {
+    pub fn new() -> HeadlessAnimation {
+        Default::default()
+    }
+}
+
+impl Animation for HeadlessAnimation {
+    type Texture = HeadlessTexture;
+
+    fn add_texture(&self, _texture: &Self::Texture) {}
+
+    fn set_is_loop(&self, _is_loop: bool) {}
+
+    fn set_name(&self, _name: String) {}
+}
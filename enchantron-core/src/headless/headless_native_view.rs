@@ -0,0 +1,195 @@
+use super::{
+    HeadlessScene, HeadlessSprite, HeadlessSpriteGroup, HeadlessTexture,
+    HeadlessViewport,
+};
+use crate::ui::{
+    HasLayoutHandlers, HasMagnifyHandlers, HasMultiTouchHandlers, HasViewport,
+    LayoutHandler, MagnifyHandler, MultiTouchHandler, RustHandlerRegistration,
+    SpriteSource,
+};
+use crate::util::BoxedAny;
+use crate::view::NativeView;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct Handlers {
+    next_id: u64,
+    layout: Vec<(u64, LayoutHandler)>,
+    multi_touch: Vec<(u64, MultiTouchHandler)>,
+    magnify: Vec<(u64, MagnifyHandler)>,
+}
+
+/// Native view backed by an in-memory scene. Clones share the same scene,
+/// handlers, and presenter, so a test can keep a copy of the view it hands to
+/// a presenter and drive it with synthetic ui events
+#[derive(Clone)]
+pub struct HeadlessNativeView {
+    scene: HeadlessScene,
+    handlers: Arc<Mutex<Handlers>>,
+    presenter: Arc<Mutex<Option<BoxedAny>>>,
+}
+
+macro_rules! add_handler {
+    ($self:ident, $list:ident, $handler:ident) => {{
+        let id = {
+            let mut handlers = $self.handlers.lock().unwrap();
+            let id = handlers.next_id;
+            handlers.next_id += 1;
+            handlers.$list.push((id, $handler));
+            id
+        };
+
+        let handlers = $self.handlers.clone();
+
+        RustHandlerRegistration::new(move || {
+            handlers
+                .lock()
+                .unwrap()
+                .$list
+                .retain(|(h_id, _)| *h_id != id)
+        })
+    }};
+}
+
+impl HeadlessNativeView {
+    pub fn new() -> HeadlessNativeView {
+        HeadlessNativeView {
+            scene: HeadlessScene::new(),
+            handlers: Default::default(),
+            presenter: Default::default(),
+        }
+    }
+
+    pub fn scene(&self) -> &HeadlessScene {
+        &self.scene
+    }
+
+    /// Check if the view currently holds a presenter
+    pub fn has_presenter(&self) -> bool {
+        self.presenter.lock().unwrap().is_some()
+    }
+
+    /// Send a layout event to all the registered layout handlers
+    pub fn layout(&self, width: f64, height: f64, scale: f64) {
+        self.handlers
+            .lock()
+            .unwrap()
+            .layout
+            .iter()
+            .for_each(|(_, h)| h.on_layout(width, height, scale));
+    }
+
+    /// Send a magnify event to all the registered magnify handlers
+    pub fn magnify(&self, scale_change_additive: f64, x: f64, y: f64) {
+        self.handlers
+            .lock()
+            .unwrap()
+            .magnify
+            .iter()
+            .for_each(|(_, h)| h.on_magnify(scale_change_additive, x, y));
+    }
+
+    /// Send the start of a single touch to all the registered touch handlers
+    pub fn touch_start(&self, touch_id: i64, x: f64, y: f64, clicks: i64) {
+        self.handlers
+            .lock()
+            .unwrap()
+            .multi_touch
+            .iter()
+            .for_each(|(_, h)| h.on_one_drag_start(touch_id, x, y, clicks));
+    }
+
+    /// Send the movement of a single touch to all the registered touch
+    /// handlers
+    pub fn touch_move(&self, touch_id: i64, x: f64, y: f64, clicks: i64) {
+        self.handlers
+            .lock()
+            .unwrap()
+            .multi_touch
+            .iter()
+            .for_each(|(_, h)| h.on_one_drag_move(touch_id, x, y, clicks));
+    }
+
+    /// Send the end of a single touch to all the registered touch handlers
+    pub fn touch_end(&self, touch_id: i64, x: f64, y: f64, clicks: i64) {
+        self.handlers
+            .lock()
+            .unwrap()
+            .multi_touch
+            .iter()
+            .for_each(|(_, h)| h.on_one_drag_end(touch_id, x, y, clicks));
+    }
+
+    /// Send a complete single-click tap at the given screen point
+    pub fn tap(&self, touch_id: i64, x: f64, y: f64) {
+        self.touch_start(touch_id, x, y, 1);
+        self.touch_end(touch_id, x, y, 1);
+    }
+}
+
+impl Default for HeadlessNativeView {
+    fn default() -> Self {
+        HeadlessNativeView::new()
+    }
+}
+
+impl SpriteSource for HeadlessNativeView {
+    type T = HeadlessTexture;
+    type S = HeadlessSprite;
+    type G = HeadlessSpriteGroup;
+
+    fn create_sprite(&self) -> Self::S {
+        HeadlessSprite::new(self.scene.clone(), self.scene.world_root())
+    }
+
+    fn create_group(&self) -> Self::G {
+        HeadlessSpriteGroup::new(self.scene.clone(), self.scene.world_root())
+    }
+}
+
+impl HasViewport for HeadlessNativeView {
+    type V = HeadlessViewport;
+
+    fn get_viewport(&self) -> Self::V {
+        HeadlessViewport::new(self.scene.clone())
+    }
+}
+
+impl HasLayoutHandlers for HeadlessNativeView {
+    type R = RustHandlerRegistration;
+
+    fn add_layout_handler(&self, handler: LayoutHandler) -> Self::R {
+        add_handler!(self, layout, handler)
+    }
+}
+
+impl HasMultiTouchHandlers for HeadlessNativeView {
+    type R = RustHandlerRegistration;
+
+    fn add_multi_touch_handler(&self, handler: MultiTouchHandler) -> Self::R {
+        add_handler!(self, multi_touch, handler)
+    }
+}
+
+impl HasMagnifyHandlers for HeadlessNativeView {
+    type R = RustHandlerRegistration;
+
+    fn add_magnify_handler(&self, handler: MagnifyHandler) -> Self::R {
+        add_handler!(self, magnify, handler)
+    }
+}
+
+impl NativeView for HeadlessNativeView {
+    fn unset_presenter(&self) {
+        let presenter = self.presenter.lock().unwrap().take();
+
+        // drop outside the lock in case the presenter reacts to being dropped
+        drop(presenter);
+    }
+
+    fn set_presenter(&self, presenter: BoxedAny) {
+        let previous = self.presenter.lock().unwrap().replace(presenter);
+
+        drop(previous);
+    }
+}
@@ -0,0 +1,398 @@
+use super::{HeadlessScene, Layer, SpriteSnapshot, ViewportState};
+use crate::model::{ISize, Rect};
+use crate::ui::{Color, Rgba32Color};
+
+/// Get the blend of the given source color over the given destination color
+fn blend(dst: &mut [u8], src: [u8; 4]) {
+    let alpha = src[3] as u32;
+
+    if alpha == 0 {
+        return;
+    }
+
+    for i in 0..3 {
+        dst[i] = ((src[i] as u32 * alpha + dst[i] as u32 * (255 - alpha) + 127)
+            / 255) as u8;
+    }
+}
+
+/// Multiply the given RGBA value by the given tint color
+fn tint(rgba: [u8; 4], color: &Rgba32Color) -> [u8; 4] {
+    let mul = |v: u8, c: u8| ((v as u32 * c as u32 + 127) / 255) as u8;
+
+    [
+        mul(rgba[0], color.red()),
+        mul(rgba[1], color.green()),
+        mul(rgba[2], color.blue()),
+        mul(rgba[3], color.alpha()),
+    ]
+}
+
+/// Get the rect the given sprite covers on the screen in points
+fn get_screen_rect(sprite: &SpriteSnapshot, viewport: &ViewportState) -> Rect {
+    match sprite.layer {
+        Layer::Screen => Rect {
+            top_left: sprite.location,
+            size: sprite.size,
+        },
+        Layer::World => Rect::new(
+            (sprite.location.x - viewport.location.x) / viewport.scale,
+            (sprite.location.y - viewport.location.y) / viewport.scale,
+            sprite.size.width / viewport.scale,
+            sprite.size.height / viewport.scale,
+        ),
+    }
+}
+
+/// CPU rasterizer for headless scenes. Sprites are drawn by layer and z-level
+/// with their location as the top-left corner, textures are sampled with
+/// nearest-neighbor filtering, and sprite colors tint their textures (or fill
+/// the sprite if it has no texture). World sprites are transformed by the
+/// viewport where one screen point covers `viewport.scale` world units
+pub struct HeadlessRenderer {
+    size: ISize,
+    points_per_pixel: f64,
+    background: [u8; 3],
+    max_z_level: f64,
+}
+
+impl HeadlessRenderer {
+    /// Create a renderer that produces images of the given size in pixels
+    /// with one pixel per screen point
+    pub fn new(size: ISize) -> HeadlessRenderer {
+        HeadlessRenderer {
+            size,
+            points_per_pixel: 1.,
+            background: [0, 0, 0],
+            max_z_level: f64::INFINITY,
+        }
+    }
+
+    /// Set the number of screen points each pixel covers
+    pub fn with_points_per_pixel(mut self, points_per_pixel: f64) -> Self {
+        self.points_per_pixel = points_per_pixel;
+        self
+    }
+
+    /// Set the color of pixels no sprite is drawn over
+    #[cfg(test)]
+    pub fn with_background(mut self, background: [u8; 3]) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Only draw sprites with a z-level below the given value
+    pub fn with_max_z_level(mut self, max_z_level: f64) -> Self {
+        self.max_z_level = max_z_level;
+        self
+    }
+
+    /// Rasterize the given scene into tightly packed RGB pixel data
+    pub fn render(&self, scene: &HeadlessScene) -> Vec<u8> {
+        let mut data = Vec::<u8>::with_capacity(self.size.area() * 3);
+
+        for _ in 0..self.size.area() {
+            data.extend_from_slice(&self.background);
+        }
+
+        let viewport = scene.viewport();
+
+        for sprite in scene
+            .visible_sprites()
+            .iter()
+            .filter(|s| s.z_level < self.max_z_level)
+        {
+            self.draw_sprite(&mut data, sprite, &viewport);
+        }
+
+        data
+    }
+
+    fn draw_sprite(
+        &self,
+        data: &mut [u8],
+        sprite: &SpriteSnapshot,
+        viewport: &ViewportState,
+    ) {
+        if sprite.texture.is_none() && sprite.color.is_none() {
+            return;
+        }
+
+        let screen_rect = get_screen_rect(sprite, viewport);
+
+        if screen_rect.size.width <= 0. || screen_rect.size.height <= 0. {
+            return;
+        }
+
+        let ppp = self.points_per_pixel;
+
+        // Pixels are drawn if their centers are within the sprite
+        let to_pixel_range = |start: f64, length: f64, max: usize| {
+            let first = (start / ppp - 0.5).ceil().max(0.) as usize;
+            let last = ((start + length) / ppp - 0.5).ceil().max(0.) as usize;
+
+            first.min(max)..last.min(max)
+        };
+
+        let x_range = to_pixel_range(
+            screen_rect.top_left.x,
+            screen_rect.size.width,
+            self.size.width,
+        );
+        let y_range = to_pixel_range(
+            screen_rect.top_left.y,
+            screen_rect.size.height,
+            self.size.height,
+        );
+
+        for y in y_range {
+            let v = ((y as f64 + 0.5) * ppp - screen_rect.top_left.y)
+                / screen_rect.size.height;
+
+            for x in x_range.clone() {
+                let u = ((x as f64 + 0.5) * ppp - screen_rect.top_left.x)
+                    / screen_rect.size.width;
+
+                let src = match &sprite.texture {
+                    Some(texture) => {
+                        let rect = texture.rect();
+                        let rgba = texture.get_pixel(
+                            (u * rect.size.width) as usize,
+                            (v * rect.size.height) as usize,
+                        );
+
+                        match &sprite.color {
+                            Some(color) => tint(rgba, color),
+                            None => rgba,
+                        }
+                    }
+                    None => {
+                        let color = sprite.color.unwrap_or_default();
+                        [
+                            color.red(),
+                            color.green(),
+                            color.blue(),
+                            color.alpha(),
+                        ]
+                    }
+                };
+
+                let offset = (y * self.size.width + x) * 3;
+                blend(&mut data[offset..offset + 3], src);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::application_context::Ao;
+    use crate::game::{constants, TerrainConfig};
+    use crate::headless::{
+        HeadlessNativeView, HeadlessResourceLoader, HeadlessTexture,
+        HeadlessViewTypes,
+    };
+    use crate::img::{PngDecoder, PngGenerator};
+    use crate::model::IRect;
+    use crate::native::{Animations, RuntimeResources, Texture, Textures};
+    use crate::ui::{
+        HasMutableColor, HasMutableLocation, HasMutableScale, HasMutableSize,
+        HasMutableVisibility, HasMutableZLevel, HasViewport, Sprite,
+        SpriteSource, TerrainTextureProvider,
+    };
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    const GOLDEN_DIR: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/test_data/golden");
+
+    /// Compare the given rendered rgb data to the golden image with the given
+    /// name. Run with UPDATE_GOLDEN set to regenerate the golden images
+    fn assert_matches_golden(name: &str, size: &ISize, rgb: &[u8]) {
+        let path = Path::new(GOLDEN_DIR).join(name);
+        let mut png = Vec::new();
+
+        PngGenerator::get_png(rgb, size, &mut png);
+
+        if env::var("UPDATE_GOLDEN").is_ok() {
+            fs::create_dir_all(GOLDEN_DIR).unwrap();
+            fs::write(&path, &png).unwrap();
+            return;
+        }
+
+        let golden_png = fs::read(&path).unwrap_or_else(|_| {
+            panic!("Missing golden image {:?}, run with UPDATE_GOLDEN=1", path)
+        });
+
+        let (golden_size, golden_rgba) = PngDecoder::get_rgba(&golden_png);
+
+        let matches = golden_size == *size
+            && golden_rgba
+                .chunks_exact(4)
+                .zip(rgb.chunks_exact(3))
+                .all(|(expected, actual)| &expected[..3] == actual);
+
+        if !matches {
+            let actual_path = format!("target/golden_actual_{}", name);
+            fs::write(&actual_path, &png).unwrap();
+            panic!("Render doesn't match {:?}, see {}", path, actual_path);
+        }
+    }
+
+    /// Create a 4x4 texture with a different color in each 2x2 quadrant
+    fn create_quadrant_texture() -> HeadlessTexture {
+        let quadrants = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 255],
+            [255, 255, 255, 128],
+        ];
+
+        let mut rgba = Vec::new();
+
+        for y in 0..4 {
+            for x in 0..4 {
+                rgba.extend_from_slice(&quadrants[(y / 2) * 2 + x / 2]);
+            }
+        }
+
+        HeadlessTexture::from_rgba(
+            "quadrants".to_owned(),
+            ISize::new(4, 4),
+            rgba,
+        )
+    }
+
+    #[test]
+    fn test_blend_and_z_order() {
+        let view = HeadlessNativeView::new();
+        let texture = create_quadrant_texture();
+
+        let top = view.create_sprite();
+        top.set_texture(&texture.get_sub_texture(2., 0., 2., 2.));
+        top.set_rect(&Rect::new(0., 0., 2., 2.));
+        top.set_z_level(2.);
+
+        let bottom = view.create_sprite();
+        bottom.set_texture(&texture);
+        bottom.set_rect(&Rect::new(0., 0., 4., 4.));
+        bottom.set_z_level(1.);
+
+        let hidden = view.create_sprite();
+        hidden.set_8_bit_color(255, 255, 255, 255);
+        hidden.set_rect(&Rect::new(0., 0., 4., 4.));
+        hidden.set_z_level(3.);
+        hidden.set_visible(false);
+
+        let rgb = HeadlessRenderer::new(ISize::new(4, 4))
+            .with_background([0, 0, 0])
+            .render(view.scene());
+
+        let pixel = |x: usize, y: usize| {
+            let offset = (y * 4 + x) * 3;
+            [rgb[offset], rgb[offset + 1], rgb[offset + 2]]
+        };
+
+        // the green quadrant of the top sprite covers the red quadrant
+        assert_eq!([0, 255, 0], pixel(0, 0));
+        assert_eq!([0, 255, 0], pixel(1, 1));
+        assert_eq!([0, 255, 0], pixel(2, 0));
+        assert_eq!([0, 0, 255], pixel(0, 2));
+
+        // half transparent white over the black background
+        assert_eq!([128, 128, 128], pixel(3, 3));
+    }
+
+    #[test]
+    fn test_golden_synthetic_scene() {
+        let view = HeadlessNativeView::new();
+        let texture = create_quadrant_texture();
+
+        let background = view.create_sprite();
+        background.set_8_bit_color(40, 40, 60, 255);
+        background.set_rect(&Rect::new(-1., -1., 16., 12.));
+
+        let full = view.create_sprite();
+        full.set_texture(&texture);
+        full.set_rect(&Rect::new(1., 1., 4., 4.));
+        full.set_z_level(1.);
+
+        let tinted = view.create_sprite();
+        tinted.set_texture(&texture.get_sub_texture(2., 2., 2., 2.));
+        tinted.set_rect(&Rect::new(3., 3., 6., 3.));
+        tinted.set_8_bit_color(255, 64, 64, 200);
+        tinted.set_z_level(2.);
+
+        let group = view.create_group();
+        group.set_z_level(5.);
+        let grouped = group.create_sprite();
+        grouped.set_texture(&texture.get_sub_texture(0., 2., 2., 2.));
+        grouped.set_rect(&Rect::new(9., 1., 2., 2.));
+        grouped.set_z_level(-3.);
+
+        let hidden_group = view.create_group();
+        hidden_group.set_visible(false);
+        let hidden = hidden_group.create_sprite();
+        hidden.set_8_bit_color(255, 255, 0, 255);
+        hidden.set_rect(&Rect::new(0., 0., 16., 12.));
+
+        let viewport = view.get_viewport();
+        viewport.set_scale_and_location(0.25, -1., -1.);
+
+        let screen_locked = viewport.create_sprite();
+        screen_locked.set_8_bit_color(255, 200, 0, 255);
+        screen_locked.set_rect(&Rect::new(52., 36., 10., 10.));
+
+        let size = ISize::new(64, 48);
+        let rgb = HeadlessRenderer::new(size).render(view.scene());
+
+        assert_matches_golden("synthetic_scene.png", &size, &rgb);
+    }
+
+    #[test]
+    fn test_golden_terrain_and_player() {
+        let resource_loader = HeadlessResourceLoader::default();
+        let textures =
+            Textures::<HeadlessViewTypes>::new(&resource_loader, &|_| {});
+        let animations = Animations::new(&resource_loader, &textures);
+        let runtime_resources = Box::new(RuntimeResources::new(
+            textures,
+            animations,
+            TerrainConfig::load(&resource_loader),
+        ));
+
+        let mut terrain_texture_provider = TerrainTextureProvider::new(
+            Ao::new(&runtime_resources),
+            resource_loader.clone(),
+            0,
+            Default::default(),
+        );
+
+        let view = HeadlessNativeView::new();
+
+        let terrain_rect = IRect::new(-16, -16, 32, 32);
+        let terrain = view.create_sprite();
+        terrain.set_texture(
+            &terrain_texture_provider
+                .get_texture_for_rect(&terrain_rect, &terrain_rect.size),
+        );
+        terrain.set_location(-16., -16.);
+        terrain.set_size(32., 32.);
+        terrain.set_z_level(constants::TERRAIN_Z_LEVEL);
+
+        let player = view.create_sprite();
+        player.set_texture(runtime_resources.textures().character.south_rest());
+        player.set_location(0.5, -0.125);
+        player.set_size(1., 2.);
+        player.set_z_level(constants::ENTITY_Z_LEVEL);
+
+        view.get_viewport().set_scale_and_location(0.25, -16., -16.);
+
+        let size = ISize::new(128, 128);
+        let rgb = HeadlessRenderer::new(size).render(view.scene());
+
+        assert_matches_golden("terrain_and_player.png", &size, &rgb);
+    }
+}
@@ -0,0 +1,105 @@
+use super::{HeadlessAnimation, HeadlessTexture};
+use crate::model::ISize;
+use crate::native::{PixelFormat, ResourceLoader};
+use crate::util::ByteBuffer;
+use std::fs;
+use std::path::PathBuf;
+
+const DEFAULT_TEXTURE_DIR: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/textures");
+const DEFAULT_CONFIG_DIR: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/config");
+
+/// Resource loader that reads textures and text resources from directories on
+/// disk
+#[derive(Debug, Clone)]
+pub struct HeadlessResourceLoader {
+    texture_dir: PathBuf,
+    config_dir: PathBuf,
+}
+
+impl HeadlessResourceLoader {
+    pub fn new(texture_dir: PathBuf) -> HeadlessResourceLoader {
+        HeadlessResourceLoader {
+            texture_dir,
+            config_dir: PathBuf::from(DEFAULT_CONFIG_DIR),
+        }
+    }
+
+    /// Read text resources from the given directory instead of the resources
+    /// directory in the repository
+    #[cfg(test)]
+    pub fn with_config_dir(
+        self,
+        config_dir: PathBuf,
+    ) -> HeadlessResourceLoader {
+        HeadlessResourceLoader { config_dir, ..self }
+    }
+}
+
+impl Default for HeadlessResourceLoader {
+    /// Load textures from the resources directory in the repository
+    fn default() -> Self {
+        HeadlessResourceLoader::new(PathBuf::from(DEFAULT_TEXTURE_DIR))
+    }
+}
+
+impl ResourceLoader for HeadlessResourceLoader {
+    type T = HeadlessTexture;
+    type A = HeadlessAnimation;
+
+    fn load_texture(&self, name: String) -> Self::T {
+        let path = self.texture_dir.join(&name);
+
+        let png_data = fs::read(&path).unwrap_or_else(|e| {
+            error!("Failed to read texture {:?}: {:?}", path, e);
+            panic!("Failed to read texture");
+        });
+
+        HeadlessTexture::from_png_data(name, &png_data)
+    }
+
+    fn load_texture_from_png_data(&self, png_data: ByteBuffer) -> Self::T {
+        HeadlessTexture::from_png_data("png_data".to_owned(), &png_data)
+    }
+
+    fn load_texture_from_pixels(
+        &self,
+        pixel_data: ByteBuffer,
+        palette: ByteBuffer,
+        width: i64,
+        height: i64,
+        pixel_format: i64,
+    ) -> Self::T {
+        let pixel_format =
+            PixelFormat::from_code(pixel_format).unwrap_or_else(|| {
+                error!("Unknown pixel format {}", pixel_format);
+                panic!("Unknown pixel format");
+            });
+
+        HeadlessTexture::from_pixels(
+            "pixel_data".to_owned(),
+            ISize::new(width as usize, height as usize),
+            &pixel_data,
+            &palette,
+            pixel_format,
+        )
+    }
+
+    fn load_text(&self, name: String) -> String {
+        let path = self.config_dir.join(&name);
+
+        fs::read_to_string(&path).unwrap_or_else(|e| {
+            info!("Failed to read text resource {:?}: {:?}", path, e);
+            String::new()
+        })
+    }
+
+    fn get_texture_path(&self, name: String) -> String {
+        self.texture_dir.join(&name).to_string_lossy().into_owned()
+    }
+
+    fn create_animation(&self) -> Self::A {
+        HeadlessAnimation::new()
+    }
+}
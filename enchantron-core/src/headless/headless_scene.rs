@@ -0,0 +1,270 @@
+use super::HeadlessTexture;
+use crate::model::{Point, Size};
+use crate::ui::Rgba32Color;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+pub type NodeId = u64;
+
+const WORLD_ROOT: NodeId = 0;
+const SCREEN_ROOT: NodeId = 1;
+
+/// The two trees that make up a scene. World nodes are transformed by the
+/// viewport, and screen nodes are locked to the view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    World,
+    Screen,
+}
+
+/// Location and scale of the viewport into the world layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewportState {
+    pub location: Point,
+    pub scale: f64,
+}
+
+impl Default for ViewportState {
+    fn default() -> Self {
+        ViewportState {
+            location: Point::default(),
+            scale: 1.,
+        }
+    }
+}
+
+/// The drawable content of a sprite node
+#[derive(Debug, Clone, Default)]
+pub(super) struct SpriteContent {
+    pub(super) location: Point,
+    pub(super) size: Size,
+    pub(super) color: Option<Rgba32Color>,
+    pub(super) texture: Option<HeadlessTexture>,
+}
+
+#[derive(Debug)]
+pub(super) struct Node {
+    parent: Option<NodeId>,
+    layer: Layer,
+    pub(super) z_level: f64,
+    pub(super) visible: bool,
+    pub(super) sprite: Option<SpriteContent>,
+}
+
+/// Copy of the state of a sprite attached to the scene. The z-level and
+/// visibility take all the sprite's ancestor groups into account
+#[derive(Debug, Clone)]
+pub struct SpriteSnapshot {
+    pub id: NodeId,
+    pub layer: Layer,
+    pub location: Point,
+    pub size: Size,
+    pub z_level: f64,
+    pub visible: bool,
+    pub color: Option<Rgba32Color>,
+    pub texture: Option<HeadlessTexture>,
+}
+
+#[derive(Debug)]
+struct SceneGraph {
+    next_id: NodeId,
+    nodes: BTreeMap<NodeId, Node>,
+    viewport: ViewportState,
+}
+
+/// In-memory scene graph shared by all the sprites, groups, and the viewport
+/// of a single headless view
+#[derive(Debug, Clone)]
+pub struct HeadlessScene(Arc<Mutex<SceneGraph>>);
+
+impl HeadlessScene {
+    pub fn new() -> HeadlessScene {
+        let mut nodes = BTreeMap::new();
+
+        for &(id, layer) in
+            &[(WORLD_ROOT, Layer::World), (SCREEN_ROOT, Layer::Screen)]
+        {
+            nodes.insert(
+                id,
+                Node {
+                    parent: None,
+                    layer,
+                    z_level: 0.,
+                    visible: true,
+                    sprite: None,
+                },
+            );
+        }
+
+        HeadlessScene(Arc::new(Mutex::new(SceneGraph {
+            next_id: SCREEN_ROOT + 1,
+            nodes,
+            viewport: Default::default(),
+        })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SceneGraph> {
+        self.0.lock().unwrap_or_else(|e| {
+            error!("Headless scene lock poisoned: {:?}", e);
+            panic!("Headless scene lock poisoned");
+        })
+    }
+
+    /// Check if the two handles refer to the same scene
+    pub fn ptr_eq(&self, other: &HeadlessScene) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub(super) fn world_root(&self) -> NodeId {
+        WORLD_ROOT
+    }
+
+    pub(super) fn screen_root(&self) -> NodeId {
+        SCREEN_ROOT
+    }
+
+    /// Add a new node under the given parent. If the parent has already been
+    /// removed, the node is created detached and will never be drawn
+    pub(super) fn add_node(&self, parent: NodeId, is_sprite: bool) -> NodeId {
+        let mut graph = self.lock();
+
+        let id = graph.next_id;
+        graph.next_id += 1;
+
+        let layer = graph.nodes.get(&parent).map(|p| p.layer);
+
+        if let Some(layer) = layer {
+            graph.nodes.insert(
+                id,
+                Node {
+                    parent: Some(parent),
+                    layer,
+                    z_level: 0.,
+                    visible: true,
+                    sprite: if is_sprite {
+                        Some(Default::default())
+                    } else {
+                        None
+                    },
+                },
+            );
+        }
+
+        id
+    }
+
+    /// Apply the given update to the node with the given id if it's still
+    /// attached to the scene
+    pub(super) fn update(&self, id: NodeId, updater: impl FnOnce(&mut Node)) {
+        if let Some(node) = self.lock().nodes.get_mut(&id) {
+            updater(node);
+        }
+    }
+
+    /// Apply the given update to the sprite with the given id if it's still
+    /// attached to the scene
+    pub(super) fn update_sprite(
+        &self,
+        id: NodeId,
+        updater: impl FnOnce(&mut SpriteContent),
+    ) {
+        self.update(id, |node| node.sprite.as_mut().map(updater).unwrap_or(()))
+    }
+
+    /// Remove the given node and all its descendants from the scene
+    pub(super) fn remove(&self, id: NodeId) {
+        if id == WORLD_ROOT || id == SCREEN_ROOT {
+            return;
+        }
+
+        let mut graph = self.lock();
+        let mut to_remove = vec![id];
+
+        while let Some(next) = to_remove.pop() {
+            if graph.nodes.remove(&next).is_some() {
+                to_remove.extend(
+                    graph
+                        .nodes
+                        .iter()
+                        .filter(|(_, node)| node.parent == Some(next))
+                        .map(|(child_id, _)| *child_id),
+                );
+            }
+        }
+    }
+
+    pub(super) fn set_viewport(
+        &self,
+        updater: impl FnOnce(&mut ViewportState),
+    ) {
+        updater(&mut self.lock().viewport)
+    }
+
+    /// Get the current location and scale of the viewport
+    pub fn viewport(&self) -> ViewportState {
+        self.lock().viewport
+    }
+
+    /// Get snapshots of all the sprites in the scene in drawing order, which
+    /// is by layer, then by z-level, then by creation order
+    pub fn sprites(&self) -> Vec<SpriteSnapshot> {
+        let graph = self.lock();
+
+        let mut result: Vec<SpriteSnapshot> = graph
+            .nodes
+            .iter()
+            .filter_map(|(id, node)| graph.snapshot(*id, node))
+            .collect();
+
+        result.sort_by(|lhs, rhs| {
+            (lhs.layer == Layer::Screen)
+                .cmp(&(rhs.layer == Layer::Screen))
+                .then(
+                    lhs.z_level
+                        .partial_cmp(&rhs.z_level)
+                        .unwrap_or(std::cmp::Ordering::Equal),
+                )
+                .then(lhs.id.cmp(&rhs.id))
+        });
+
+        result
+    }
+
+    /// Get snapshots of all the visible sprites in the scene in drawing order
+    pub fn visible_sprites(&self) -> Vec<SpriteSnapshot> {
+        self.sprites().into_iter().filter(|s| s.visible).collect()
+    }
+}
+
+impl Default for HeadlessScene {
+    fn default() -> Self {
+        HeadlessScene::new()
+    }
+}
+
+impl SceneGraph {
+    fn snapshot(&self, id: NodeId, node: &Node) -> Option<SpriteSnapshot> {
+        let sprite = node.sprite.as_ref()?;
+
+        let mut z_level = node.z_level;
+        let mut visible = node.visible;
+        let mut parent_opt = node.parent;
+
+        while let Some(parent) = parent_opt.and_then(|p| self.nodes.get(&p)) {
+            z_level += parent.z_level;
+            visible &= parent.visible;
+            parent_opt = parent.parent;
+        }
+
+        Some(SpriteSnapshot {
+            id,
+            layer: node.layer,
+            location: sprite.location,
+            size: sprite.size,
+            z_level,
+            visible,
+            color: sprite.color,
+            texture: sprite.texture.clone(),
+        })
+    }
+}
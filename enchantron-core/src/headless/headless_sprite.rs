@@ -0,0 +1,92 @@
+use super::{HeadlessAnimation, HeadlessScene, HeadlessTexture, NodeId};
+use crate::model::{Point, Size};
+use crate::ui::{
+    HasMutableColor, HasMutableLocation, HasMutableSize, HasMutableVisibility,
+    HasMutableZLevel, Rgba32Color, Sprite,
+};
+
+/// Handle to a sprite node in a headless scene. Animated changes are applied
+/// immediately with their final values. Dropping the handle removes the
+/// sprite from the scene
+#[derive(Debug)]
+pub struct HeadlessSprite {
+    scene: HeadlessScene,
+    id: NodeId,
+}
+
+impl HeadlessSprite {
+    pub(super) fn new(scene: HeadlessScene, parent: NodeId) -> HeadlessSprite {
+        let id = scene.add_node(parent, true);
+
+        HeadlessSprite { scene, id }
+    }
+}
+
+impl HasMutableSize for HeadlessSprite {
+    fn set_size_animated(
+        &self,
+        width: f64,
+        height: f64,
+        _duration_seconds: f64,
+    ) {
+        self.scene
+            .update_sprite(self.id, |s| s.size = Size::new(width, height));
+    }
+}
+
+impl HasMutableLocation for HeadlessSprite {
+    fn set_location_animated(
+        &self,
+        left: f64,
+        top: f64,
+        _duration_seconds: f64,
+    ) {
+        self.scene
+            .update_sprite(self.id, |s| s.location = Point::new(left, top));
+    }
+}
+
+impl HasMutableVisibility for HeadlessSprite {
+    fn set_visible(&self, visible: bool) {
+        self.scene.update(self.id, |n| n.visible = visible);
+    }
+}
+
+impl HasMutableZLevel for HeadlessSprite {
+    fn set_z_level(&self, z_level: f64) {
+        self.scene.update(self.id, |n| n.z_level = z_level);
+    }
+}
+
+impl HasMutableColor for HeadlessSprite {
+    type C = Rgba32Color;
+
+    fn set_color(&self, color: Self::C) {
+        self.scene.update_sprite(self.id, |s| s.color = Some(color));
+    }
+}
+
+impl Sprite for HeadlessSprite {
+    type T = HeadlessTexture;
+    type A = HeadlessAnimation;
+
+    fn set_texture(&self, texture: &Self::T) {
+        let texture = texture.clone();
+        self.scene
+            .update_sprite(self.id, move |s| s.texture = Some(texture));
+    }
+
+    fn remove_from_parent(&self) {
+        self.scene.remove(self.id);
+    }
+
+    fn animate(&self, _animation: &Self::A, _frame_duration_sec: f64) {}
+
+    fn clear_animations(&self) {}
+}
+
+impl Drop for HeadlessSprite {
+    fn drop(&mut self) {
+        self.remove_from_parent();
+    }
+}
@@ -0,0 +1,61 @@
+use super::{HeadlessScene, HeadlessSprite, HeadlessTexture, NodeId};
+use crate::ui::{
+    HasMutableVisibility, HasMutableZLevel, SpriteGroup, SpriteSource,
+};
+
+/// Handle to a group node in a headless scene. Dropping the handle removes the
+/// group and everything in it from the scene
+#[derive(Debug)]
+pub struct HeadlessSpriteGroup {
+    scene: HeadlessScene,
+    id: NodeId,
+}
+
+impl HeadlessSpriteGroup {
+    pub(super) fn new(
+        scene: HeadlessScene,
+        parent: NodeId,
+    ) -> HeadlessSpriteGroup {
+        let id = scene.add_node(parent, false);
+
+        HeadlessSpriteGroup { scene, id }
+    }
+}
+
+impl SpriteSource for HeadlessSpriteGroup {
+    type T = HeadlessTexture;
+    type S = HeadlessSprite;
+    type G = HeadlessSpriteGroup;
+
+    fn create_sprite(&self) -> Self::S {
+        HeadlessSprite::new(self.scene.clone(), self.id)
+    }
+
+    fn create_group(&self) -> Self::G {
+        HeadlessSpriteGroup::new(self.scene.clone(), self.id)
+    }
+}
+
+impl HasMutableVisibility for HeadlessSpriteGroup {
+    fn set_visible(&self, visible: bool) {
+        self.scene.update(self.id, |n| n.visible = visible);
+    }
+}
+
+impl HasMutableZLevel for HeadlessSpriteGroup {
+    fn set_z_level(&self, z_level: f64) {
+        self.scene.update(self.id, |n| n.z_level = z_level);
+    }
+}
+
+impl SpriteGroup for HeadlessSpriteGroup {
+    fn remove_from_parent(&self) {
+        self.scene.remove(self.id);
+    }
+}
+
+impl Drop for HeadlessSpriteGroup {
+    fn drop(&mut self) {
+        self.remove_from_parent();
+    }
+}
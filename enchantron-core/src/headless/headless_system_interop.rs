@@ -0,0 +1,91 @@
+use super::{
+    HeadlessNativeView, HeadlessResourceLoader, HeadlessTexture,
+    HeadlessTransitionService, HeadlessViewTypes,
+};
+use crate::native::SystemInterop;
+use crate::view::{GameViewPublic, LoadingViewPublic, MainMenuViewPublic};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_SAVE_DIRECTORY_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Get a save directory that isn't shared with any other headless interop
+fn unique_temp_save_directory() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "enchantron-saves-{}-{}",
+        process::id(),
+        NEXT_SAVE_DIRECTORY_ID.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// System interop that creates headless views. The composite views spawn
+/// tasks when they are created, so views must be created from within a tokio
+/// runtime. Unless set, saved games go to a fresh temporary directory
+#[derive(Clone)]
+pub struct HeadlessSystemInterop {
+    resource_loader: HeadlessResourceLoader,
+    transition_service: HeadlessTransitionService,
+    save_directory: PathBuf,
+}
+
+impl Default for HeadlessSystemInterop {
+    fn default() -> Self {
+        HeadlessSystemInterop::new(Default::default())
+    }
+}
+
+impl HeadlessSystemInterop {
+    pub fn new(
+        resource_loader: HeadlessResourceLoader,
+    ) -> HeadlessSystemInterop {
+        HeadlessSystemInterop {
+            resource_loader,
+            transition_service: Default::default(),
+            save_directory: unique_temp_save_directory(),
+        }
+    }
+
+    pub fn with_save_directory(mut self, save_directory: PathBuf) -> Self {
+        self.save_directory = save_directory;
+        self
+    }
+}
+
+impl SystemInterop for HeadlessSystemInterop {
+    type T = HeadlessTexture;
+    type TL = HeadlessResourceLoader;
+    type TS = HeadlessTransitionService;
+    type NV = HeadlessNativeView;
+    type LV = LoadingViewPublic<HeadlessViewTypes>;
+    type MV = MainMenuViewPublic<HeadlessViewTypes>;
+    type GV = GameViewPublic<HeadlessViewTypes>;
+
+    fn get_resource_loader(&self) -> Self::TL {
+        self.resource_loader.clone()
+    }
+
+    fn get_transition_service(&self) -> Self::TS {
+        self.transition_service.clone()
+    }
+
+    fn create_native_view(&self) -> Self::NV {
+        HeadlessNativeView::new()
+    }
+
+    fn create_loading_view(&self) -> Self::LV {
+        LoadingViewPublic::new(self.create_native_view())
+    }
+
+    fn create_main_menu_view(&self) -> Self::MV {
+        MainMenuViewPublic::new(self.create_native_view())
+    }
+
+    fn create_game_view(&self) -> Self::GV {
+        GameViewPublic::new(self.create_native_view())
+    }
+
+    fn get_save_directory(&self) -> String {
+        self.save_directory.to_string_lossy().into_owned()
+    }
+}
@@ -0,0 +1,205 @@
+use crate::img::PngDecoder;
+use crate::model::{ISize, Rect};
+use crate::native::{PixelFormat, Texture};
+use crate::ui::HasSize;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::sync::Arc;
+
+struct TextureData {
+    name: String,
+    size: ISize,
+    rgba: Vec<u8>,
+}
+
+/// Texture backed by decoded RGBA pixels. Sub-textures share the pixels of
+/// the texture they were taken from
+#[derive(Clone)]
+pub struct HeadlessTexture {
+    data: Arc<TextureData>,
+    rect: Rect,
+}
+
+impl HeadlessTexture {
+    pub fn from_png_data(name: String, png_data: &[u8]) -> HeadlessTexture {
+        let (size, rgba) = PngDecoder::get_rgba(png_data);
+
+        HeadlessTexture::from_rgba(name, size, rgba)
+    }
+
+    /// Create a texture from uncompressed pixels in the given format. The
+    /// palette is a list of RGB colors and is only used for indexed pixels
+    pub fn from_pixels(
+        name: String,
+        size: ISize,
+        pixel_data: &[u8],
+        palette: &[u8],
+        pixel_format: PixelFormat,
+    ) -> HeadlessTexture {
+        assert_eq!(
+            size.area() * pixel_format.bytes_per_pixel(),
+            pixel_data.len(),
+            "Pixel data size mismatch"
+        );
+
+        let mut rgba = Vec::<u8>::with_capacity(size.area() * 4);
+
+        match pixel_format {
+            PixelFormat::Rgba => rgba.extend_from_slice(pixel_data),
+            PixelFormat::Rgb => pixel_data.chunks_exact(3).for_each(|px| {
+                rgba.extend_from_slice(&[px[0], px[1], px[2], 255])
+            }),
+            PixelFormat::Indexed => pixel_data.iter().for_each(|index| {
+                let color_start = 3 * *index as usize;
+                let color = palette
+                    .get(color_start..color_start + 3)
+                    .unwrap_or_else(|| {
+                        error!("Palette index {} out of range", index);
+                        panic!("Palette index out of range");
+                    });
+
+                rgba.extend_from_slice(color);
+                rgba.push(255);
+            }),
+        }
+
+        HeadlessTexture::from_rgba(name, size, rgba)
+    }
+
+    pub fn from_rgba(
+        name: String,
+        size: ISize,
+        rgba: Vec<u8>,
+    ) -> HeadlessTexture {
+        assert_eq!(size.area() * 4, rgba.len(), "Rgba data size mismatch");
+
+        HeadlessTexture {
+            rect: Rect::new(0., 0., size.width as f64, size.height as f64),
+            data: Arc::new(TextureData { name, size, rgba }),
+        }
+    }
+
+    /// Name of the resource (or generated data) the pixels came from
+    pub fn name(&self) -> &str {
+        &self.data.name
+    }
+
+    /// Region of the full image covered by this texture in pixels
+    pub fn rect(&self) -> &Rect {
+        &self.rect
+    }
+
+    /// Check if this texture is the same region of the same image as the other
+    #[cfg(test)]
+    pub fn same_as(&self, other: &HeadlessTexture) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+            && self.rect.top_left == other.rect.top_left
+            && self.rect.size == other.rect.size
+    }
+
+    /// Get the RGBA value of the pixel at the given coordinates relative to
+    /// the top-left corner of this texture. Coordinates outside the texture
+    /// are clamped to its edge
+    pub fn get_pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let max_x = (self.rect.size.width as usize).max(1) - 1;
+        let max_y = (self.rect.size.height as usize).max(1) - 1;
+
+        let img_x = self.rect.top_left.x as usize + x.min(max_x);
+        let img_y = self.rect.top_left.y as usize + y.min(max_y);
+
+        let offset = (img_y * self.data.size.width + img_x) * 4;
+        let mut result = [0u8; 4];
+        result.copy_from_slice(&self.data.rgba[offset..offset + 4]);
+        result
+    }
+}
+
+impl Debug for HeadlessTexture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("HeadlessTexture")
+            .field("name", &self.data.name)
+            .field("rect", &self.rect)
+            .finish()
+    }
+}
+
+impl HasSize for HeadlessTexture {
+    fn get_width(&self) -> f64 {
+        self.rect.size.width
+    }
+
+    fn get_height(&self) -> f64 {
+        self.rect.size.height
+    }
+}
+
+impl Texture for HeadlessTexture {
+    fn get_sub_texture(
+        &self,
+        left: f64,
+        top: f64,
+        width: f64,
+        height: f64,
+    ) -> Self {
+        HeadlessTexture {
+            data: self.data.clone(),
+            rect: Rect::new(
+                self.rect.top_left.x + left,
+                self.rect.top_left.y + top,
+                width,
+                height,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sub_texture_pixels() {
+        let size = ISize::new(4, 2);
+        let rgba: Vec<u8> = (0..(size.area() * 4) as u8).collect();
+        let texture = HeadlessTexture::from_rgba("test".to_owned(), size, rgba);
+
+        let sub = texture.get_sub_texture(1., 1., 2., 1.);
+
+        assert_eq!(2., sub.get_width());
+        assert_eq!(1., sub.get_height());
+        assert_eq!([20, 21, 22, 23], sub.get_pixel(0, 0));
+        assert_eq!([24, 25, 26, 27], sub.get_pixel(1, 0));
+
+        // clamped to the edge of the sub texture
+        assert_eq!([24, 25, 26, 27], sub.get_pixel(5, 5));
+
+        assert!(sub.same_as(&texture.get_sub_texture(1., 1., 2., 1.)));
+        assert!(!sub.same_as(&texture));
+    }
+
+    #[test]
+    fn test_from_pixels() {
+        let size = ISize::new(2, 1);
+        let palette = [10, 20, 30, 40, 50, 60];
+
+        let indexed = HeadlessTexture::from_pixels(
+            "indexed".to_owned(),
+            size,
+            &[1, 0],
+            &palette,
+            PixelFormat::Indexed,
+        );
+
+        assert_eq!([40, 50, 60, 255], indexed.get_pixel(0, 0));
+        assert_eq!([10, 20, 30, 255], indexed.get_pixel(1, 0));
+
+        let rgb = HeadlessTexture::from_pixels(
+            "rgb".to_owned(),
+            size,
+            &[1, 2, 3, 4, 5, 6],
+            &[],
+            PixelFormat::Rgb,
+        );
+
+        assert_eq!([4, 5, 6, 255], rgb.get_pixel(1, 0));
+    }
+}
@@ -0,0 +1,59 @@
+use super::{HeadlessNativeView, HeadlessViewTypes};
+use crate::ui::TransitionService;
+use crate::view::{
+    GameViewPublic, LoadingViewPublic, MainMenuViewPublic, NativeView,
+};
+use std::sync::{Arc, Mutex};
+
+/// Transition service that tracks which view is currently being shown.
+/// Transitioning with `drop_current` releases the previous view's presenter
+/// the way the native implementations do when they tear down a view
+#[derive(Clone, Default)]
+pub struct HeadlessTransitionService {
+    current: Arc<Mutex<Option<HeadlessNativeView>>>,
+}
+
+impl HeadlessTransitionService {
+    /// Get the view that was most recently transitioned to
+    #[cfg(test)]
+    pub fn current_view(&self) -> Option<HeadlessNativeView> {
+        self.current.lock().unwrap().clone()
+    }
+
+    fn transition_to(&self, view: &HeadlessNativeView, drop_current: bool) {
+        let previous = self.current.lock().unwrap().replace(view.clone());
+
+        if let Some(previous) = previous {
+            if drop_current && !previous.scene().ptr_eq(view.scene()) {
+                previous.unset_presenter();
+            }
+        }
+    }
+}
+
+impl TransitionService for HeadlessTransitionService {
+    type NV = HeadlessNativeView;
+    type LV = LoadingViewPublic<HeadlessViewTypes>;
+    type MV = MainMenuViewPublic<HeadlessViewTypes>;
+    type GV = GameViewPublic<HeadlessViewTypes>;
+
+    fn transition_to_native_view(&self, view: &Self::NV, drop_current: bool) {
+        self.transition_to(view, drop_current);
+    }
+
+    fn transition_to_loading_view(&self, view: &Self::LV, drop_current: bool) {
+        self.transition_to(&view.raw_view, drop_current);
+    }
+
+    fn transition_to_main_menu_view(
+        &self,
+        view: &Self::MV,
+        drop_current: bool,
+    ) {
+        self.transition_to(&view.raw_view, drop_current);
+    }
+
+    fn transition_to_game_view(&self, view: &Self::GV, drop_current: bool) {
+        self.transition_to(&view.raw_view, drop_current);
+    }
+}
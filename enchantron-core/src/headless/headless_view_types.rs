@@ -0,0 +1,30 @@
+use super::{
+    HeadlessAnimation, HeadlessNativeView, HeadlessResourceLoader,
+    HeadlessSprite, HeadlessSpriteGroup, HeadlessSystemInterop,
+    HeadlessTexture, HeadlessTransitionService, HeadlessViewport,
+};
+use crate::ui::{ButtonPublic, ProgressBarPublic, Rgba32Color};
+use crate::view::{GameViewPublic, LoadingViewPublic, MainMenuViewPublic};
+use crate::view_types::ViewTypes;
+
+/// View types that keep the whole scene in memory so the presenters can be
+/// run and inspected without a native ui
+pub struct HeadlessViewTypes {}
+
+impl ViewTypes for HeadlessViewTypes {
+    type Color = Rgba32Color;
+    type Texture = HeadlessTexture;
+    type Animation = HeadlessAnimation;
+    type ResourceLoader = HeadlessResourceLoader;
+    type Sprite = HeadlessSprite;
+    type SpriteGroup = HeadlessSpriteGroup;
+    type Viewport = HeadlessViewport;
+    type ProgressBar = ProgressBarPublic<Self>;
+    type Button = ButtonPublic<Self>;
+    type NativeView = HeadlessNativeView;
+    type LoadingView = LoadingViewPublic<Self>;
+    type MainMenuView = MainMenuViewPublic<Self>;
+    type GameView = GameViewPublic<Self>;
+    type TransitionService = HeadlessTransitionService;
+    type SystemInterop = HeadlessSystemInterop;
+}
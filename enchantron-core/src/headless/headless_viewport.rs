@@ -0,0 +1,64 @@
+use super::{
+    HeadlessScene, HeadlessSprite, HeadlessSpriteGroup, HeadlessTexture,
+};
+use crate::model::Point;
+use crate::ui::{HasMutableLocation, HasMutableScale, SpriteSource, Viewport};
+
+/// Viewport of a headless scene. Sprites created from the viewport are locked
+/// to the screen
+#[derive(Debug, Clone)]
+pub struct HeadlessViewport {
+    scene: HeadlessScene,
+}
+
+impl HeadlessViewport {
+    pub(super) fn new(scene: HeadlessScene) -> HeadlessViewport {
+        HeadlessViewport { scene }
+    }
+}
+
+impl SpriteSource for HeadlessViewport {
+    type T = HeadlessTexture;
+    type S = HeadlessSprite;
+    type G = HeadlessSpriteGroup;
+
+    fn create_sprite(&self) -> Self::S {
+        HeadlessSprite::new(self.scene.clone(), self.scene.screen_root())
+    }
+
+    fn create_group(&self) -> Self::G {
+        HeadlessSpriteGroup::new(self.scene.clone(), self.scene.screen_root())
+    }
+}
+
+impl HasMutableLocation for HeadlessViewport {
+    fn set_location_animated(
+        &self,
+        left: f64,
+        top: f64,
+        _duration_seconds: f64,
+    ) {
+        self.scene
+            .set_viewport(|v| v.location = Point::new(left, top));
+    }
+}
+
+impl HasMutableScale for HeadlessViewport {
+    fn set_scale(&self, scale: f64) {
+        self.scene.set_viewport(|v| v.scale = scale);
+    }
+
+    fn set_scale_and_location(
+        &self,
+        scale: f64,
+        top_left_x: f64,
+        top_left_y: f64,
+    ) {
+        self.scene.set_viewport(|v| {
+            v.scale = scale;
+            v.location = Point::new(top_left_x, top_left_y);
+        });
+    }
+}
+
+impl Viewport for HeadlessViewport {}
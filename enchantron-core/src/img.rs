@@ -1,5 +1,7 @@
 // Image specific module
 
+pub use png_decoder::PngDecoder;
 pub use png_generator::PngGenerator;
 
+mod png_decoder;
 mod png_generator;
@@ -0,0 +1,86 @@
+use crate::model::ISize;
+use png::{BitDepth, ColorType, Decoder, Transformations};
+
+pub struct PngDecoder {}
+
+impl PngDecoder {
+    /// Decode the given png data into tightly packed 8-bit RGBA pixels and
+    /// return the size of the image along with the pixel data
+    pub fn get_rgba(png_data: &[u8]) -> (ISize, Vec<u8>) {
+        let mut decoder = Decoder::new(png_data);
+        decoder.set_transformations(
+            Transformations::EXPAND | Transformations::STRIP_16,
+        );
+
+        let (info, mut reader) = decoder.read_info().unwrap_or_else(|e| {
+            error!("Failed to read png header data: {:?}", e);
+            panic!("Failed to read png header");
+        });
+
+        let mut raw = vec![0u8; info.buffer_size()];
+
+        reader.next_frame(&mut raw).unwrap_or_else(|e| {
+            error!("Failed to read png image data: {:?}", e);
+            panic!("Failed to read png image data");
+        });
+
+        let (color_type, bit_depth) = reader.output_color_type();
+
+        if bit_depth != BitDepth::Eight {
+            error!("Unsupported png bit depth: {:?}", bit_depth);
+            panic!("Unsupported png bit depth");
+        }
+
+        let size = ISize::new(info.width as usize, info.height as usize);
+        let mut rgba = Vec::<u8>::with_capacity(size.area() * 4);
+
+        match color_type {
+            ColorType::RGBA => rgba.extend_from_slice(&raw[..size.area() * 4]),
+            ColorType::RGB => {
+                raw.chunks_exact(3).take(size.area()).for_each(|px| {
+                    rgba.extend_from_slice(&[px[0], px[1], px[2], 255])
+                })
+            }
+            ColorType::GrayscaleAlpha => {
+                raw.chunks_exact(2).take(size.area()).for_each(|px| {
+                    rgba.extend_from_slice(&[px[0], px[0], px[0], px[1]])
+                })
+            }
+            ColorType::Grayscale => raw
+                .iter()
+                .take(size.area())
+                .for_each(|v| rgba.extend_from_slice(&[*v, *v, *v, 255])),
+            ColorType::Indexed => {
+                error!("Png palette was not expanded");
+                panic!("Png palette was not expanded");
+            }
+        }
+
+        (size, rgba)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::img::PngGenerator;
+
+    #[test]
+    fn test_round_trip() {
+        let size = ISize::new(3, 2);
+        let data: Vec<u8> = (0..(size.area() * 3) as u8).collect();
+        let mut png = Vec::new();
+
+        PngGenerator::get_png(&data, &size, &mut png);
+
+        let (decoded_size, rgba) = PngDecoder::get_rgba(&png);
+
+        assert_eq!(size, decoded_size);
+        assert_eq!(rgba.len(), size.area() * 4);
+
+        for (rgb, rgba) in data.chunks_exact(3).zip(rgba.chunks_exact(4)) {
+            assert_eq!(rgb, &rgba[..3]);
+            assert_eq!(255, rgba[3]);
+        }
+    }
+}
@@ -5,8 +5,13 @@ extern crate getset;
 
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate serde;
 pub use self::lib_gen::*;
 
+#[cfg(any(test, feature = "terminal"))]
+pub use self::terminal::run as run_terminal;
+
 mod util;
 
 #[macro_use]
@@ -17,6 +22,12 @@ mod model;
 
 mod game;
 
+#[cfg(any(test, feature = "terminal", feature = "bench"))]
+mod headless;
+
+#[cfg(any(test, feature = "terminal"))]
+mod terminal;
+
 mod application_context;
 mod event;
 mod img;
@@ -27,3 +38,7 @@ mod view;
 mod view_types;
 
 mod lib_gen;
+
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
@@ -2,7 +2,9 @@ use super::{ISize, Point};
 use rstar::Point as RTreePoint;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub};
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
 pub struct IPoint {
     pub x: i64,
     pub y: i64,
@@ -10,10 +12,10 @@ pub struct IPoint {
 
 impl IPoint {
     /// Create a new IPoint with the given x and y IPoint:
-    /// ```
-    /// let p = IPoint::new(1.0, -2.0);
-    /// assert_eq!(p.x, 1.0);
-    /// assert_eq!(p.y, -2.0);
+    /// ```ignore
+    /// let p = IPoint::new(1, -2);
+    /// assert_eq!(p.x, 1);
+    /// assert_eq!(p.y, -2);
     /// ```
     pub fn new(x: i64, y: i64) -> IPoint {
         IPoint { x, y }
@@ -2,7 +2,9 @@ use super::{IPoint, ISize};
 use rstar::{Envelope, Point, PointDistance, RTreeObject};
 use std::cmp::{max, min};
 
-#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(
+    Default, Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize,
+)]
 pub struct IRect {
     pub top_left: IPoint,
     pub size: ISize,
@@ -78,8 +80,11 @@ impl IRect {
         } else {
             let d = if i_point.y < self.top_left.y {
                 self.top_left.y - i_point.y
-            } else {
+            } else if i_point.y > bottom {
                 i_point.y - bottom
+            } else {
+                // On the right or bottom edge
+                0
             };
 
             d * d
@@ -342,6 +347,8 @@ fn test_distance() {
     assert_eq!(r.distance_to(&IPoint::new(0, -4)), 2.); // under
     assert_eq!(r.distance_to(&IPoint::new(-4, 0)), 3.); // left
     assert_eq!(r.distance_to(&IPoint::new(6, 0)), 4.); //right
+    assert_eq!(r.distance_to(&IPoint::new(2, 0)), 0.); // right edge
+    assert_eq!(r.distance_to(&IPoint::new(0, 2)), 0.); // bottom edge
 
     assert_eq!(r.distance_to(&IPoint::new(-5, -5)), 5.); // under left
     assert_eq!(r.distance_to(&IPoint::new(-4, -6)), 5.); // under left
@@ -1,6 +1,8 @@
 use std::ops::{Div, DivAssign, Mul, MulAssign};
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(
+    Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
 pub struct ISize {
     pub width: usize,
     pub height: usize,
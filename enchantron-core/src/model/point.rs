@@ -10,7 +10,7 @@ pub struct Point {
 
 impl Point {
     /// Create a new point with the given x and y point:
-    /// ```
+    /// ```ignore
     /// let p = Point::new(1.0, -2.0);
     /// assert_eq!(p.x, 1.0);
     /// assert_eq!(p.y, -2.0);
@@ -8,7 +8,7 @@ pub struct UPoint {
 
 impl UPoint {
     /// Create a new UPoint with the given x and y UPoint:
-    /// ```
+    /// ```ignore
     /// let p = UPoint::new(11, 8);
     /// assert_eq!(p.x, 11);
     /// assert_eq!(p.y, 8);
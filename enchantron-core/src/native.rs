@@ -1,14 +1,18 @@
 pub use self::animation::Animation;
 pub use self::animations::Animations;
+pub use self::pixel_format::PixelFormat;
 pub use self::resource_loader::ResourceLoader;
 pub use self::runtime_resources::RuntimeResources;
 pub use self::shader_variable_type::ShaderVariableType;
 pub use self::system_interop::SystemInterop;
 pub use self::texture::Texture;
-pub use self::textures::Textures;
+pub use self::textures::{
+    overworld_tiles, AtlasTile, Textures, OVERWORLD_TEXTURE,
+};
 
 mod animation;
 mod animations;
+mod pixel_format;
 mod resource_loader;
 mod runtime_resources;
 mod shader_variable_type;
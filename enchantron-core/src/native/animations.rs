@@ -1,4 +1,5 @@
 use super::{Animation, ResourceLoader, Textures};
+use crate::game::Direction;
 use crate::view_types::ViewTypes;
 
 const LOOP: bool = true;
@@ -39,10 +40,41 @@ macro_rules! create_animations {
 }
 
 create_animations! {
-    player_walk_south: LOOP [
-        gist.south_rest,
-        gist.south_step_left,
-        gist.south_step_mid,
-        gist.south_step_right
+    character_walk_north: LOOP [
+        character.north_rest,
+        character.north_step_left,
+        character.north_step_mid,
+        character.north_step_right
     ]
+    character_walk_east: LOOP [
+        character.east_rest,
+        character.east_step_left,
+        character.east_step_mid,
+        character.east_step_right
+    ]
+    character_walk_south: LOOP [
+        character.south_rest,
+        character.south_step_left,
+        character.south_step_mid,
+        character.south_step_right
+    ]
+    character_walk_west: LOOP [
+        character.west_rest,
+        character.west_step_left,
+        character.west_step_mid,
+        character.west_step_right
+    ]
+}
+
+impl<T: ViewTypes> Animations<T> {
+    /// Get the animation of a character walking in the given direction.
+    /// Characters walking diagonally face sideways
+    pub fn character_walk(&self, direction: &Direction) -> &T::Animation {
+        match direction.get_facing() {
+            Direction::NORTH => &self.character_walk_north,
+            Direction::EAST => &self.character_walk_east,
+            Direction::WEST => &self.character_walk_west,
+            _ => &self.character_walk_south,
+        }
+    }
 }
@@ -0,0 +1,35 @@
+/// Layout of the uncompressed pixel data given to
+/// `ResourceLoader::load_texture_from_pixels`. The codes are shared with the
+/// native side, so they must not change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// Three bytes per pixel
+    Rgb = 0,
+    /// Four bytes per pixel with straight alpha
+    Rgba = 1,
+    /// One byte per pixel indexing into a palette of RGB colors
+    Indexed = 2,
+}
+
+impl PixelFormat {
+    pub fn from_code(code: i64) -> Option<PixelFormat> {
+        match code {
+            0 => Some(PixelFormat::Rgb),
+            1 => Some(PixelFormat::Rgba),
+            2 => Some(PixelFormat::Indexed),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> i64 {
+        self as i64
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb => 3,
+            PixelFormat::Rgba => 4,
+            PixelFormat::Indexed => 1,
+        }
+    }
+}
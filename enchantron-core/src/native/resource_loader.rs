@@ -9,5 +9,25 @@ pub trait ResourceLoader: Send + Sync + Unpin + 'static {
 
     fn load_texture_from_png_data(&self, png_data: ByteBuffer) -> Self::T;
 
+    /// Load a texture from uncompressed rows of pixels in the `PixelFormat`
+    /// with the given code. The palette is only used by indexed pixel data
+    /// and is a list of RGB colors
+    fn load_texture_from_pixels(
+        &self,
+        pixel_data: ByteBuffer,
+        palette: ByteBuffer,
+        width: i64,
+        height: i64,
+        pixel_format: i64,
+    ) -> Self::T;
+
+    /// Load the contents of the named text resource. An empty string is
+    /// returned if the resource doesn't exist
+    fn load_text(&self, name: String) -> String;
+
+    /// Get the path on disk of the named texture, for reading its pixels
+    /// directly. An empty string is returned if the texture doesn't exist
+    fn get_texture_path(&self, name: String) -> String;
+
     fn create_animation(&self) -> Self::A;
 }
@@ -1,19 +1,23 @@
 use super::{Animations, Textures};
+use crate::game::TerrainConfig;
 use crate::view_types::ViewTypes;
 
 pub struct RuntimeResources<T: ViewTypes> {
     textures: Textures<T>,
     animations: Animations<T>,
+    terrain_config: TerrainConfig,
 }
 
 impl<T: ViewTypes> RuntimeResources<T> {
     pub fn new(
         textures: Textures<T>,
         animations: Animations<T>,
+        terrain_config: TerrainConfig,
     ) -> RuntimeResources<T> {
         RuntimeResources {
             textures,
             animations,
+            terrain_config,
         }
     }
 
@@ -24,4 +28,8 @@ impl<T: ViewTypes> RuntimeResources<T> {
     pub fn animations(&self) -> &Animations<T> {
         &self.animations
     }
+
+    pub fn terrain_config(&self) -> &TerrainConfig {
+        &self.terrain_config
+    }
 }
@@ -17,4 +17,7 @@ pub trait SystemInterop: 'static + Sync + Send {
     fn create_loading_view(&self) -> Self::LV;
     fn create_main_menu_view(&self) -> Self::MV;
     fn create_game_view(&self) -> Self::GV;
+
+    /// Get the path to the directory where saved games should be stored
+    fn get_save_directory(&self) -> String;
 }
@@ -1,9 +1,22 @@
 use super::{ResourceLoader, Texture};
+use crate::game::Direction;
 use crate::ui::HasSize;
 use crate::view_types::ViewTypes;
 
 const CENTER: usize = 0;
 
+/// Name of the texture with the overworld atlas
+pub const OVERWORLD_TEXTURE: &str = "overworld.png";
+
+/// Location and size of a texture in an atlas, measured in tiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasTile {
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
 macro_rules! count {
     ($h:expr) => (1);
     ($h:expr, $($t:expr),*) =>
@@ -75,32 +88,72 @@ macro_rules! define_texture_atlas {
       )*
     }
 
+    paste::paste! {
+      /// Locations of the textures in the atlas, for composing textures out
+      /// of the atlas's pixels
+      #[allow(dead_code)]
+      pub mod [<$texture_type:snake _tiles>] {
+        use super::AtlasTile;
+
+        $(
+          pub const [<$name:upper>]: AtlasTile = AtlasTile {
+            left: $left,
+            top: $top,
+            width: $width,
+            height: $height,
+          };
+        )*
+      }
+    }
+
   };
 }
 
 define_texture_atlas!(Overworld(x_tile_count: 40, y_tile_count: 36) {
   grass(left: 0, top: 0, width: 1, height: 1),
-  dirt(left: 2, top: 32, width: 1, height: 1)
+  sand_patch(left: 0, top: 3, width: 3, height: 3),
+  sand_inner_corners(left: 0, top: 6, width: 2, height: 2),
+  water_patch(left: 2, top: 6, width: 3, height: 3),
+  water_inner_corners(left: 2, top: 9, width: 2, height: 2),
+  dirt_patch(left: 0, top: 29, width: 3, height: 3),
+  dirt_inner_corners(left: 0, top: 32, width: 2, height: 2)
 });
 
 define_texture_atlas!(Character(x_tile_count: 17, y_tile_count: 16) {
     south_rest(left: 0, top: 0, width: 1, height: 2, register: CENTER),
     south_step_left(left: 1, top: 0, width: 1, height: 2, register: CENTER),
     south_step_mid(left: 2, top: 0, width: 1, height: 2, register: CENTER),
-    south_step_right(left: 3, top: 0, width: 1, height: 2, register: CENTER)
+    south_step_right(left: 3, top: 0, width: 1, height: 2, register: CENTER),
+    west_rest(left: 0, top: 2, width: 1, height: 2, register: CENTER),
+    west_step_left(left: 1, top: 2, width: 1, height: 2, register: CENTER),
+    west_step_mid(left: 2, top: 2, width: 1, height: 2, register: CENTER),
+    west_step_right(left: 3, top: 2, width: 1, height: 2, register: CENTER),
+    north_rest(left: 0, top: 4, width: 1, height: 2, register: CENTER),
+    north_step_left(left: 1, top: 4, width: 1, height: 2, register: CENTER),
+    north_step_mid(left: 2, top: 4, width: 1, height: 2, register: CENTER),
+    north_step_right(left: 3, top: 4, width: 1, height: 2, register: CENTER),
+    east_rest(left: 0, top: 6, width: 1, height: 2, register: CENTER),
+    east_step_left(left: 1, top: 6, width: 1, height: 2, register: CENTER),
+    east_step_mid(left: 2, top: 6, width: 1, height: 2, register: CENTER),
+    east_step_right(left: 3, top: 6, width: 1, height: 2, register: CENTER)
 });
 
-define_texture_atlas!(Gist(x_tile_count: 4, y_tile_count: 1) {
-    south_rest(left: 0, top: 0, width: 1, height: 1, register: CENTER),
-    south_step_left(left: 1, top: 0, width: 1, height: 1, register: CENTER),
-    south_step_mid(left: 2, top: 0, width: 1, height: 1, register: CENTER),
-    south_step_right(left: 3, top: 0, width: 1, height: 1, register: CENTER)
-});
+impl<T: ViewTypes> Character<T> {
+    /// Get the texture of the character resting while facing the cardinal
+    /// direction nearest the given direction
+    pub fn rest(&self, direction: &Direction) -> &T::Texture {
+        match direction.get_facing() {
+            Direction::NORTH => self.north_rest(),
+            Direction::EAST => self.east_rest(),
+            Direction::WEST => self.west_rest(),
+            _ => self.south_rest(),
+        }
+    }
+}
 
 pub struct Textures<T: ViewTypes> {
     pub overworld: Overworld<T>,
     pub character: Character<T>,
-    pub gist: Gist<T>,
 }
 
 impl<T: ViewTypes> Textures<T> {
@@ -109,7 +162,7 @@ impl<T: ViewTypes> Textures<T> {
         progress_callback: &impl Fn(f64),
     ) -> Textures<T> {
         let overworld = Overworld::new(
-            texture_loader.load_texture(String::from("overworld.png")),
+            texture_loader.load_texture(String::from(OVERWORLD_TEXTURE)),
             |p| progress_callback(p),
         );
 
@@ -118,15 +171,9 @@ impl<T: ViewTypes> Textures<T> {
             |p| progress_callback(p),
         );
 
-        let gist = Gist::new(
-            texture_loader.load_texture(String::from("gist.png")),
-            |p| progress_callback(p),
-        );
-
         Textures {
             overworld,
             character,
-            gist,
         }
     }
 }
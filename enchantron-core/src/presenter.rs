@@ -13,5 +13,6 @@ mod game_presenter;
 mod loading_presenter;
 mod main_menu_presenter;
 mod player_presenter;
+mod terrain_layer;
 mod terrain_presenter;
 mod viewport_presenter;
@@ -1,8 +1,11 @@
 use super::{FocusedEntityPresenter, TerrainPresenter, ViewportPresenter};
 use crate::application_context::{Ao, NUM_CPUS};
 use crate::event::*;
-use crate::game::{constants, Gor, SavedGame, Services};
-use crate::model::{Point, Size};
+use crate::game::{
+    constants, Entity, EntityType, Gor, SaveSlotManager, SavedGame, Services,
+    ViewService,
+};
+use crate::model::{IPoint, Point, Size};
 use crate::native::{RuntimeResources, SystemInterop};
 use crate::ui::{
     HandlerRegistration, HasLayoutHandlers, HasMagnifyHandlers,
@@ -14,11 +17,14 @@ use crate::view::{GameView, NativeView};
 use crate::view_types::ViewTypes;
 use futures::future::join_all;
 use futures::pin_mut;
+use std::iter::once;
 use std::time::Duration;
 use tokio::runtime::Builder;
 use tokio::select;
 use tokio::stream::StreamExt;
 
+const AUTOSAVE_SLOT_NAME: &str = "autosave";
+
 pub struct GamePresenter<T>
 where
     T: ViewTypes,
@@ -27,6 +33,9 @@ where
     event_bus: EventBus,
     runtime_resources: Ao<RuntimeResources<T>>,
     system_interop: Ao<T::SystemInterop>,
+    services: Services,
+    entity_sprite_group: Gor<T::SpriteGroup>,
+    save_slots: SaveSlotManager,
 
     touch_tracker: TouchTracker,
     viewport_presenter: ViewportPresenter<T>,
@@ -65,6 +74,51 @@ where
         self.viewport_presenter.on_touch_event(&touch_event);
     }
 
+    /// Create a new entity at the given tile and start its presenter
+    async fn spawn(&mut self, entity_type: EntityType, location: IPoint) {
+        let run_bundle = self
+            .services
+            .spawn(entity_type, location, Default::default())
+            .await;
+
+        info!("Spawned {:?} at {:?}", run_bundle.entity, location);
+
+        self.services
+            .run(
+                self.entity_sprite_group.clone(),
+                self.runtime_resources.clone(),
+                once(run_bundle),
+            )
+            .await;
+    }
+
+    /// Remove the given entity from the game. This waits for the entity's
+    /// presenter to stop, so it's done off the ui loop
+    fn despawn(&self, entity: Entity) {
+        let services = self.services.clone();
+
+        self.event_bus.spawn(async move {
+            if !services.despawn(&entity).await {
+                error!("Failed to despawn {:?}", entity);
+            }
+        });
+    }
+
+    /// Snapshot the running game and write it over the autosave slot
+    async fn autosave(&mut self) {
+        let result = self.services.snapshot().await.and_then(|saved_game| {
+            self.save_slots.save(AUTOSAVE_SLOT_NAME, &saved_game)
+        });
+
+        match result {
+            Ok(_) => {
+                info!("Game saved to {} slot", AUTOSAVE_SLOT_NAME);
+                self.event_bus.post(GameSaved::new());
+            }
+            Err(e) => error!("Failed to autosave game, {}", e),
+        }
+    }
+
     fn bind_ui_events(
         view: &T::GameView,
         event_bus: EventBus,
@@ -118,6 +172,8 @@ where
             terrain_sprite_group,
             self.runtime_resources.clone(),
             self.system_interop.clone(),
+            self.services.seed(),
+            self.services.terrain_service(),
         );
 
         (terrain_presenter)
@@ -128,13 +184,12 @@ where
         event_bus: EventBus,
         runtime_resources: Ao<RuntimeResources<T>>,
         system_interop: Ao<T::SystemInterop>,
+        saved_game: SavedGame,
     ) {
-        let saved_game = SavedGame::new(Default::default());
-
         let boxed_runtime = Box::new(
             Builder::new_multi_thread()
                 .thread_name("GameThread")
-                .worker_threads(NUM_CPUS.checked_sub(1).unwrap_or(1)) // one for os
+                .worker_threads(NUM_CPUS.saturating_sub(1).max(1)) // one for os
                 .enable_time()
                 .pausable_time(
                     true,
@@ -146,8 +201,11 @@ where
                 }),
         );
 
-        let (services, run_bundles, mut droppers) =
-            Services::new(boxed_runtime, saved_game);
+        let (services, run_bundles, mut droppers) = Services::new(
+            boxed_runtime,
+            saved_game,
+            &runtime_resources.terrain_config().biome,
+        );
 
         let terrain_sprite_group = view.create_group();
 
@@ -158,6 +216,20 @@ where
 
         let end_event = event_bus.register_for_one::<StopGameRequested>();
         let (_listener_reg, ui_stream) = event_bus.register::<UI>();
+        let (_background_listener_reg, background_stream) =
+            event_bus.register::<ApplicationBackgrounded>();
+        let (_spawn_listener_reg, spawn_stream) =
+            event_bus.register::<SpawnEntityRequested>();
+        let (_despawn_listener_reg, despawn_stream) =
+            event_bus.register::<DespawnEntityRequested>();
+        let (_modify_terrain_listener_reg, modify_terrain_stream) =
+            event_bus.register::<ModifyTerrainRequested>();
+
+        let save_slots =
+            SaveSlotManager::new(system_interop.get_save_directory())
+                .with_terrain_config(
+                    runtime_resources.terrain_config().biome.clone(),
+                );
 
         let _handler_registrations =
             Self::bind_ui_events(&view, event_bus.clone());
@@ -168,7 +240,7 @@ where
 
         services
             .run(
-                entity_sprite_group,
+                entity_sprite_group.clone(),
                 runtime_resources.clone(),
                 run_bundles.into_iter(),
             )
@@ -182,13 +254,17 @@ where
             1. / constants::TILE_SCALE,
         );
 
-        let focused_entity_presenter = FocusedEntityPresenter::new(services);
+        let focused_entity_presenter =
+            FocusedEntityPresenter::new(services.clone());
 
         let mut presenter = GamePresenter {
             view,
             event_bus: event_bus.clone(),
             runtime_resources,
             system_interop,
+            services,
+            entity_sprite_group,
+            save_slots,
             touch_tracker: Default::default(),
             viewport_presenter,
             focused_entity_presenter,
@@ -198,12 +274,19 @@ where
         let (terrain_presenter) =
             presenter.create_sub_presenters(&terrain_sprite_group);
 
-        let sub_presenters_future =
-            join_all(vec![event_bus.spawn(terrain_presenter.run())]);
+        // register before spawning so the started event can't be missed
+        let terrain_presenter_started =
+            event_bus.register_for_one::<TerrainPresenterStarted>();
 
-        event_bus
-            .register_for_one::<TerrainPresenterStarted>()
-            .await;
+        let view_service =
+            ViewService::new(presenter.services.clone(), event_bus.clone());
+
+        let sub_presenters_future = join_all(vec![
+            event_bus.spawn(terrain_presenter.run()),
+            event_bus.spawn(view_service.run()),
+        ]);
+
+        terrain_presenter_started.await;
 
         presenter.view.set_presenter(Box::new(
             event_bus.post_on_drop(StopGameRequested::new()),
@@ -212,13 +295,41 @@ where
         event_bus.spawn_blocking(move || game_runtime.resume());
 
         pin_mut!(ui_stream);
+        pin_mut!(background_stream);
+        pin_mut!(spawn_stream);
+        pin_mut!(despawn_stream);
+        pin_mut!(modify_terrain_stream);
         pin_mut!(end_event);
 
         // Main ui handler loop
-        while let Some(UI { event: ui_event }) = select! {
-            _ = &mut end_event => None,
-            ui_event_opt = ui_stream.next() => ui_event_opt
-        } {
+        loop {
+            let ui_event = select! {
+                _ = &mut end_event => break,
+                Some(_) = background_stream.next() => {
+                    presenter.autosave().await;
+                    continue;
+                }
+                Some(event) = spawn_stream.next() => {
+                    presenter.spawn(event.entity_type, event.location).await;
+                    continue;
+                }
+                Some(event) = despawn_stream.next() => {
+                    presenter.despawn(event.entity);
+                    continue;
+                }
+                Some(event) = modify_terrain_stream.next() => {
+                    presenter
+                        .services
+                        .terrain_service()
+                        .modify(event.position, event.terrain_type);
+                    continue;
+                }
+                ui_event_opt = ui_stream.next() => match ui_event_opt {
+                    Some(UI { event }) => event,
+                    None => break,
+                }
+            };
+
             match ui_event {
                 UIEvent::RawTouchEvent { event } => {
                     presenter.on_touch(event).await
@@ -228,6 +339,8 @@ where
             }
         }
 
+        presenter.autosave().await;
+
         sub_presenters_future.await;
 
         event_bus.post(GameStopped::new());
@@ -241,10 +354,250 @@ where
     fn drop(&mut self) {
         info!("Dropping Game Presenter");
 
+        let droppers: Vec<_> = self.droppers.drain(..).collect();
+
         // Run the droppers in the stored order so that the runtime is dropped
-        // first. This is what ensures that the whole Gor system is safe
-        for dropper in self.droppers.drain(..) {
-            dropper();
+        // first. This is what ensures that the whole Gor system is safe. The
+        // presenter is usually dropped from within the application runtime
+        // where dropping another runtime isn't allowed, so the droppers are
+        // run (and waited for) on a separate thread
+        let dropper_thread = std::thread::spawn(move || {
+            for dropper in droppers {
+                dropper();
+            }
+        });
+
+        if dropper_thread.join().is_err() {
+            error!("Failed to drop game resources");
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::{TerrainConfig, TerrainType};
+    use crate::headless::{
+        HeadlessNativeView, HeadlessSystemInterop, HeadlessViewTypes, Layer,
+    };
+    use crate::model::IRect;
+    use crate::native::{Animations, Textures};
+    use tokio::time::sleep;
+
+    /// Poll the given condition until it's true or a generous timeout passes
+    async fn wait_for(condition: impl Fn() -> bool) -> bool {
+        for _ in 0..500 {
+            if condition() {
+                return true;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        condition()
+    }
+
+    #[test]
+    fn test_run_headless() {
+        let runtime = Box::new(
+            Builder::new_multi_thread()
+                .worker_threads(2)
+                .enable_time()
+                .build()
+                .unwrap(),
+        );
+
+        let (event_bus, event_bus_dropper) = EventBus::new(Ao::new(&runtime));
+
+        let system_interop = Box::new(HeadlessSystemInterop::default());
+        let resource_loader = system_interop.get_resource_loader();
+        let textures = Textures::new(&resource_loader, &|_| {});
+        let animations = Animations::new(&resource_loader, &textures);
+        let runtime_resources = Box::new(RuntimeResources::new(
+            textures,
+            animations,
+            TerrainConfig::load(&resource_loader),
+        ));
+
+        // Start the player away from the origin so its sprite has to be
+        // moved onto its tile
+        let mut saved_game = SavedGame::new(5);
+        let player_location_key = saved_game.player.location_key;
+
+        saved_game
+            .locations
+            .get_mut(&player_location_key)
+            .unwrap()
+            .location = IRect::new(-3, -2, 1, 1);
+
+        runtime.block_on(async {
+            let view = system_interop.create_game_view();
+            let raw_view: HeadlessNativeView = view.raw_view.clone();
+            let scene = raw_view.scene().clone();
+
+            let game_stopped = event_bus.register_for_one::<GameStopped>();
+
+            let presenter_future = GamePresenter::<HeadlessViewTypes>::run(
+                view,
+                event_bus.clone(),
+                Ao::new(&runtime_resources),
+                Ao::new(&system_interop),
+                saved_game,
+            );
+
+            let test_future = async {
+                assert!(wait_for(|| raw_view.has_presenter()).await);
+
+                assert!(system_interop
+                    .get_transition_service()
+                    .current_view()
+                    .map(|v| v.scene().ptr_eq(&scene))
+                    .unwrap_or_default());
+
+                raw_view.layout(800., 600., 1.);
+
+                let terrain_sprites = || {
+                    scene
+                        .visible_sprites()
+                        .into_iter()
+                        .filter(|s| {
+                            s.texture.as_ref().map(|t| t.name() == "pixel_data")
+                                == Some(true)
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                assert_eq!(1. / constants::TILE_SCALE, scene.viewport().scale);
+
+                // The viewport is 50 x 37.5 tiles, so it's drawn at zoom level
+                // 0 with autotiled sprites of 16 x 16 tiles
+                let covers_viewport = || {
+                    let viewport = scene.viewport();
+                    let sprites = terrain_sprites();
+
+                    if sprites.is_empty() {
+                        return false;
+                    }
+
+                    let mut covered_top_left = Point::new(f64::MAX, f64::MAX);
+                    let mut covered_bottom_right =
+                        Point::new(f64::MIN, f64::MIN);
+
+                    for sprite in sprites {
+                        assert_eq!(Size::new(16., 16.), sprite.size);
+                        assert_eq!(0., sprite.location.x % 16.);
+                        assert_eq!(0., sprite.location.y % 16.);
+
+                        covered_top_left.x =
+                            covered_top_left.x.min(sprite.location.x);
+                        covered_top_left.y =
+                            covered_top_left.y.min(sprite.location.y);
+                        covered_bottom_right.x =
+                            covered_bottom_right.x.max(sprite.location.x + 16.);
+                        covered_bottom_right.y =
+                            covered_bottom_right.y.max(sprite.location.y + 16.);
+                    }
+
+                    covered_top_left.x <= viewport.location.x
+                        && covered_top_left.y <= viewport.location.y
+                        && covered_bottom_right.x >= viewport.location.x + 50.
+                        && covered_bottom_right.y >= viewport.location.y + 37.5
+                };
+
+                assert!(wait_for(covers_viewport).await);
+
+                let south_rest =
+                    runtime_resources.textures().character.south_rest();
+
+                let find_player_sprite = || {
+                    scene.sprites().into_iter().find(|s| {
+                        s.texture
+                            .as_ref()
+                            .map(|t| t.same_as(south_rest))
+                            .unwrap_or_default()
+                    })
+                };
+
+                // The player's view is only created once the view service
+                // sees the player in the viewport
+                assert!(wait_for(|| find_player_sprite().is_some()).await);
+
+                let player_sprite =
+                    find_player_sprite().expect("No player sprite");
+
+                assert_eq!(Layer::World, player_sprite.layer);
+                assert!(player_sprite.visible);
+                assert_eq!(Size::new(1., 2.), player_sprite.size);
+                assert_eq!(constants::ENTITY_Z_LEVEL, player_sprite.z_level);
+
+                let player_sprite_location =
+                    || find_player_sprite().map(|sprite| sprite.location);
+
+                assert!(
+                    wait_for(|| player_sprite_location()
+                        == Some(Point::new(-2.5, -2.125)))
+                    .await
+                );
+
+                // A spawned entity gets its own sprite once it's in view
+                let character_sprite_count = || {
+                    scene
+                        .sprites()
+                        .into_iter()
+                        .filter(|s| {
+                            s.texture
+                                .as_ref()
+                                .map(|t| t.same_as(south_rest))
+                                .unwrap_or_default()
+                        })
+                        .count()
+                };
+
+                event_bus.post(SpawnEntityRequested::new(
+                    EntityType::Player,
+                    IPoint::new(-6, -2),
+                ));
+
+                assert!(wait_for(|| character_sprite_count() == 2).await);
+
+                event_bus.post(ModifyTerrainRequested::new(
+                    IPoint::new(-4, -2),
+                    Some(TerrainType::Dirt),
+                ));
+
+                let save_slots =
+                    SaveSlotManager::new(system_interop.get_save_directory());
+
+                let game_saved = event_bus.register_for_one::<GameSaved>();
+                event_bus.post(ApplicationBackgrounded::new());
+                assert!(game_saved.await.is_some());
+                assert!(save_slots.load(AUTOSAVE_SLOT_NAME).is_ok());
+
+                let game_saved = event_bus.register_for_one::<GameSaved>();
+                raw_view.unset_presenter();
+
+                assert!(game_saved.await.is_some());
+                assert!(game_stopped.await.is_some());
+
+                let saved_game = save_slots.load(AUTOSAVE_SLOT_NAME).unwrap();
+                assert_eq!(2, saved_game.entities.len());
+                assert_eq!(
+                    Some(TerrainType::Dirt),
+                    saved_game.terrain_modifications.get(&IPoint::new(-4, -2))
+                );
+
+                let metadata = save_slots.most_recent().unwrap().unwrap();
+                assert_eq!(AUTOSAVE_SLOT_NAME, metadata.slot_name);
+                assert_eq!(5, metadata.seed);
+                assert_eq!(saved_game.player_tile(), metadata.player_tile);
+
+                let _ = std::fs::remove_dir_all(
+                    system_interop.get_save_directory(),
+                );
+            };
+
+            futures::join!(presenter_future, test_future);
+        });
+
+        drop(runtime);
+        event_bus_dropper();
+    }
+}
@@ -1,6 +1,7 @@
 use super::MainMenuPresenter;
 use crate::application_context::Ao;
 use crate::event::{EventBus, LoadResources};
+use crate::game::TerrainConfig;
 use crate::native::{Animations, RuntimeResources, SystemInterop, Textures};
 use crate::ui::{HasIntValue, HasMutableFloatValue, TransitionService};
 use crate::view::{LoadingView, NativeView};
@@ -36,8 +37,13 @@ where
 
         let animations = Animations::<T>::new(resource_loader, &textures);
 
-        let runtime_resources =
-            (self.resources_sink)(RuntimeResources::new(textures, animations));
+        let terrain_config = TerrainConfig::load(resource_loader);
+
+        let runtime_resources = (self.resources_sink)(RuntimeResources::new(
+            textures,
+            animations,
+            terrain_config,
+        ));
 
         println!("done loading resources");
 
@@ -1,6 +1,7 @@
 use super::GamePresenter;
 use crate::application_context::Ao;
 use crate::event::{EventBus, StartGame};
+use crate::game::{SaveSlotManager, SavedGame};
 use crate::native::{RuntimeResources, SystemInterop};
 use crate::ui::{
     ClickHandler, HandlerRegistration, HasClickHandlers, HasText,
@@ -19,6 +20,17 @@ pub struct MainMenuPresenter<T: ViewTypes> {
 }
 
 impl<T: ViewTypes> MainMenuPresenter<T> {
+    /// Get the game to start. Continuing picks up the most recently saved
+    /// slot, and falls back to a new game if there isn't one to load
+    fn get_saved_game(&self, new: bool) -> SavedGame {
+        if new {
+            SavedGame::new(Default::default())
+        } else {
+            SaveSlotManager::new(self.system_interop.get_save_directory())
+                .load_most_recent_or_new()
+        }
+    }
+
     async fn bind(mut self) -> Arc<MainMenuPresenter<T>> {
         let copied_event_bus = self.event_bus.clone();
 
@@ -32,6 +44,18 @@ impl<T: ViewTypes> MainMenuPresenter<T> {
                 .add_click_handler(click_handler),
         ));
 
+        let copied_event_bus = self.event_bus.clone();
+
+        let click_handler = create_click_handler!({
+            copied_event_bus.post(StartGame::new(false))
+        });
+
+        self.handler_registrations.push(Box::new(
+            self.view
+                .get_continue_game_button()
+                .add_click_handler(click_handler),
+        ));
+
         let result = Arc::new(self);
 
         let start_game_event_future =
@@ -40,12 +64,13 @@ impl<T: ViewTypes> MainMenuPresenter<T> {
         let this = result.clone();
 
         result.event_bus.spawn(async move {
-            if start_game_event_future.await.is_some() {
+            if let Some(StartGame { new }) = start_game_event_future.await {
                 GamePresenter::<T>::run(
                     this.system_interop.create_game_view(),
                     this.event_bus.clone(),
                     this.runtime_resources.clone(),
                     this.system_interop.clone(),
+                    this.get_saved_game(new),
                 )
                 .await;
             }
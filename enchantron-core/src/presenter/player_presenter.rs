@@ -1,10 +1,13 @@
 use super::EntityPresenter;
 use crate::game::{
-    Direction, EntityMessage, EntityRunBundle, LocationService, Player,
-    PresenterServiceLease, Services, Time,
+    BiomeTerrain, Direction, Entity, EntityMessage, EntityRunBundle,
+    LocationService, ModifiedTerrain, PathfindingService, Player,
+    PresenterServiceLease, TerrainProvider, Time,
 };
 use crate::model::IPoint;
 use crate::view::PlayerView;
+use std::collections::VecDeque;
+use std::sync::Arc;
 use tokio::select;
 use tokio::sync::mpsc::Receiver;
 
@@ -17,7 +20,7 @@ macro_rules! handle_interrupts {
 
         match response {
             Some(Some(val)) => {
-                $this.handle_interrupt(val);
+                $this.handle_interrupt(val).await;
                 continue;
             }
             Some(None) => {
@@ -40,21 +43,30 @@ where
     V: PlayerView,
 {
     view: Option<V>,
+    entity: Entity,
     player: Player,
     view_provider: F,
     time: Time,
     location_service: LocationService,
+    pathfinding_service: PathfindingService,
+    terrain: Arc<ModifiedTerrain<BiomeTerrain>>,
     state: PresenterServiceLease<PlayerPresenterState>,
     interrupts: Receiver<EntityMessage>,
+    /// Remaining steps towards the move target after the current one
+    path: VecDeque<IPoint>,
+    /// Tiles the player is walking from and to in the current step
+    step: Option<(IPoint, IPoint)>,
+    /// Direction the player last walked in, which they keep facing at rest
+    facing: Direction,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct PlayerPresenterState {
     coarse_state: CoarseState,
     move_target: Option<IPoint>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum CoarseState {
     Spawning(f64),
     Idle(f64),
@@ -84,32 +96,106 @@ where
         info!("Creating player presenter");
 
         let EntityRunBundle {
-            entity: _,
+            entity,
             entity_data,
             entity_message_source: interrupts,
             services,
         } = entity_bundle;
 
         PlayerPresenter {
-            view: Some(view_provider()),
+            // The view is created once the view service says the player is
+            // in the viewport
+            view: None,
+            entity,
             player: Player::from(&entity_data),
             view_provider,
             time: services.time(),
             location_service: services.location_service(),
+            pathfinding_service: services.pathfinding_service(),
+            terrain: services.terrain(),
             state,
             interrupts,
+            path: VecDeque::new(),
+            step: None,
+            facing: Direction::SOUTH,
         }
     }
 
-    fn handle_interrupt(&mut self, interrupt: EntityMessage) {
+    async fn handle_interrupt(&mut self, interrupt: EntityMessage) {
         match interrupt {
             EntityMessage::EnteredViewport => {
-                self.view = Some((self.view_provider)())
+                let view = (self.view_provider)();
+
+                view.set_tile(&self.get_tile().await);
+                self.view = Some(view);
             }
             EntityMessage::ExitedViewport => {
                 drop(self.view.take());
             }
-            EntityMessage::GoalSet(target_tile) => {}
+            EntityMessage::GoalSet(target_tile) => {
+                // Any step in progress is finished before heading towards the
+                // new target
+                self.state.move_target = Some(target_tile);
+                self.path.clear();
+            }
+        }
+    }
+
+    async fn get_tile(&self) -> IPoint {
+        self.location_service
+            .get_by_key(&self.player.location_key)
+            .await
+            .unwrap()
+            .top_left
+    }
+
+    /// Get how long walking onto the given tile takes relative to walking on
+    /// grass, or None if the terrain there can't be walked on
+    fn get_step_cost(&self, tile: &IPoint) -> Option<f64> {
+        let properties = self.terrain.get_for(tile).properties();
+
+        if properties.walkable {
+            Some(properties.movement_cost)
+        } else {
+            None
+        }
+    }
+
+    /// Get the tiles to walk from and to for the next step towards the move
+    /// target, planning a new path if there isn't one. The target is cleared
+    /// once it's reached or when there's no way to get any closer to it
+    async fn get_next_step(&mut self) -> Option<(IPoint, IPoint)> {
+        let target = self.state.move_target?;
+        let tile = self.get_tile().await;
+
+        if tile == target {
+            self.state.move_target = None;
+            self.path.clear();
+            return None;
+        }
+
+        if self.path.is_empty() {
+            self.path = self
+                .pathfinding_service
+                .find_path(&self.entity, &tile, &target)
+                .await
+                .into();
+        }
+
+        match self.path.pop_front() {
+            Some(next) if Direction::from_point(&(&next - &tile)).is_some() => {
+                Some((tile, next))
+            }
+            Some(_) => {
+                // The player was moved off the path, so plan a new one next
+                // time
+                self.path.clear();
+                None
+            }
+            None => {
+                self.state.move_target = None;
+                None
+            }
         }
     }
 
@@ -121,60 +207,130 @@ where
 
             match self.state.coarse_state {
                 Spawning(start) => {
-                    self.view.as_ref().map(V::rest);
+                    if let Some(view) = &self.view {
+                        view.rest(self.facing);
+                    }
 
                     interruptible!(self.time.sleep_until(start + 0.5));
 
                     self.state.coarse_state = Idle(self.time.now());
                 }
                 Idle(start) => {
-                    self.view.as_ref().map(V::rest);
+                    if let Some(view) = &self.view {
+                        view.rest(self.facing);
+                    }
 
                     interruptible!(self.time.sleep_until(start + 0.5));
 
-                    self.state.coarse_state = WalkingIn(self.time.now());
+                    self.state.coarse_state = match self.state.move_target {
+                        Some(_) => WalkingIn(self.time.now()),
+                        None => Idle(self.time.now()),
+                    };
                 }
                 WalkingIn(start) => {
-                    let tile = self
-                        .location_service
-                        .get_by_key(&self.player.location_key)
-                        .await
-                        .unwrap()
-                        .top_left;
+                    if self.step.is_none() {
+                        self.step = self.get_next_step().await;
+                    }
+
+                    let (from, to) = match self.step {
+                        Some(step) => step,
+                        None => {
+                            self.state.coarse_state = Idle(self.time.now());
+                            continue;
+                        }
+                    };
+
+                    let movement_cost = match self.get_step_cost(&to) {
+                        Some(cost) => cost,
+                        None => {
+                            self.step = None;
+                            self.path.clear();
+                            self.state.coarse_state = Idle(self.time.now());
+                            continue;
+                        }
+                    };
 
+                    let direction = get_step_direction(&from, &to);
+                    let cost = movement_cost * direction.get_length();
+
+                    self.facing = direction;
                     self.view.as_ref().map(|view| {
-                        view.start_walk(Direction::SOUTH, &tile, start, 0.5)
+                        view.start_walk(
+                            direction,
+                            &from,
+                            start,
+                            0.5 / movement_cost,
+                        )
                     });
 
-                    interruptible!(self.time.sleep_until(start + 1.));
+                    interruptible!(self.time.sleep_until(start + cost));
                     self.state.coarse_state = WalkingOut(self.time.now());
                 }
                 WalkingOut(start) => {
-                    let tile = self
-                        .location_service
-                        .get_by_key(&self.player.location_key)
-                        .await
-                        .unwrap()
-                        .top_left;
-
-                    self.location_service
-                        .move_by_key_delta(
-                            &self.player.location_key,
-                            Direction::SOUTH.get_point(),
-                        )
-                        .await;
+                    let (from, to) = match self.step {
+                        Some(step) => step,
+                        None => {
+                            self.state.coarse_state = Idle(self.time.now());
+                            continue;
+                        }
+                    };
+
+                    // The terrain may have changed since the walk started
+                    let movement_cost = self.get_step_cost(&to);
+
+                    // Interrupts while finishing the walk come back here
+                    // after the player already moved
+                    let moved = self.get_tile().await == to
+                        || self
+                            .location_service
+                            .move_by_key_delta(
+                                &self.player.location_key,
+                                &(&to - &from),
+                            )
+                            .await;
+
+                    let movement_cost = match movement_cost {
+                        Some(cost) if moved => cost,
+                        _ => {
+                            self.step = None;
+                            self.path.clear();
+                            self.state.coarse_state = Idle(self.time.now());
+                            continue;
+                        }
+                    };
+
+                    let direction = get_step_direction(&from, &to);
+                    let cost = movement_cost * direction.get_length();
 
                     self.view.as_ref().map(|view| {
-                        view.finish_walk(Direction::SOUTH, &tile, start, 0.5);
+                        view.finish_walk(
+                            direction,
+                            &from,
+                            start,
+                            0.5 / movement_cost,
+                        );
                     });
-                    interruptible!(self.time.sleep_until(start + 1.));
-                    self.state.coarse_state = Idle(self.time.now());
+                    interruptible!(self.time.sleep_until(start + cost));
+
+                    self.step = None;
+
+                    // Keep walking without resting until the target is reached
+                    self.state.coarse_state = match self.state.move_target {
+                        Some(_) => WalkingIn(self.time.now()),
+                        None => Idle(self.time.now()),
+                    };
                 }
             }
         }
     }
 }
 
+/// Get the direction of a step between adjacent tiles
+fn get_step_direction(from: &IPoint, to: &IPoint) -> Direction {
+    Direction::from_point(&(to - from))
+        .expect("Steps are between adjacent tiles")
+}
+
 impl<F, V> EntityPresenter for PlayerPresenter<F, V>
 where
     F: Fn() -> V + Send + 'static,
@@ -186,3 +342,333 @@ where
         (self.view_provider)()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::{EntityType, SavedGame, Services, TerrainType};
+    use crate::view::EntityView;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::runtime::{Builder, Runtime};
+    use tokio::time::sleep;
+
+    /// Calls made to a player view
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum ViewCall {
+        SetTile(IPoint),
+        Rest(Direction),
+        StartWalk(IPoint, Direction),
+        FinishWalk(IPoint, Direction),
+    }
+
+    /// Player view that records the calls made to it
+    #[derive(Clone, Default)]
+    struct RecordingView(Arc<Mutex<Vec<ViewCall>>>);
+
+    impl RecordingView {
+        fn calls(&self) -> Vec<ViewCall> {
+            self.0.lock().unwrap().clone()
+        }
+
+        fn record(&self, call: ViewCall) {
+            self.0.lock().unwrap().push(call);
+        }
+    }
+
+    impl EntityView for RecordingView {}
+
+    impl PlayerView for RecordingView {
+        fn set_tile(&self, tile: &IPoint) {
+            self.record(ViewCall::SetTile(*tile));
+        }
+
+        fn rest(&self, direction: Direction) {
+            self.record(ViewCall::Rest(direction));
+        }
+
+        fn start_walk(
+            &self,
+            direction: Direction,
+            start_tile: &IPoint,
+            _start_time: f64,
+            _speed: f64,
+        ) {
+            self.record(ViewCall::StartWalk(*start_tile, direction));
+        }
+
+        fn finish_walk(
+            &self,
+            direction: Direction,
+            start_tile: &IPoint,
+            _start_time: f64,
+            _speed: f64,
+        ) {
+            self.record(ViewCall::FinishWalk(*start_tile, direction));
+        }
+    }
+
+    /// Start the player's presenter in a new game where the tiles around the
+    /// player are all grass. The player starts at the origin and can already
+    /// see the view
+    async fn start_player(
+        services: &Services,
+        run_bundles: Vec<EntityRunBundle>,
+    ) -> RecordingView {
+        let terrain_service = services.terrain_service();
+
+        for y in -1..=3 {
+            for x in -1..=4 {
+                terrain_service
+                    .modify(IPoint::new(x, y), Some(TerrainType::Grass));
+            }
+        }
+
+        let run_bundle = run_bundles.into_iter().next().unwrap();
+        let entity = run_bundle.entity;
+        let state = services
+            .presenter_service()
+            .rent_player_presenter_state(&entity)
+            .await
+            .unwrap();
+
+        let view = RecordingView::default();
+        let provided_view = view.clone();
+        let presenter = PlayerPresenter::new(run_bundle, state, move || {
+            provided_view.clone()
+        });
+
+        services.runtime().spawn(presenter.run());
+        services
+            .message_service()
+            .send_message(&entity, EntityMessage::EnteredViewport)
+            .await;
+
+        view
+    }
+
+    async fn get_player_tile(services: &Services) -> IPoint {
+        let player = services.entity_service().get_player();
+
+        services
+            .location_service()
+            .get_by_key(&player.location_key)
+            .await
+            .unwrap()
+            .top_left
+    }
+
+    async fn set_goal(services: &Services, goal: IPoint) {
+        let player = services.entity_service().get_player();
+
+        services
+            .message_service()
+            .send_message(&player.entity, EntityMessage::GoalSet(goal))
+            .await;
+    }
+
+    /// Poll the view's calls until the given condition holds for them or a
+    /// generous timeout passes
+    async fn wait_for_calls(
+        view: &RecordingView,
+        condition: impl Fn(&[ViewCall]) -> bool,
+    ) -> bool {
+        for _ in 0..750 {
+            if condition(&view.calls()) {
+                return true;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        condition(&view.calls())
+    }
+
+    /// Run the given test against a new game, then shut the game down
+    fn with_new_game<F, Fut>(test: F)
+    where
+        F: FnOnce(Services, Vec<EntityRunBundle>) -> Fut,
+        Fut: std::future::Future<Output = ()>,
+    {
+        let app_runtime =
+            Builder::new_current_thread().enable_time().build().unwrap();
+        let game_runtime: Box<Runtime> = Box::new(
+            Builder::new_multi_thread()
+                .worker_threads(1)
+                .enable_time()
+                .build()
+                .unwrap(),
+        );
+
+        let (services, run_bundles, droppers) = Services::new(
+            game_runtime,
+            SavedGame::new(42),
+            &Default::default(),
+        );
+
+        app_runtime.block_on(test(services, run_bundles));
+
+        for dropper in droppers {
+            dropper();
+        }
+    }
+
+    #[test]
+    fn test_follows_path() {
+        with_new_game(|services, run_bundles| async move {
+            let view = start_player(&services, run_bundles).await;
+
+            set_goal(&services, IPoint::new(2, 0)).await;
+
+            assert!(
+                wait_for_calls(&view, |calls| calls.contains(
+                    &ViewCall::FinishWalk(IPoint::new(1, 0), Direction::EAST)
+                ))
+                .await
+            );
+
+            assert_eq!(
+                Some(&ViewCall::SetTile(IPoint::new(0, 0))),
+                view.calls().first()
+            );
+
+            let walks = view
+                .calls()
+                .into_iter()
+                .filter(|call| matches!(call, ViewCall::StartWalk(..)))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                vec![
+                    ViewCall::StartWalk(IPoint::new(0, 0), Direction::EAST),
+                    ViewCall::StartWalk(IPoint::new(1, 0), Direction::EAST),
+                ],
+                walks
+            );
+            assert_eq!(IPoint::new(2, 0), get_player_tile(&services).await);
+        });
+    }
+
+    #[test]
+    fn test_replans_when_moved_off_path() {
+        with_new_game(|services, run_bundles| async move {
+            let view = start_player(&services, run_bundles).await;
+            let goal = IPoint::new(2, 0);
+
+            set_goal(&services, goal).await;
+
+            assert!(
+                wait_for_calls(&view, |calls| calls.contains(
+                    &ViewCall::StartWalk(IPoint::new(0, 0), Direction::EAST)
+                ))
+                .await
+            );
+
+            // Push the player away mid-step. The step still finishes, which
+            // leaves the player two tiles below the path
+            let player = services.entity_service().get_player();
+
+            services
+                .location_service()
+                .move_by_key(&player.location_key, IPoint::new(0, 2))
+                .await;
+
+            let player_reached_goal = wait_for_calls(&view, |calls| {
+                calls.iter().any(|call| match call {
+                    ViewCall::FinishWalk(from, direction) => {
+                        from + direction.get_point() == goal
+                    }
+                    _ => false,
+                })
+            })
+            .await;
+
+            assert!(player_reached_goal);
+            assert!(view.calls().iter().any(|call| matches!(
+                call,
+                ViewCall::StartWalk(from, _) if *from == IPoint::new(1, 2)
+            )));
+            assert_eq!(goal, get_player_tile(&services).await);
+        });
+    }
+
+    #[test]
+    fn test_idles_when_terrain_blocks_step() {
+        with_new_game(|services, run_bundles| async move {
+            let view = start_player(&services, run_bundles).await;
+
+            set_goal(&services, IPoint::new(3, 0)).await;
+
+            assert!(
+                wait_for_calls(&view, |calls| calls.contains(
+                    &ViewCall::StartWalk(IPoint::new(0, 0), Direction::EAST)
+                ))
+                .await
+            );
+
+            // The path floods after it was planned
+            services
+                .terrain_service()
+                .modify(IPoint::new(2, 0), Some(TerrainType::Water));
+
+            let first_step =
+                ViewCall::FinishWalk(IPoint::new(0, 0), Direction::EAST);
+
+            let rested_after_first_step = wait_for_calls(&view, |calls| {
+                calls
+                    .iter()
+                    .skip_while(|call| **call != first_step)
+                    .any(|call| *call == ViewCall::Rest(Direction::EAST))
+            })
+            .await;
+
+            assert!(rested_after_first_step);
+            assert!(!view.calls().contains(&ViewCall::StartWalk(
+                IPoint::new(1, 0),
+                Direction::EAST
+            )));
+            assert_eq!(IPoint::new(1, 0), get_player_tile(&services).await);
+        });
+    }
+
+    #[test]
+    fn test_idles_when_entity_blocks_step() {
+        with_new_game(|services, run_bundles| async move {
+            let view = start_player(&services, run_bundles).await;
+
+            set_goal(&services, IPoint::new(3, 0)).await;
+
+            assert!(
+                wait_for_calls(&view, |calls| calls.contains(
+                    &ViewCall::StartWalk(IPoint::new(0, 0), Direction::EAST)
+                ))
+                .await
+            );
+
+            // Another player steps onto the path after it was planned
+            let _blocker = services
+                .spawn(
+                    EntityType::Player,
+                    IPoint::new(2, 0),
+                    Default::default(),
+                )
+                .await;
+
+            let blocked_step =
+                ViewCall::StartWalk(IPoint::new(1, 0), Direction::EAST);
+
+            let rested_after_blocked_step = wait_for_calls(&view, |calls| {
+                calls
+                    .iter()
+                    .skip_while(|call| **call != blocked_step)
+                    .any(|call| *call == ViewCall::Rest(Direction::EAST))
+            })
+            .await;
+
+            assert!(rested_after_blocked_step);
+            assert!(!view.calls().contains(&ViewCall::FinishWalk(
+                IPoint::new(1, 0),
+                Direction::EAST
+            )));
+            assert_eq!(IPoint::new(1, 0), get_player_tile(&services).await);
+        });
+    }
+}
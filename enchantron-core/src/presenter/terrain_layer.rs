@@ -0,0 +1,917 @@
+use crate::model::{IPoint, IRect, ISize, Point, Rect, UPoint, URect};
+use crate::ui::{
+    HasMutableColor, HasMutableLocation, HasMutableSize, HasMutableVisibility,
+    HasMutableZLevel, Sprite, SpriteSource, TerrainChunkKey, TerrainJob,
+    TerrainTextureProvider, TerrainUpdateInfo, ViewportInfo,
+    AUTOTILE_SIDE_LENGTH,
+};
+use crate::view_types::ViewTypes;
+use std::collections::HashSet;
+use std::iter;
+
+/// Length in pixels of the sides of the flat colored terrain textures. At zoom
+/// level 1 each pixel is one tile, and each zoom level above that doubles the
+/// number of tiles each pixel covers
+pub(super) const TERRAIN_TEXTURE_SIDE_LENGTH: usize = 128;
+const TERRAIN_TEXTURE_SIZE: ISize = ISize {
+    width: TERRAIN_TEXTURE_SIDE_LENGTH,
+    height: TERRAIN_TEXTURE_SIDE_LENGTH,
+};
+
+/// Length in tiles of the sides of the terrain sprites at zoom level 0, where
+/// the terrain is autotiled from the overworld atlas. Autotiled textures need
+/// a full atlas tile for each terrain tile, so these sprites cover less
+/// terrain to keep the textures small
+const DETAIL_SPRITE_LENGTH_IN_TILES: usize = 16;
+const DETAIL_TEXTURE_SIZE: ISize = ISize {
+    width: DETAIL_SPRITE_LENGTH_IN_TILES * AUTOTILE_SIDE_LENGTH,
+    height: DETAIL_SPRITE_LENGTH_IN_TILES * AUTOTILE_SIDE_LENGTH,
+};
+
+/// Get the number of tiles covered by the side of one terrain sprite at the
+/// given zoom level
+pub(super) fn get_sprite_length_in_tiles(zoom_level: usize) -> usize {
+    match zoom_level {
+        0 => DETAIL_SPRITE_LENGTH_IN_TILES,
+        _ => TERRAIN_TEXTURE_SIDE_LENGTH << (zoom_level - 1),
+    }
+}
+
+/// Get the size of the textures drawn for terrain sprites at the given zoom
+/// level
+fn get_texture_size(zoom_level: usize) -> ISize {
+    match zoom_level {
+        0 => DETAIL_TEXTURE_SIZE,
+        _ => TERRAIN_TEXTURE_SIZE,
+    }
+}
+
+/// Get the minimum terrain sprite rect needed to cover the given terrain rect
+/// at the given zoom level as well as the size of the 2-D array of sprites
+/// needed to cover it.
+pub(super) fn get_min_sprite_covering(
+    zoom_level: usize,
+    terrain_rect: &IRect,
+) -> (IRect, ISize) {
+    let sprite_width_in_tiles = get_sprite_length_in_tiles(zoom_level) as i64;
+
+    let mut top_left = IPoint::new(
+        terrain_rect.top_left.x.div_euclid(sprite_width_in_tiles),
+        terrain_rect.top_left.y.div_euclid(sprite_width_in_tiles),
+    );
+
+    // shift the top left up and left by one to include an extra area of
+    // terrain
+    top_left.x -= 1;
+    top_left.y -= 1;
+
+    top_left *= sprite_width_in_tiles;
+
+    let partial_terrain_size =
+        terrain_rect.bottom_right_inclusive() - &top_left;
+
+    // increase the array size by 2 in each dimension to account for inclusivity
+    // and adding an extra boundary of terrain tiles to the top and bottom
+
+    let mut sprite_array_size = partial_terrain_size / sprite_width_in_tiles;
+
+    sprite_array_size.x += 2;
+    sprite_array_size.y += 2;
+
+    let terrain_size = sprite_array_size * sprite_width_in_tiles;
+
+    (
+        IRect {
+            top_left,
+            size: terrain_size.to_size().unwrap(),
+        },
+        sprite_array_size.to_size().unwrap(),
+    )
+}
+
+/// Create a job to generate the given chunk prioritized by the distance between
+/// the chunk's center and the viewport's center
+fn create_job(
+    key: &TerrainChunkKey,
+    viewport_center: &Point,
+    prefetch: bool,
+) -> TerrainJob {
+    let chunk_center = Point::from(key.terrain_rect.center());
+
+    TerrainJob {
+        key: *key,
+        prefetch,
+        distance: chunk_center.distance_squared_to(viewport_center) as u64,
+    }
+}
+
+/// Wrapping 2-D array of terrain sprites that covers the viewport at a single
+/// zoom level. Sprites are only re-textured when they scroll into view or the
+/// zoom level of the layer changes. Sprites whose textures aren't cached stay
+/// hidden until the terrain workers generate them
+pub(super) struct TerrainLayer<T: ViewTypes> {
+    sprite_group: T::SpriteGroup,
+    terrain_sprites: Vec<Vec<T::Sprite>>,
+    terrain_sprites_size: ISize,
+    sprite_terrain_coverage: IRect,
+    top_left_sprite: UPoint,
+    zoom_level: usize,
+    alpha: u8,
+    /// Chunks that sprites in the layer are waiting on the terrain workers for
+    pending_chunks: HashSet<TerrainChunkKey>,
+}
+
+impl<T> TerrainLayer<T>
+where
+    T: ViewTypes,
+{
+    pub fn new(sprite_group: T::SpriteGroup) -> TerrainLayer<T> {
+        TerrainLayer {
+            sprite_group,
+            terrain_sprites: Default::default(),
+            terrain_sprites_size: Default::default(),
+            sprite_terrain_coverage: Default::default(),
+            top_left_sprite: Default::default(),
+            zoom_level: 0,
+            alpha: u8::MAX,
+            pending_chunks: Default::default(),
+        }
+    }
+
+    pub fn zoom_level(&self) -> usize {
+        self.zoom_level
+    }
+
+    pub fn set_z_level(&self, z_level: f64) {
+        self.sprite_group.set_z_level(z_level);
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.sprite_group.set_visible(visible);
+    }
+
+    /// Set the opacity of all the sprites in the layer
+    pub fn set_alpha(&mut self, alpha: f64) {
+        let alpha = (alpha.clamp(0., 1.) * u8::MAX as f64).round() as u8;
+
+        if alpha == self.alpha {
+            return;
+        }
+
+        self.alpha = alpha;
+
+        self.terrain_sprites
+            .iter()
+            .flat_map(|row| row.iter())
+            .for_each(|sprite| sprite.set_8_bit_color(255, 255, 255, alpha));
+    }
+
+    /// Update the sprites in the layer to cover the given viewport at the
+    /// given zoom level
+    pub fn update(
+        &mut self,
+        viewport_info: &ViewportInfo,
+        zoom_level: usize,
+        terrain_texture_provider: &TerrainTextureProvider<T>,
+    ) {
+        let terrain_update_info =
+            self.terrain_updates_required(viewport_info, zoom_level);
+
+        // Sprites at zoom level 0 cover much less terrain than the other zoom
+        // levels, so the sprite array is trimmed to what the new zoom level
+        // needs instead of being kept at its largest size
+        if terrain_update_info.zoom_level_changed {
+            self.decrease_size_to(&terrain_update_info.sprite_array_size);
+        }
+
+        let valid_sprite_rect = {
+            let size_increased = self.check_sprite_array_size_increased(
+                &terrain_update_info.sprite_array_size,
+            );
+
+            if size_increased {
+                debug!("Size increased");
+                self.increase_size_for(terrain_update_info)
+            } else {
+                debug!("Size not increased");
+                let (top_left, new_valid_rect) = self
+                    .calculate_new_valid_sprites(&terrain_update_info)
+                    .unwrap_or_default();
+
+                self.update_terrain_sprite_info(terrain_update_info, top_left);
+
+                new_valid_rect
+            }
+        };
+
+        let sprite_length_in_tiles = get_sprite_length_in_tiles(zoom_level);
+        let texture_size = get_texture_size(zoom_level);
+        let sprite_width = sprite_length_in_tiles as f64;
+        let alpha = self.alpha;
+        let mut missing_chunks = Vec::new();
+
+        self.update_terrain_sprites(valid_sprite_rect, |sprite, point| {
+            sprite.set_visible(false);
+            sprite.set_size(sprite_width, sprite_width);
+            sprite.set_8_bit_color(255, 255, 255, alpha);
+
+            let key = TerrainChunkKey {
+                terrain_rect: IRect {
+                    top_left: *point,
+                    size: ISize::new(
+                        sprite_length_in_tiles,
+                        sprite_length_in_tiles,
+                    ),
+                },
+                texture_size,
+            };
+
+            match terrain_texture_provider.get_cached_texture(&key) {
+                Some(texture) => {
+                    sprite.set_texture(&texture);
+                    sprite.set_visible(true);
+                }
+                None => missing_chunks.push(key),
+            }
+        });
+
+        // Chunks that scrolled out of the layer or were drawn for a different
+        // zoom level are no longer needed
+        let coverage = self.sprite_terrain_coverage;
+
+        self.pending_chunks.retain(|key| {
+            key.terrain_rect.size.width == sprite_length_in_tiles
+                && coverage.contains_point(&key.terrain_rect.top_left)
+        });
+        self.pending_chunks.extend(missing_chunks);
+    }
+
+    /// Mark the chunks in this layer that show any of the tiles in the given
+    /// rect as waiting for new textures. The sprites keep showing their old
+    /// textures until the new ones are generated
+    pub fn invalidate(&mut self, rect: &IRect) {
+        // Autotiled chunks show the edges of the tiles around them too
+        let affected_rect = rect.expanded_by(1);
+        let coverage = self.sprite_terrain_coverage;
+
+        let invalid_rect = match coverage.intersection(&affected_rect) {
+            Some(invalid_rect) => invalid_rect,
+            None => return,
+        };
+
+        let sprite_length_in_tiles =
+            get_sprite_length_in_tiles(self.zoom_level);
+        let texture_size = get_texture_size(self.zoom_level);
+        let tiles_per_sprite = sprite_length_in_tiles as i64;
+
+        let first =
+            (&invalid_rect.top_left - &coverage.top_left) / tiles_per_sprite;
+        let last = (invalid_rect.bottom_right_inclusive() - &coverage.top_left)
+            / tiles_per_sprite;
+
+        for y in first.y..=last.y {
+            for x in first.x..=last.x {
+                self.pending_chunks.insert(TerrainChunkKey {
+                    terrain_rect: IRect {
+                        top_left: &coverage.top_left
+                            + &IPoint::new(
+                                x * tiles_per_sprite,
+                                y * tiles_per_sprite,
+                            ),
+                        size: ISize::new(
+                            sprite_length_in_tiles,
+                            sprite_length_in_tiles,
+                        ),
+                    },
+                    texture_size,
+                });
+            }
+        }
+    }
+
+    /// Get the jobs needed to generate the textures for the sprites in this
+    /// layer that are still waiting for them
+    pub fn pending_jobs<'a>(
+        &'a self,
+        viewport_center: &'a Point,
+    ) -> impl Iterator<Item = TerrainJob> + 'a {
+        self.pending_chunks
+            .iter()
+            .map(move |key| create_job(key, viewport_center, false))
+    }
+
+    /// Get the jobs to generate the chunks at this layer's zoom level that
+    /// would be needed to cover the given rect but aren't covered by the
+    /// layer yet
+    pub fn prefetch_jobs(
+        &self,
+        lookahead_rect: &Rect,
+        viewport_center: &Point,
+    ) -> Vec<TerrainJob> {
+        let terrain_rect = self.viewport_rect_to_terrain_rect(lookahead_rect);
+        let (covering_rect, sprite_array_size) =
+            get_min_sprite_covering(self.zoom_level, &terrain_rect);
+        let sprite_length_in_tiles =
+            get_sprite_length_in_tiles(self.zoom_level);
+        let texture_size = get_texture_size(self.zoom_level);
+
+        let mut result = Vec::new();
+
+        for y in 0..sprite_array_size.height {
+            for x in 0..sprite_array_size.width {
+                let top_left = &covering_rect.top_left
+                    + &IPoint::new(
+                        (x * sprite_length_in_tiles) as i64,
+                        (y * sprite_length_in_tiles) as i64,
+                    );
+
+                if self.sprite_terrain_coverage.contains_point(&top_left) {
+                    continue;
+                }
+
+                let key = TerrainChunkKey {
+                    terrain_rect: IRect {
+                        top_left,
+                        size: ISize::new(
+                            sprite_length_in_tiles,
+                            sprite_length_in_tiles,
+                        ),
+                    },
+                    texture_size,
+                };
+
+                result.push(create_job(&key, viewport_center, true));
+            }
+        }
+
+        result
+    }
+
+    /// Show the given texture generated for the given chunk if a sprite in this
+    /// layer is waiting for it. Returns true if the texture was used
+    pub fn on_texture_generated(
+        &mut self,
+        key: &TerrainChunkKey,
+        texture: &T::Texture,
+    ) -> bool {
+        if !self.pending_chunks.remove(key) {
+            return false;
+        }
+
+        let sprite = self
+            .get_sprite_for_terrain_point(&key.terrain_rect.top_left)
+            .expect("Pending terrain chunk outside of the layer");
+
+        sprite.set_texture(texture);
+        sprite.set_visible(true);
+
+        true
+    }
+
+    /// Get the sprite that shows the terrain starting at the given point if
+    /// the point is covered by the layer
+    fn get_sprite_for_terrain_point(
+        &self,
+        terrain_point: &IPoint,
+    ) -> Option<&T::Sprite> {
+        if !self.sprite_terrain_coverage.contains_point(terrain_point) {
+            return None;
+        }
+
+        let mut offset = terrain_point - &self.sprite_terrain_coverage.top_left;
+
+        offset /= get_sprite_length_in_tiles(self.zoom_level) as i64;
+
+        self.get_sprite(&self.sprite_grid_add(&self.top_left_sprite, &offset))
+    }
+
+    /// Update the invalid terrain sprites
+    fn update_terrain_sprites(
+        &self,
+        new_valid_rect: URect,
+        mut sprite_updater: impl FnMut(&T::Sprite, &IPoint),
+    ) {
+        trace!("Updating terrain sprites");
+
+        // hit all the partial rows to the right of the valid region
+
+        let new_valid_size = &new_valid_rect.size;
+        let valid_top_left = &new_valid_rect.top_left;
+
+        let mut action = |x: usize, y: usize| {
+            let (sprite, terrain_point) =
+                self.get_sprite_at(valid_top_left, &x, &y);
+            sprite.set_location_point(&terrain_point.into());
+            sprite_updater(sprite, &terrain_point);
+        };
+
+        for y in 0..new_valid_size.height {
+            for x in new_valid_size.width..self.terrain_sprites_size.width {
+                action(x, y);
+            }
+        }
+
+        // hit all the complete rows below the valid region
+
+        for y in new_valid_size.height..self.terrain_sprites_size.height {
+            for x in 0..self.terrain_sprites_size.width {
+                action(x, y);
+            }
+        }
+    }
+
+    /// Get the terrain rect required to cover the given viewport rect based on
+    /// the current size of the terrain sprites array.
+    fn viewport_rect_to_terrain_rect(&self, viewport_rect: &Rect) -> IRect {
+        let viewport_top_left = &viewport_rect.top_left;
+        let viewport_bottom_right = viewport_top_left + &viewport_rect.size;
+
+        let top_left = IPoint {
+            x: viewport_top_left.x.floor() as i64,
+            y: viewport_top_left.y.floor() as i64,
+        };
+
+        let bottom_right = IPoint {
+            x: viewport_bottom_right.x.ceil() as i64,
+            y: viewport_bottom_right.y.ceil() as i64,
+        };
+
+        let size = (bottom_right - &top_left).to_size().expect("bad size");
+
+        IRect { top_left, size }
+    }
+
+    /// return true if the current size of the 2d vector array is bigger than
+    /// or equal to the size given in both height and width
+    fn check_sprite_array_size_increased(
+        &self,
+        min_sprite_array_size: &ISize,
+    ) -> bool {
+        min_sprite_array_size.width > self.terrain_sprites_size.width
+            || min_sprite_array_size.height > self.terrain_sprites_size.height
+    }
+
+    /// Determine the terrain the layer needs to cover for the given viewport
+    /// at the given zoom level
+    fn terrain_updates_required(
+        &self,
+        viewport_info: &ViewportInfo,
+        zoom_level: usize,
+    ) -> TerrainUpdateInfo {
+        let viewport_rect = &viewport_info.viewport_rect;
+
+        let terrain_rect = self.viewport_rect_to_terrain_rect(viewport_rect);
+
+        let (min_covered_terrain, mut min_sprite_array_size) =
+            get_min_sprite_covering(zoom_level, &terrain_rect);
+
+        let zoom_level_changed = zoom_level != self.zoom_level;
+
+        // If the minimum size of the sprite array is less than the current
+        // size of the sprite array in either dimension, keep using the whole
+        // sprite array unless the zoom level changed
+        if !zoom_level_changed {
+            if min_sprite_array_size.width <= self.terrain_sprites_size.width {
+                min_sprite_array_size.width = self.terrain_sprites_size.width;
+            }
+
+            if min_sprite_array_size.height <= self.terrain_sprites_size.height
+            {
+                min_sprite_array_size.height = self.terrain_sprites_size.height;
+            }
+        }
+
+        TerrainUpdateInfo {
+            zoom_level,
+            terrain_rect: min_covered_terrain,
+            sprite_length_in_tiles: get_sprite_length_in_tiles(zoom_level),
+            sprite_array_size: min_sprite_array_size,
+            zoom_level_changed,
+        }
+    }
+
+    /// Increase the size of the 2d array of terrain sprites to accommodate the
+    /// given size
+    fn increase_size_for(
+        &mut self,
+        terrain_update_info: TerrainUpdateInfo,
+    ) -> URect {
+        let min_sprite_array_size = &terrain_update_info.sprite_array_size;
+
+        debug!(
+            "Increasing terrain sprites cache to {:?}",
+            min_sprite_array_size
+        );
+
+        let width_inc = min_sprite_array_size
+            .width
+            .saturating_sub(self.terrain_sprites_size.width);
+        let height_inc = min_sprite_array_size
+            .height
+            .saturating_sub(self.terrain_sprites_size.height);
+
+        if width_inc > 0 {
+            self.increase_row_width_by(width_inc);
+        }
+
+        if height_inc > 0 {
+            self.increase_row_count_by(height_inc);
+        }
+
+        let (new_top_left, valid_rect) = self
+            .calculate_new_valid_sprites(&terrain_update_info)
+            .unwrap_or_default();
+
+        self.update_terrain_sprite_info(terrain_update_info, new_top_left);
+
+        valid_rect
+    }
+
+    /// Add the two points together within the bounds of the sprite grid system
+    fn sprite_grid_add(&self, lhs: &UPoint, rhs: &IPoint) -> UPoint {
+        let sprite_grid_width = self.terrain_sprites_size.width as i64;
+        let sprite_grid_height = self.terrain_sprites_size.height as i64;
+
+        UPoint::new(
+            (lhs.x as i64 + rhs.x).rem_euclid(sprite_grid_width) as usize,
+            (lhs.y as i64 + rhs.y).rem_euclid(sprite_grid_height) as usize,
+        )
+    }
+
+    /// Determine which portion if any of the existing valid terrain sprites will
+    /// remain valid when the viewport shifts to the given terrain rect, and
+    /// return the new top-left point within the sprite array, and the region of
+    /// the sprite array that's still valid
+    fn calculate_new_valid_sprites(
+        &self,
+        terrain_update_info: &TerrainUpdateInfo,
+    ) -> Option<(UPoint, URect)> {
+        // A change in zoom level means that none of the sprites are valid
+        if terrain_update_info.zoom_level_changed {
+            return None;
+        }
+
+        let new_terrain_rect = &terrain_update_info.terrain_rect;
+        let tiles_per_sprite = terrain_update_info.sprite_length_in_tiles;
+
+        self.sprite_terrain_coverage
+            .intersection(new_terrain_rect)
+            .map(|itx_rect| {
+                // Determine the top left and size of the valid region
+                let mut valid_top_left_shift =
+                    &itx_rect.top_left - &self.sprite_terrain_coverage.top_left;
+
+                valid_top_left_shift /= tiles_per_sprite as i64;
+
+                let valid_sprites_top_left = self.sprite_grid_add(
+                    &self.top_left_sprite,
+                    &valid_top_left_shift,
+                );
+
+                let new_valid_rect = URect {
+                    top_left: valid_sprites_top_left,
+                    size: itx_rect.size / tiles_per_sprite,
+                };
+
+                // determine the new top left of the viewport rect
+
+                let mut new_top_left_shift = &new_terrain_rect.top_left
+                    - &self.sprite_terrain_coverage.top_left;
+
+                new_top_left_shift /= tiles_per_sprite as i64;
+
+                let new_top_left = self.sprite_grid_add(
+                    &self.top_left_sprite,
+                    &new_top_left_shift,
+                );
+
+                trace!("valid sprite area = {}", new_valid_rect.area());
+
+                (new_top_left, new_valid_rect)
+            })
+    }
+
+    /// Update the information about the terrain sprites that describes which part
+    /// of the terrain is being shown and where in the sprite array the top-left
+    /// corner is
+    fn update_terrain_sprite_info(
+        &mut self,
+        update_info: TerrainUpdateInfo,
+        top_left_sprite: UPoint,
+    ) {
+        self.top_left_sprite = top_left_sprite;
+        self.sprite_terrain_coverage = update_info.terrain_rect;
+        self.zoom_level = update_info.zoom_level;
+    }
+
+    /// Increase the size of all the existing rows in the terrain to the given
+    /// width
+    fn increase_row_width_by(&mut self, cols_to_add: usize) {
+        let insert_point = self.top_left_sprite.x;
+        let sprite_group = &self.sprite_group;
+
+        let sprite_source = || {
+            let result = sprite_group.create_sprite();
+            result.set_visible(true);
+            result
+        };
+
+        self.terrain_sprites.iter_mut().for_each(|row| {
+            let to_insert = iter::repeat_with(sprite_source).take(cols_to_add);
+
+            row.splice(insert_point..insert_point, to_insert)
+                .for_each(|_| {});
+        });
+
+        self.top_left_sprite.x += cols_to_add;
+        self.terrain_sprites_size.width += cols_to_add;
+    }
+
+    /// Remove rows and columns from the sprite array until it's no bigger than
+    /// the given size. This is only done when the zoom level changes, so none
+    /// of the remaining sprites need to keep their textures
+    fn decrease_size_to(&mut self, max_size: &ISize) {
+        let width = self.terrain_sprites_size.width.min(max_size.width);
+        let height = self.terrain_sprites_size.height.min(max_size.height);
+
+        if width == self.terrain_sprites_size.width
+            && height == self.terrain_sprites_size.height
+        {
+            return;
+        }
+
+        debug!("Decreasing terrain sprites cache to {}x{}", width, height);
+
+        self.terrain_sprites
+            .drain(height..)
+            .flatten()
+            .for_each(|sprite| sprite.remove_from_parent());
+
+        self.terrain_sprites.iter_mut().for_each(|row| {
+            row.drain(width..)
+                .for_each(|sprite| sprite.remove_from_parent())
+        });
+
+        self.terrain_sprites_size = ISize::new(width, height);
+        self.top_left_sprite = UPoint::default();
+    }
+
+    /// Increase the number of rows in the sprite array by the given number
+    fn increase_row_count_by(&mut self, rows_to_add: usize) {
+        let total_columns = self.terrain_sprites_size.width;
+        let sprite_group = &self.sprite_group;
+
+        let sprite_source = || {
+            let result = sprite_group.create_sprite();
+            result.set_visible(true);
+            result
+        };
+
+        let new_rows = iter::repeat_with(|| {
+            iter::repeat_with(sprite_source)
+                .take(total_columns)
+                .collect()
+        })
+        .take(rows_to_add);
+
+        let insert_point = &self.top_left_sprite.y;
+
+        self.terrain_sprites
+            .splice(insert_point..insert_point, new_rows);
+
+        self.top_left_sprite.y += rows_to_add;
+        self.terrain_sprites_size.height += rows_to_add;
+    }
+
+    fn get_sprite<'a>(&'a self, point: &UPoint) -> Option<&'a T::Sprite> {
+        self.terrain_sprites
+            .get(point.y)
+            .and_then(|row| row.get(point.x))
+    }
+
+    /// Assume that the given point p is a point within the bounds of the
+    /// sprite grid, get the offset from origin of the sprite grid (the top left)
+    fn sprite_grid_offset_from_origin(&self, p: &UPoint) -> IPoint {
+        let grid_width = self.terrain_sprites_size.width as i64;
+        let grid_height = self.terrain_sprites_size.height as i64;
+
+        IPoint::new(
+            (p.x as i64 - self.top_left_sprite.x as i64).rem_euclid(grid_width),
+            (p.y as i64 - self.top_left_sprite.y as i64)
+                .rem_euclid(grid_height),
+        )
+    }
+
+    /// Get the sprite at the given point in the sprite grid using the given natural
+    /// origin.  Also return the terrain coordinates of the sprite based on the
+    /// configured top-left sprite in the grid and the sprite-terrain coverage
+    fn get_sprite_at<'a>(
+        &'a self,
+        natural_origin: &UPoint,
+        natural_x: &usize,
+        natural_y: &usize,
+    ) -> (&'a T::Sprite, IPoint) {
+        let real_point = UPoint::new(
+            (natural_origin.x + natural_x) % self.terrain_sprites_size.width,
+            (natural_origin.y + natural_y) % self.terrain_sprites_size.height,
+        );
+
+        let sprite = self.get_sprite(&real_point).unwrap_or_else(|| {
+            error!("Invalid sprite_coordinate {:?}", real_point);
+            panic!("Index out of bounds error in terrain sprites array");
+        });
+
+        let offset = self.sprite_grid_offset_from_origin(&real_point)
+            * (get_sprite_length_in_tiles(self.zoom_level) as i64);
+
+        let terrain_point = &self.sprite_terrain_coverage.top_left + &offset;
+
+        (sprite, terrain_point)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::application_context::Ao;
+    use crate::headless::{
+        HeadlessNativeView, HeadlessResourceLoader, HeadlessTexture,
+        HeadlessViewTypes,
+    };
+    use crate::native::{Animations, RuntimeResources, Textures};
+
+    /// Create a texture provider along with the runtime resources it refers
+    /// to, which must be kept alive as long as the provider is used
+    fn create_provider() -> (
+        Box<RuntimeResources<HeadlessViewTypes>>,
+        TerrainTextureProvider<HeadlessViewTypes>,
+    ) {
+        let resource_loader = HeadlessResourceLoader::default();
+        let textures =
+            Textures::<HeadlessViewTypes>::new(&resource_loader, &|_| {});
+        let animations = Animations::new(&resource_loader, &textures);
+        let runtime_resources = Box::new(RuntimeResources::new(
+            textures,
+            animations,
+            Default::default(),
+        ));
+
+        let provider = TerrainTextureProvider::new(
+            Ao::new(&runtime_resources),
+            resource_loader,
+            0,
+            Default::default(),
+        );
+
+        (runtime_resources, provider)
+    }
+
+    fn create_texture() -> HeadlessTexture {
+        HeadlessTexture::from_rgba(
+            "chunk".to_owned(),
+            ISize::new(1, 1),
+            vec![0, 0, 0, 255],
+        )
+    }
+
+    fn viewport(x: f64, y: f64, width: f64, height: f64) -> ViewportInfo {
+        let mut viewport_info = ViewportInfo::default();
+        viewport_info.viewport_rect = Rect::new(x, y, width, height);
+        viewport_info
+    }
+
+    /// Get the chunks the layer is waiting on ordered by row and then column
+    fn pending_keys(
+        layer: &TerrainLayer<HeadlessViewTypes>,
+    ) -> Vec<TerrainChunkKey> {
+        let mut result = layer
+            .pending_jobs(&Point::default())
+            .map(|job| job.key)
+            .collect::<Vec<_>>();
+
+        result.sort_by_key(|key| {
+            (key.terrain_rect.top_left.y, key.terrain_rect.top_left.x)
+        });
+
+        result
+    }
+
+    fn pending_top_lefts(
+        layer: &TerrainLayer<HeadlessViewTypes>,
+    ) -> Vec<IPoint> {
+        pending_keys(layer)
+            .iter()
+            .map(|key| key.terrain_rect.top_left)
+            .collect()
+    }
+
+    /// Hand the layer a texture for each of the chunks it's waiting on
+    fn generate_pending(
+        layer: &mut TerrainLayer<HeadlessViewTypes>,
+        texture: &HeadlessTexture,
+    ) {
+        for key in pending_keys(layer) {
+            assert!(layer.on_texture_generated(&key, texture));
+        }
+    }
+
+    #[test]
+    fn test_invalidate_tile_on_sprite_boundary() {
+        let (_runtime_resources, provider) = create_provider();
+        let view = HeadlessNativeView::new();
+        let texture = create_texture();
+        let mut layer = TerrainLayer::new(view.create_group());
+
+        // The viewport is covered by 4 x 4 sprites of 16 x 16 tiles starting
+        // a sprite up and left of the origin, and none of them are cached
+        layer.update(&viewport(0., 0., 20., 20.), 0, &provider);
+
+        assert_eq!(16, pending_keys(&layer).len());
+        assert!(view.scene().visible_sprites().is_empty());
+
+        generate_pending(&mut layer, &texture);
+
+        assert!(pending_keys(&layer).is_empty());
+        assert_eq!(16, view.scene().visible_sprites().len());
+
+        // The edge of a tile is drawn in the autotiled sprite next to it too
+        layer.invalidate(&IRect::new(15, 3, 1, 1));
+
+        assert_eq!(
+            vec![IPoint::new(0, 0), IPoint::new(16, 0)],
+            pending_top_lefts(&layer)
+        );
+
+        // The sprites keep their old textures in the meantime
+        assert_eq!(16, view.scene().visible_sprites().len());
+
+        generate_pending(&mut layer, &texture);
+
+        // so a tile on a sprite corner touches four sprites
+        layer.invalidate(&IRect::new(16, 16, 1, 1));
+
+        assert_eq!(
+            vec![
+                IPoint::new(0, 0),
+                IPoint::new(16, 0),
+                IPoint::new(0, 16),
+                IPoint::new(16, 16)
+            ],
+            pending_top_lefts(&layer)
+        );
+
+        generate_pending(&mut layer, &texture);
+
+        // Only the part inside the layer is invalidated
+        layer.invalidate(&IRect::new(-17, -17, 1, 1));
+
+        assert_eq!(vec![IPoint::new(-16, -16)], pending_top_lefts(&layer));
+
+        generate_pending(&mut layer, &texture);
+
+        layer.invalidate(&IRect::new(100, 100, 4, 4));
+
+        assert!(pending_keys(&layer).is_empty());
+    }
+
+    #[test]
+    fn test_texture_generated_after_layer_changed() {
+        let (_runtime_resources, provider) = create_provider();
+        let view = HeadlessNativeView::new();
+        let texture = create_texture();
+        let mut layer = TerrainLayer::new(view.create_group());
+
+        layer.update(&viewport(0., 0., 20., 20.), 0, &provider);
+
+        let detail_keys = pending_keys(&layer);
+
+        layer.update(&viewport(0., 0., 200., 200.), 1, &provider);
+
+        assert_eq!(1, layer.zoom_level());
+
+        // Textures still on their way for the old zoom level are ignored
+        for key in &detail_keys {
+            assert!(!layer.on_texture_generated(key, &texture));
+        }
+
+        let coarse_keys = pending_keys(&layer);
+
+        assert_eq!(16, coarse_keys.len());
+        assert!(coarse_keys
+            .iter()
+            .all(|key| key.terrain_rect.size == ISize::new(128, 128)));
+
+        // and so are textures for chunks that scrolled out of the layer
+        layer.update(&viewport(5000., 5000., 200., 200.), 1, &provider);
+
+        for key in &coarse_keys {
+            assert!(!layer.on_texture_generated(key, &texture));
+        }
+
+        assert!(view.scene().visible_sprites().is_empty());
+
+        generate_pending(&mut layer, &texture);
+
+        assert_eq!(16, view.scene().visible_sprites().len());
+    }
+}
@@ -1,74 +1,124 @@
+use super::terrain_layer::{get_sprite_length_in_tiles, TerrainLayer};
 use crate::application_context::Ao;
 use crate::event::*;
-use crate::game::constants;
-use crate::model::{IPoint, IRect, ISize, Rect, UPoint, URect};
+use crate::game::{constants, TerrainService};
+use crate::model::{IPoint, IRect, ISize, Point, Rect};
 use crate::native::{RuntimeResources, SystemInterop};
 use crate::ui::{
-    HasMutableLocation, HasMutableSize, HasMutableVisibility, Sprite,
-    SpriteSource, TerrainTextureProvider, TerrainUpdateInfo, ViewportInfo,
+    SpriteSource, TerrainChunkKey, TerrainTextureProvider, ViewportInfo,
 };
 use crate::view_types::ViewTypes;
 use futures::pin_mut;
-use std::iter;
+use std::time::Instant;
 use tokio::select;
 use tokio::stream::StreamExt;
+use tokio::sync::mpsc::UnboundedReceiver;
 
-const TERRAIN_TEXTURE_SIDE_LENGTH: usize = 128;
-const TERRAIN_TEXTURE_SIDE_LENGTH_F64: f64 = TERRAIN_TEXTURE_SIDE_LENGTH as f64;
-const TERRAIN_TEXTURE_SIZE: ISize = ISize {
-    width: TERRAIN_TEXTURE_SIDE_LENGTH,
-    height: TERRAIN_TEXTURE_SIDE_LENGTH,
-};
+const LAYER_COUNT: usize = 2;
+const MAX_ZOOM_LEVEL: usize = 13;
+
+/// Viewport scale (tiles per point) at which the autotiled terrain drawn at
+/// zoom level 0 is replaced by zoom level 1. Each zoom level after that
+/// doubles the scale
+const ZOOM_LEVEL_1_VIEWPORT_SCALE: f64 = 3. / constants::TILE_SCALE;
 
-/// Get the minimum terrain sprite rect needed to cover the given terrain rect
-/// at the given zoom level as well as the size of the 2-D array of sprites
-/// needed to cover it.
-fn get_min_sprite_covering(terrain_rect: &IRect) -> (IRect, ISize) {
-    let sprite_width_in_tiles = TERRAIN_TEXTURE_SIDE_LENGTH as i64;
+/// Width of the range of fractional zoom levels below each zoom level where it
+/// fades in over the zoom level below it
+const CROSS_FADE_WIDTH: f64 = 0.35;
 
-    let mut top_left = IPoint::new(
-        terrain_rect.top_left.x.div_euclid(sprite_width_in_tiles),
-        terrain_rect.top_left.y.div_euclid(sprite_width_in_tiles),
-    );
+/// Seconds of panning at the current velocity to prefetch terrain for
+const PREFETCH_LOOKAHEAD_SECONDS: f64 = 0.5;
 
-    // shift the top left up and left by one to include an extra area of
-    // terrain
-    top_left.x -= 1;
-    top_left.y -= 1;
+/// Weight given to the newest sample when smoothing the pan velocity
+const PAN_VELOCITY_SMOOTHING: f64 = 0.5;
 
-    top_left *= sprite_width_in_tiles;
+/// Viewport changes further apart than this start a new pan
+const MAX_PAN_SAMPLE_INTERVAL_SECONDS: f64 = 0.25;
 
-    let partial_terrain_size =
-        terrain_rect.bottom_right_inclusive() - &top_left;
+const BACKGROUND_Z_LEVEL: f64 = constants::TERRAIN_Z_LEVEL;
+const FOREGROUND_Z_LEVEL: f64 = constants::TERRAIN_Z_LEVEL + 1.;
 
-    // increase the array size by 2 in each dimension to account for inclusivity
-    // and adding an extra boundary of terrain tiles to the top and bottom
+/// Get the fractional zoom level for the given viewport scale. The integer
+/// part is the zoom level the terrain should be drawn at
+fn get_fractional_zoom_level(viewport_scale: f64) -> f64 {
+    let frac_zoom_level =
+        (viewport_scale / ZOOM_LEVEL_1_VIEWPORT_SCALE).log2() + 1.;
 
-    let mut sprite_array_size = partial_terrain_size / sprite_width_in_tiles;
+    frac_zoom_level.clamp(0., MAX_ZOOM_LEVEL as f64)
+}
 
-    sprite_array_size.x += 2;
-    sprite_array_size.y += 2;
+/// Get the zoom level to draw opaquely for the given fractional zoom level
+/// along with the next zoom level and its opacity if the fractional zoom is
+/// close enough to that level that it should be fading in
+fn get_visible_zoom_levels(
+    frac_zoom_level: f64,
+) -> (usize, Option<(usize, f64)>) {
+    let zoom_level = (frac_zoom_level.floor() as usize).min(MAX_ZOOM_LEVEL);
+    let next_zoom_level = zoom_level + 1;
+
+    if next_zoom_level > MAX_ZOOM_LEVEL {
+        return (zoom_level, None);
+    }
 
-    let terrain_size = sprite_array_size * sprite_width_in_tiles;
+    let fade_progress = (frac_zoom_level - next_zoom_level as f64
+        + CROSS_FADE_WIDTH)
+        / CROSS_FADE_WIDTH;
+
+    if fade_progress <= 0. {
+        (zoom_level, None)
+    } else {
+        (zoom_level, Some((next_zoom_level, fade_progress.min(1.))))
+    }
+}
 
-    (
-        IRect {
-            top_left,
-            size: terrain_size.to_size().unwrap(),
-        },
-        sprite_array_size.to_size().unwrap(),
+/// Blend the velocity of the move between the two given viewport centers into
+/// the given pan velocity (tiles per second)
+fn smooth_pan_velocity(
+    velocity: &Point,
+    last_center: &Point,
+    center: &Point,
+    elapsed_seconds: f64,
+) -> Point {
+    if elapsed_seconds <= 0. {
+        return *velocity;
+    }
+
+    let sample = (center - last_center) * (1. / elapsed_seconds);
+
+    if elapsed_seconds > MAX_PAN_SAMPLE_INTERVAL_SECONDS {
+        return sample;
+    }
+
+    velocity * (1. - PAN_VELOCITY_SMOOTHING) + sample * PAN_VELOCITY_SMOOTHING
+}
+
+/// Get how far ahead of the viewport to prefetch terrain at the given zoom
+/// level for the given pan velocity. The offset is limited to one sprite in
+/// each direction to bound the amount of prefetching
+fn get_prefetch_offset(velocity: &Point, zoom_level: usize) -> Point {
+    let max_offset = get_sprite_length_in_tiles(zoom_level) as f64;
+    let offset = velocity * PREFETCH_LOOKAHEAD_SECONDS;
+
+    Point::new(
+        offset.x.clamp(-max_offset, max_offset),
+        offset.y.clamp(-max_offset, max_offset),
     )
 }
 
+enum TerrainPresenterEvent<X> {
+    ViewportChanged(ViewportInfo),
+    TextureGenerated(TerrainChunkKey, X),
+    TerrainModified(IPoint),
+}
+
 pub struct TerrainPresenter<T: ViewTypes> {
     event_bus: EventBus,
     terrain_texture_provider: TerrainTextureProvider<T>,
+    terrain_modifications: UnboundedReceiver<IPoint>,
     listener_registrations: Vec<ListenerRegistration>,
-    sprite_group: T::SpriteGroup,
-    terrain_sprites: Vec<Vec<T::Sprite>>,
-    terrain_sprites_size: ISize,
-    sprite_terrain_coverage: IRect,
-    top_left_sprite: UPoint,
+    layers: [TerrainLayer<T>; LAYER_COUNT],
+    last_viewport_center: Option<(Point, Instant)>,
+    pan_velocity: Point,
 }
 
 impl<T> TerrainPresenter<T>
@@ -80,24 +130,31 @@ where
         sprite_source: &S,
         runtime_resources: Ao<RuntimeResources<T>>,
         system_interop: Ao<T::SystemInterop>,
+        seed: u64,
+        terrain_service: TerrainService,
     ) -> TerrainPresenter<T>
     where
         S: SpriteSource<T = T::Texture, S = T::Sprite, G = T::SpriteGroup>,
     {
+        let terrain_modifications = terrain_service.subscribe();
         let terrain_texture_provider = TerrainTextureProvider::new(
             runtime_resources,
             system_interop.get_resource_loader(),
+            seed,
+            terrain_service,
         );
 
         TerrainPresenter {
             event_bus,
             terrain_texture_provider,
+            terrain_modifications,
             listener_registrations: Vec::new(),
-            sprite_group: sprite_source.create_group(),
-            terrain_sprites: Default::default(),
-            terrain_sprites_size: Default::default(),
-            sprite_terrain_coverage: Default::default(),
-            top_left_sprite: Default::default(),
+            layers: [
+                TerrainLayer::new(sprite_source.create_group()),
+                TerrainLayer::new(sprite_source.create_group()),
+            ],
+            last_viewport_center: None,
+            pan_velocity: Default::default(),
         }
     }
 
@@ -114,389 +171,245 @@ where
         info!("Terrain presenter started");
         self.event_bus.post(TerrainPresenterStarted::new());
 
-        while let Some(ViewportChange { new_viewport }) = select! {
-            viewport_info_opt = event_stream.next() => viewport_info_opt,
+        while let Some(event) = select! {
+            viewport_info_opt = event_stream.next() => viewport_info_opt
+                .map(|ViewportChange { new_viewport }| {
+                    TerrainPresenterEvent::ViewportChanged(new_viewport)
+                }),
+            generated_opt =
+                self.terrain_texture_provider.next_generated_texture() =>
+                generated_opt.map(|(key, texture)| {
+                    TerrainPresenterEvent::TextureGenerated(key, texture)
+                }),
+            Some(position) = self.terrain_modifications.recv() =>
+                Some(TerrainPresenterEvent::TerrainModified(position)),
             _ = &mut end_event => None
         } {
-            self.on_viewport_change(&new_viewport).await;
+            match event {
+                TerrainPresenterEvent::ViewportChanged(new_viewport) => {
+                    self.on_viewport_change(&new_viewport).await
+                }
+                TerrainPresenterEvent::TextureGenerated(key, texture) => {
+                    self.on_texture_generated(&key, &texture)
+                }
+                TerrainPresenterEvent::TerrainModified(position) => {
+                    self.on_terrain_modified(&position)
+                }
+            }
         }
 
-        info!("Terrain Presenter Stopped");
+        let cache_stats = self.terrain_texture_provider.get_cache_stats();
+
+        info!(
+            "Terrain Presenter Stopped, chunk cache hit rate {:.2} ({:?})",
+            cache_stats.hit_rate(),
+            cache_stats
+        );
     }
 
+    /// Show the zoom level needed for the new viewport on the layer matching
+    /// it, and fade in the next zoom level on the other layer if the viewport
+    /// is close to needing it. Layers that aren't needed are hidden. Missing
+    /// textures for the shown layers are then requested from the terrain
+    /// workers along with the terrain the viewport is panning towards
     async fn on_viewport_change(&mut self, viewport_info: &ViewportInfo) {
-        let terrain_update_info_opt =
-            self.terrain_updates_required(viewport_info);
-
-        if terrain_update_info_opt.is_none() {
-            return;
-        }
+        let frac_zoom_level =
+            get_fractional_zoom_level(viewport_info.viewport_scale);
+        let (zoom_level, fading_in) = get_visible_zoom_levels(frac_zoom_level);
 
-        let terrain_update_info = terrain_update_info_opt.unwrap();
+        let viewport_center = viewport_info.viewport_rect.center();
+        self.update_pan_velocity(viewport_center);
 
-        let valid_sprite_rect = {
-            let size_increased = self.check_sprite_array_size_increased(
-                &terrain_update_info.sprite_array_size,
-            );
+        let terrain_texture_provider = &self.terrain_texture_provider;
+        let mut jobs = Vec::new();
 
-            if size_increased {
-                debug!("Size increased");
-                self.increase_size_for(terrain_update_info)
+        for (layer_index, layer) in self.layers.iter_mut().enumerate() {
+            let display = if zoom_level % LAYER_COUNT == layer_index {
+                Some((zoom_level, 1., BACKGROUND_Z_LEVEL))
             } else {
-                debug!("Size not increased");
-                let (top_left, new_valid_rect) = self
-                    .calculate_new_valid_sprites(&terrain_update_info)
-                    .unwrap_or_default();
-
-                self.update_terrain_sprite_info(terrain_update_info, top_left);
+                fading_in
+                    .filter(|(fade_zoom_level, _)| {
+                        fade_zoom_level % LAYER_COUNT == layer_index
+                    })
+                    .map(|(fade_zoom_level, alpha)| {
+                        (fade_zoom_level, alpha, FOREGROUND_Z_LEVEL)
+                    })
+            };
 
-                new_valid_rect
+            match display {
+                Some((layer_zoom_level, alpha, z_level)) => {
+                    if layer.zoom_level() != layer_zoom_level {
+                        debug!(
+                            "Terrain layer {} switching to zoom level {}",
+                            layer_index, layer_zoom_level
+                        );
+                    }
+
+                    layer.set_z_level(z_level);
+                    layer.set_alpha(alpha);
+                    layer.update(
+                        viewport_info,
+                        layer_zoom_level,
+                        terrain_texture_provider,
+                    );
+                    layer.set_visible(true);
+
+                    jobs.extend(layer.pending_jobs(&viewport_center));
+                }
+                None => layer.set_visible(false),
             }
-        };
-
-        let sprite_width = TERRAIN_TEXTURE_SIDE_LENGTH_F64;
+        }
 
-        self.update_terrain_sprites(valid_sprite_rect, |sprite, point| {
-            sprite.set_visible(false);
+        let prefetch_offset =
+            get_prefetch_offset(&self.pan_velocity, zoom_level);
 
-            let texture_terrain_rect = IRect {
-                top_left: *point,
-                size: TERRAIN_TEXTURE_SIZE,
+        if prefetch_offset != Point::default() {
+            let viewport_rect = &viewport_info.viewport_rect;
+            let lookahead_rect = Rect {
+                top_left: viewport_rect.top_left + prefetch_offset,
+                size: viewport_rect.size,
             };
 
-            sprite.set_texture(
-                &self.terrain_texture_provider.get_texture_for_rect(
-                    &texture_terrain_rect,
-                    &TERRAIN_TEXTURE_SIZE,
-                ),
+            jobs.extend(
+                self.layers[zoom_level % LAYER_COUNT]
+                    .prefetch_jobs(&lookahead_rect, &viewport_center),
             );
+        }
 
-            sprite.set_size(sprite_width, sprite_width);
-            sprite.set_visible(true);
-        });
+        self.terrain_texture_provider.schedule(jobs);
     }
 
-    /// Update the invalid terrain sprites
-    fn update_terrain_sprites(
-        &self,
-        new_valid_rect: URect,
-        sprite_updater: impl Fn(&T::Sprite, &IPoint),
+    /// Show the newly generated texture on whichever layer is waiting for it
+    fn on_texture_generated(
+        &mut self,
+        key: &TerrainChunkKey,
+        texture: &T::Texture,
     ) {
-        trace!("Updating terrain sprites");
-
-        // hit all the partial rows to the right of the valid region
-
-        let new_valid_size = &new_valid_rect.size;
-        let valid_top_left = &new_valid_rect.top_left;
-
-        let action = |x: usize, y: usize| {
-            let (sprite, terrain_point) =
-                self.get_sprite_at(valid_top_left, &x, &y);
-            sprite.set_location_point(&terrain_point.into());
-            sprite_updater(sprite, &terrain_point);
-        };
-
-        for y in 0..new_valid_size.height {
-            for x in new_valid_size.width..self.terrain_sprites_size.width {
-                action(x, y);
-            }
-        }
+        let used = self
+            .layers
+            .iter_mut()
+            .any(|layer| layer.on_texture_generated(key, texture));
 
-        // hit all the complete rows below the valid region
-
-        for y in new_valid_size.height..self.terrain_sprites_size.height {
-            for x in 0..self.terrain_sprites_size.width {
-                action(x, y);
-            }
+        if !used {
+            trace!("Generated terrain chunk {:?} is no longer shown", key);
         }
     }
 
-    /// Get the terrain rect required to cover the given viewport rect based on
-    /// the current size of the terrain sprites array.
-    fn viewport_rect_to_terrain_rect(&self, viewport_rect: &Rect) -> IRect {
-        let viewport_top_left = &viewport_rect.top_left;
-        let viewport_bottom_right = viewport_top_left + &viewport_rect.size;
-
-        let top_left = IPoint {
-            x: viewport_top_left.x.floor() as i64,
-            y: viewport_top_left.y.floor() as i64,
+    /// Regenerate the chunks that show the modified tile. Only the affected
+    /// chunks are generated again, and the rest of the terrain is left as is
+    fn on_terrain_modified(&mut self, position: &IPoint) {
+        let rect = IRect {
+            top_left: *position,
+            size: ISize::new(1, 1),
         };
 
-        let bottom_right = IPoint {
-            x: viewport_bottom_right.x.ceil() as i64,
-            y: viewport_bottom_right.y.ceil() as i64,
-        };
-
-        let size = (bottom_right - &top_left).to_size().expect("bad size");
-
-        IRect { top_left, size }
-    }
-
-    /// return true if the current size of the 2d vector array is bigger than
-    /// or equal to the size given in both height and width
-    fn check_sprite_array_size_increased(
-        &self,
-        min_sprite_array_size: &ISize,
-    ) -> bool {
-        min_sprite_array_size.width > self.terrain_sprites_size.width
-            || min_sprite_array_size.height > self.terrain_sprites_size.height
-    }
-
-    /// Determine if the terrain sprites need updating, and return the new terrain
-    /// rect, if an update is needed
-    fn terrain_updates_required(
-        &self,
-        viewport_info: &ViewportInfo,
-    ) -> Option<TerrainUpdateInfo> {
-        if viewport_info.viewport_scale >= 6. / constants::TILE_SCALE {
-            return None;
-        }
-
-        let viewport_rect = &viewport_info.viewport_rect;
+        self.terrain_texture_provider.invalidate(&rect);
 
-        let mut terrain_rect =
-            self.viewport_rect_to_terrain_rect(viewport_rect);
-
-        let (min_covered_terrain, mut min_sprite_array_size) =
-            get_min_sprite_covering(&terrain_rect);
-
-        let tiles_per_sprite = TERRAIN_TEXTURE_SIDE_LENGTH;
-        let tiles_per_sprite_i64 = tiles_per_sprite as i64;
+        let viewport_center = match self.last_viewport_center {
+            Some((viewport_center, _)) => viewport_center,
+            None => return,
+        };
 
-        // If the minimum size of the sprite array is less than the current
-        // size of the sprite array in either dimension, center the minimum
-        // terrain rect within a larger terrain rect that has the same size as
-        // what can be covered by the current sprite array
-        if min_sprite_array_size.width <= self.terrain_sprites_size.width {
-            let dw =
-                self.terrain_sprites_size.width - min_sprite_array_size.width;
-            terrain_rect.top_left.x -= (dw as i64 / 2) * tiles_per_sprite_i64;
-            terrain_rect.size.width =
-                self.terrain_sprites_size.width * tiles_per_sprite;
-            min_sprite_array_size.width = self.terrain_sprites_size.width;
-        }
+        let mut jobs = Vec::new();
 
-        if min_sprite_array_size.height <= self.terrain_sprites_size.height {
-            let dh =
-                self.terrain_sprites_size.height - min_sprite_array_size.height;
-            terrain_rect.top_left.y -= (dh as i64 / 2) * tiles_per_sprite_i64;
-            terrain_rect.size.height =
-                self.terrain_sprites_size.height * tiles_per_sprite;
-            min_sprite_array_size.height = self.terrain_sprites_size.height;
+        for layer in self.layers.iter_mut() {
+            layer.invalidate(&rect);
+            jobs.extend(layer.pending_jobs(&viewport_center));
         }
 
-        Some(TerrainUpdateInfo {
-            zoom_level: 0,
-            terrain_rect: min_covered_terrain,
-            sprite_length_in_tiles: tiles_per_sprite,
-            sprite_array_size: min_sprite_array_size,
-            zoom_level_changed: false,
-        })
+        self.terrain_texture_provider.schedule(jobs);
     }
 
-    /// Increase the size of the 2d array of terrain sprites to accommodate the
-    /// given size
-    fn increase_size_for(
-        &mut self,
-        terrain_update_info: TerrainUpdateInfo,
-    ) -> URect {
-        let min_sprite_array_size = &terrain_update_info.sprite_array_size;
-
-        debug!(
-            "Increasing terrain sprites cache to {:?}",
-            min_sprite_array_size
-        );
-
-        let width_inc = min_sprite_array_size
-            .width
-            .checked_sub(self.terrain_sprites_size.width)
-            .unwrap_or_default();
-        let height_inc = min_sprite_array_size
-            .height
-            .checked_sub(self.terrain_sprites_size.height)
-            .unwrap_or_default();
-
-        if width_inc > 0 {
-            self.increase_row_width_by(width_inc);
-        }
-
-        if height_inc > 0 {
-            self.increase_row_count_by(height_inc);
+    fn update_pan_velocity(&mut self, viewport_center: Point) {
+        let now = Instant::now();
+
+        if let Some((last_center, last_time)) =
+            self.last_viewport_center.replace((viewport_center, now))
+        {
+            self.pan_velocity = smooth_pan_velocity(
+                &self.pan_velocity,
+                &last_center,
+                &viewport_center,
+                now.duration_since(last_time).as_secs_f64(),
+            );
         }
-
-        let (new_top_left, valid_rect) = self
-            .calculate_new_valid_sprites(&terrain_update_info)
-            .unwrap_or_default();
-
-        self.update_terrain_sprite_info(terrain_update_info, new_top_left);
-
-        valid_rect
-    }
-
-    /// Add the two points together within the bounds of the sprite grid system
-    fn sprite_grid_add(&self, lhs: &UPoint, rhs: &IPoint) -> UPoint {
-        let sprite_grid_width = self.terrain_sprites_size.width as i64;
-        let sprite_grid_height = self.terrain_sprites_size.height as i64;
-
-        UPoint::new(
-            (lhs.x as i64 + rhs.x).rem_euclid(sprite_grid_width) as usize,
-            (lhs.y as i64 + rhs.y).rem_euclid(sprite_grid_height) as usize,
-        )
     }
+}
 
-    /// Determine which portion if any of the existing valid terrain sprites will
-    /// remain valid when the viewport shifts to the given terrain rect, and
-    /// return the new top-left point within the sprite array, and the region of
-    /// the sprite array that's still valid
-    fn calculate_new_valid_sprites(
-        &self,
-        terrain_update_info: &TerrainUpdateInfo,
-    ) -> Option<(UPoint, URect)> {
-        let new_terrain_rect = &terrain_update_info.terrain_rect;
-        let tiles_per_sprite = terrain_update_info.sprite_length_in_tiles;
-
-        self.sprite_terrain_coverage
-            .intersection(new_terrain_rect)
-            .map(|itx_rect| {
-                // Determine the top left and size of the valid region
-                let mut valid_top_left_shift =
-                    &itx_rect.top_left - &self.sprite_terrain_coverage.top_left;
-
-                valid_top_left_shift /= tiles_per_sprite as i64;
-
-                let valid_sprites_top_left = self.sprite_grid_add(
-                    &self.top_left_sprite,
-                    &valid_top_left_shift,
-                );
-
-                let new_valid_rect = URect {
-                    top_left: valid_sprites_top_left,
-                    size: itx_rect.size / tiles_per_sprite,
-                };
-
-                // determine the new top left of the viewport rect
-
-                let mut new_top_left_shift = &new_terrain_rect.top_left
-                    - &self.sprite_terrain_coverage.top_left;
-
-                new_top_left_shift /= tiles_per_sprite as i64;
-
-                let new_top_left = self.sprite_grid_add(
-                    &self.top_left_sprite,
-                    &new_top_left_shift,
-                );
-
-                trace!("valid sprite area = {}", new_valid_rect.area());
+#[cfg(test)]
+mod test {
+    use super::*;
 
-                (new_top_left, new_valid_rect)
-            })
-    }
+    #[test]
+    fn test_fractional_zoom_level() {
+        let unit_scale = 1. / constants::TILE_SCALE;
 
-    /// Update the information about the terrain sprites that describes which part
-    /// of the terrain is being shown and where in the sprite array the top-left
-    /// corner is
-    fn update_terrain_sprite_info(
-        &mut self,
-        update_info: TerrainUpdateInfo,
-        top_left_sprite: UPoint,
-    ) {
-        self.top_left_sprite = top_left_sprite;
-        self.sprite_terrain_coverage = update_info.terrain_rect;
+        assert_eq!(0., get_fractional_zoom_level(unit_scale));
+        assert_eq!(0., get_fractional_zoom_level(unit_scale / 8.));
+        assert_eq!(1., get_fractional_zoom_level(ZOOM_LEVEL_1_VIEWPORT_SCALE));
+        assert_eq!(
+            3.,
+            get_fractional_zoom_level(4. * ZOOM_LEVEL_1_VIEWPORT_SCALE)
+        );
+        assert_eq!(MAX_ZOOM_LEVEL as f64, get_fractional_zoom_level(1e12));
     }
 
-    /// Increase the size of all the existing rows in the terrain to the given
-    /// width
-    fn increase_row_width_by(&mut self, cols_to_add: usize) {
-        let insert_point = self.top_left_sprite.x;
-        let sprite_group = &self.sprite_group;
+    #[test]
+    fn test_visible_zoom_levels() {
+        assert_eq!((0, None), get_visible_zoom_levels(0.));
+        assert_eq!((0, None), get_visible_zoom_levels(1. - CROSS_FADE_WIDTH));
+        assert_eq!((2, None), get_visible_zoom_levels(2.5));
 
-        let sprite_source = || {
-            let result = sprite_group.create_sprite();
-            result.set_visible(true);
-            result
-        };
-
-        self.terrain_sprites.iter_mut().for_each(|row| {
-            let to_insert = iter::repeat_with(sprite_source).take(cols_to_add);
+        let (zoom_level, fading_in) =
+            get_visible_zoom_levels(2. - CROSS_FADE_WIDTH / 2.);
+        let (next_zoom_level, alpha) = fading_in.unwrap();
 
-            row.splice(insert_point..insert_point, to_insert)
-                .for_each(|_| {});
-        });
+        assert_eq!(1, zoom_level);
+        assert_eq!(2, next_zoom_level);
+        assert!((alpha - 0.5).abs() < 1e-9);
 
-        self.top_left_sprite.x += cols_to_add;
-        self.terrain_sprites_size.width += cols_to_add;
+        // There is nothing to fade in past the maximum zoom level
+        assert_eq!(
+            (MAX_ZOOM_LEVEL, None),
+            get_visible_zoom_levels(MAX_ZOOM_LEVEL as f64)
+        );
     }
 
-    /// Increase the number of rows in the sprite array by the given number
-    fn increase_row_count_by(&mut self, rows_to_add: usize) {
-        let total_columns = self.terrain_sprites_size.width;
-        let sprite_group = &self.sprite_group;
-
-        let sprite_source = || {
-            let result = sprite_group.create_sprite();
-            result.set_visible(true);
-            result
-        };
-
-        let new_rows = iter::repeat_with(|| {
-            iter::repeat_with(sprite_source)
-                .take(total_columns)
-                .collect()
-        })
-        .take(rows_to_add);
+    #[test]
+    fn test_smooth_pan_velocity() {
+        let origin = Point::new(0., 0.);
 
-        let insert_point = &self.top_left_sprite.y;
-
-        self.terrain_sprites
-            .splice(insert_point..insert_point, new_rows);
-
-        self.top_left_sprite.y += rows_to_add;
-        self.terrain_sprites_size.height += rows_to_add;
-    }
+        // A first move far from the last one is taken as is
+        assert_eq!(
+            Point::new(-2., 4.),
+            smooth_pan_velocity(&origin, &origin, &Point::new(-1., 2.), 0.5)
+        );
 
-    fn get_sprite<'a>(&'a self, point: &UPoint) -> Option<&'a T::Sprite> {
-        self.terrain_sprites
-            .get(point.y)
-            .and_then(|row| row.get(point.x))
-    }
+        assert_eq!(
+            Point::new(15., 0.),
+            smooth_pan_velocity(
+                &Point::new(10., 0.),
+                &origin,
+                &Point::new(2., 0.),
+                0.1
+            )
+        );
 
-    /// Assume that the given point p is a point within the bounds of the
-    /// sprite grid, get the offset from origin of the sprite grid (the top left)
-    fn sprite_grid_offset_from_origin(&self, p: &UPoint) -> IPoint {
-        let grid_width = self.terrain_sprites_size.width as i64;
-        let grid_height = self.terrain_sprites_size.height as i64;
-
-        IPoint::new(
-            (p.x as i64 - self.top_left_sprite.x as i64).rem_euclid(grid_width),
-            (p.y as i64 - self.top_left_sprite.y as i64)
-                .rem_euclid(grid_height),
-        )
+        assert_eq!(
+            Point::new(3., 3.),
+            smooth_pan_velocity(&Point::new(3., 3.), &origin, &origin, 0.)
+        );
     }
 
-    /// Get the sprite at the given point in the sprite grid using the given natural
-    /// origin.  Also return the terrain coordinates of the sprite based on the
-    /// configured top-left sprite in the grid and the sprite-terrain coverage
-    fn get_sprite_at<'a>(
-        &'a self,
-        natural_origin: &UPoint,
-        natural_x: &usize,
-        natural_y: &usize,
-    ) -> (&'a T::Sprite, IPoint) {
-        let real_point = UPoint::new(
-            (natural_origin.x + natural_x) % self.terrain_sprites_size.width,
-            (natural_origin.y + natural_y) % self.terrain_sprites_size.height,
+    #[test]
+    fn test_prefetch_offset() {
+        assert_eq!(
+            Point::new(5., -10.),
+            get_prefetch_offset(&Point::new(10., -20.), 0)
+        );
+        assert_eq!(
+            Point::new(100., -256.),
+            get_prefetch_offset(&Point::new(200., -1000.), 2)
         );
-
-        let sprite = self.get_sprite(&real_point).unwrap_or_else(|| {
-            error!("Invalid sprite_coordinate {:?}", real_point);
-            panic!("Index out of bounds error in terrain sprites array");
-        });
-
-        let offset = self.sprite_grid_offset_from_origin(&real_point)
-            * (TERRAIN_TEXTURE_SIDE_LENGTH as i64);
-
-        let terrain_point = &self.sprite_terrain_coverage.top_left + &offset;
-
-        (sprite, terrain_point)
     }
 }
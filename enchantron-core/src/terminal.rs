@@ -0,0 +1,10 @@
+// Terminal frontend that plays the game on a linux console using the
+// headless view types
+
+pub use self::terminal_app::run;
+pub use self::terminal_key::TerminalKey;
+pub use self::terminal_renderer::TerminalRenderer;
+
+mod terminal_app;
+mod terminal_key;
+mod terminal_renderer;
@@ -0,0 +1,299 @@
+use super::{TerminalKey, TerminalRenderer};
+use crate::application_context::Ao;
+use crate::event::{EventBus, GameStopped};
+use crate::game::{constants, SaveSlotManager, TerrainConfig};
+use crate::headless::{
+    HeadlessNativeView, HeadlessSystemInterop, HeadlessViewTypes,
+};
+use crate::model::ISize;
+use crate::native::{Animations, RuntimeResources, SystemInterop, Textures};
+use crate::presenter::GamePresenter;
+use crate::view::NativeView;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::Builder;
+use tokio::select;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::time::sleep;
+
+const FRAME_DURATION: Duration = Duration::from_millis(100);
+const DEFAULT_TERMINAL_SIZE: (usize, usize) = (24, 80);
+const ZOOM_STEP: f64 = 0.1;
+
+/// Run stty against the controlling terminal with the given arguments
+fn stty(args: &[&str]) -> Option<String> {
+    Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+}
+
+/// Get the size of the terminal as (rows, columns)
+fn get_terminal_size() -> (usize, usize) {
+    stty(&["size"])
+        .and_then(|size| {
+            let mut parts = size.split_whitespace().map(str::parse::<usize>);
+
+            match (parts.next(), parts.next()) {
+                (Some(Ok(rows)), Some(Ok(cols))) => Some((rows, cols)),
+                _ => None,
+            }
+        })
+        .unwrap_or(DEFAULT_TERMINAL_SIZE)
+}
+
+/// Puts the terminal into raw mode and restores it when dropped
+struct RawMode {}
+
+impl RawMode {
+    fn enable() -> RawMode {
+        let _ = stty(&["raw", "-echo"]);
+        print!("\x1b[?25l\x1b[2J");
+        RawMode {}
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&["sane"]);
+        print!("\x1b[0m\x1b[?25h\x1b[2J\x1b[H");
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Read keys from stdin on a dedicated thread until stdin closes or the
+/// receiving side goes away
+fn spawn_key_reader(sender: UnboundedSender<TerminalKey>) {
+    thread::spawn(move || {
+        let mut buffer = [0u8; 64];
+        let stdin = io::stdin();
+
+        while let Ok(count) = stdin.lock().read(&mut buffer) {
+            if count == 0 {
+                break;
+            }
+
+            for key in TerminalKey::parse(&buffer[..count]) {
+                if sender.send(key).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Translates keys into the ui events the game view understands and keeps
+/// track of the cursor used to pick tiles
+struct KeyMapper {
+    view: HeadlessNativeView,
+    grid_size: ISize,
+    cursor: (usize, usize),
+    next_touch_id: i64,
+}
+
+impl KeyMapper {
+    fn new(view: HeadlessNativeView, grid_size: ISize) -> KeyMapper {
+        KeyMapper {
+            view,
+            cursor: (grid_size.width / 2, grid_size.height / 2),
+            grid_size,
+            next_touch_id: 0,
+        }
+    }
+
+    fn next_touch_id(&mut self) -> i64 {
+        self.next_touch_id += 1;
+        self.next_touch_id
+    }
+
+    /// Get the center of the given cell in screen points
+    fn cell_center(&self, cell: (usize, usize)) -> (f64, f64) {
+        (
+            (cell.0 as f64 + 0.5) * constants::TILE_SCALE,
+            (cell.1 as f64 + 0.5) * constants::TILE_SCALE,
+        )
+    }
+
+    /// Drag the viewport by one cell in the given direction
+    fn pan(&mut self, dx: f64, dy: f64) {
+        let touch_id = self.next_touch_id();
+        let (x, y) = self
+            .cell_center((self.grid_size.width / 2, self.grid_size.height / 2));
+        let (end_x, end_y) = (
+            x - dx * constants::TILE_SCALE,
+            y - dy * constants::TILE_SCALE,
+        );
+
+        self.view.touch_start(touch_id, x, y, 1);
+        self.view.touch_move(touch_id, end_x, end_y, 1);
+        self.view.touch_end(touch_id, end_x, end_y, 1);
+    }
+
+    fn move_cursor(&mut self, dx: i64, dy: i64) {
+        let clamp = |v: usize, d: i64, max: usize| {
+            (v as i64 + d).max(0).min(max as i64 - 1) as usize
+        };
+
+        self.cursor = (
+            clamp(self.cursor.0, dx, self.grid_size.width),
+            clamp(self.cursor.1, dy, self.grid_size.height),
+        );
+    }
+
+    fn zoom(&mut self, scale_change_additive: f64) {
+        let (x, y) = self
+            .cell_center((self.grid_size.width / 2, self.grid_size.height / 2));
+        self.view.magnify(scale_change_additive, x, y);
+    }
+
+    /// Handle the given key, and return false if the key means quit
+    fn on_key(&mut self, key: TerminalKey) -> bool {
+        match key {
+            TerminalKey::Up => self.pan(0., -1.),
+            TerminalKey::Down => self.pan(0., 1.),
+            TerminalKey::Left => self.pan(-1., 0.),
+            TerminalKey::Right => self.pan(1., 0.),
+            TerminalKey::Char('w') => self.move_cursor(0, -1),
+            TerminalKey::Char('s') => self.move_cursor(0, 1),
+            TerminalKey::Char('a') => self.move_cursor(-1, 0),
+            TerminalKey::Char('d') => self.move_cursor(1, 0),
+            TerminalKey::Char('+') | TerminalKey::Char('=') => {
+                self.zoom(ZOOM_STEP)
+            }
+            TerminalKey::Char('-') => self.zoom(-ZOOM_STEP),
+            TerminalKey::Enter | TerminalKey::Char(' ') => {
+                let touch_id = self.next_touch_id();
+                let (x, y) = self.cell_center(self.cursor);
+                self.view.tap(touch_id, x, y);
+            }
+            TerminalKey::Char('q') => return false,
+            _ => {}
+        }
+
+        true
+    }
+}
+
+/// Keep terminal saves in the home directory so they outlive the process
+fn get_save_directory() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(".enchantron")
+        .join("saves")
+}
+
+/// Run the game in the terminal until the user quits. Each terrain tile is
+/// drawn as a colored cell with entities as glyphs on top
+pub fn run() {
+    let (rows, cols) = get_terminal_size();
+
+    // Leave the bottom row for the status line
+    let grid_size =
+        ISize::new((cols / 2).max(1), rows.saturating_sub(1).max(1));
+
+    let runtime = Box::new(
+        Builder::new_multi_thread()
+            .thread_name("TerminalThread")
+            .enable_time()
+            .build()
+            .unwrap_or_else(|e| {
+                panic!("Failed to create terminal runtime, {:?}", e);
+            }),
+    );
+
+    let (event_bus, event_bus_dropper) = EventBus::new(Ao::new(&runtime));
+
+    let system_interop = Box::new(
+        HeadlessSystemInterop::default()
+            .with_save_directory(get_save_directory()),
+    );
+    let resource_loader = system_interop.get_resource_loader();
+    let textures = Textures::new(&resource_loader, &|_| {});
+    let animations = Animations::new(&resource_loader, &textures);
+    let runtime_resources = Box::new(RuntimeResources::new(
+        textures,
+        animations,
+        TerrainConfig::load(&resource_loader),
+    ));
+
+    let raw_mode = RawMode::enable();
+
+    runtime.block_on(async {
+        let view = system_interop.create_game_view();
+        let raw_view = view.raw_view.clone();
+        let scene = raw_view.scene().clone();
+
+        let game_stopped = event_bus.register_for_one::<GameStopped>();
+
+        let presenter_future = GamePresenter::<HeadlessViewTypes>::run(
+            view,
+            event_bus.clone(),
+            Ao::new(&runtime_resources),
+            Ao::new(&system_interop),
+            SaveSlotManager::new(system_interop.get_save_directory())
+                .load_most_recent_or_new(),
+        );
+
+        let ui_future = async {
+            while !raw_view.has_presenter() {
+                sleep(FRAME_DURATION).await;
+            }
+
+            raw_view.layout(
+                grid_size.width as f64 * constants::TILE_SCALE,
+                grid_size.height as f64 * constants::TILE_SCALE,
+                1.,
+            );
+
+            let (key_sender, mut keys) = unbounded_channel();
+            spawn_key_reader(key_sender);
+
+            let renderer =
+                TerminalRenderer::new(grid_size, constants::TILE_SCALE);
+            let mut key_mapper = KeyMapper::new(raw_view.clone(), grid_size);
+            let mut frame = String::new();
+
+            loop {
+                frame.clear();
+                renderer.render_ansi(
+                    &scene,
+                    Some(key_mapper.cursor),
+                    &mut frame,
+                );
+                frame.push_str(
+                    "arrows: pan  wasd: cursor  space: walk  +/-: zoom  \
+                     q: quit\x1b[K",
+                );
+
+                print!("{}", frame);
+                let _ = io::stdout().flush();
+
+                let key_opt = select! {
+                    key_opt = keys.recv() => key_opt,
+                    _ = sleep(FRAME_DURATION) => continue
+                };
+
+                match key_opt {
+                    Some(key) if key_mapper.on_key(key) => {}
+                    _ => break,
+                }
+            }
+
+            raw_view.unset_presenter();
+            game_stopped.await;
+        };
+
+        futures::join!(presenter_future, ui_future);
+    });
+
+    drop(raw_mode);
+    drop(runtime);
+    event_bus_dropper();
+}
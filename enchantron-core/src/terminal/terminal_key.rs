@@ -0,0 +1,61 @@
+/// Keys the terminal frontend understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Char(char),
+}
+
+impl TerminalKey {
+    /// Parse the keys out of a chunk of raw bytes read from a terminal in
+    /// raw mode. Unrecognized escape sequences are dropped
+    pub fn parse(bytes: &[u8]) -> Vec<TerminalKey> {
+        let mut result = Vec::new();
+        let mut iter = bytes.iter().peekable();
+
+        while let Some(byte) = iter.next() {
+            match byte {
+                0x1b if iter.peek() == Some(&&b'[') => {
+                    iter.next();
+
+                    match iter.next() {
+                        Some(b'A') => result.push(TerminalKey::Up),
+                        Some(b'B') => result.push(TerminalKey::Down),
+                        Some(b'C') => result.push(TerminalKey::Right),
+                        Some(b'D') => result.push(TerminalKey::Left),
+                        _ => {}
+                    }
+                }
+                b'\r' | b'\n' => result.push(TerminalKey::Enter),
+                b if b.is_ascii_graphic() || *b == b' ' => {
+                    result.push(TerminalKey::Char(*b as char))
+                }
+                _ => {}
+            }
+        }
+
+        result
+    }
+}
+
+#[test]
+fn test_parse() {
+    use TerminalKey::*;
+
+    assert_eq!(
+        vec![
+            Up,
+            Char('w'),
+            Left,
+            Enter,
+            Char(' '),
+            Right,
+            Down,
+            Char('q')
+        ],
+        TerminalKey::parse(b"\x1b[Aw\x1b[D\r \x1b[C\x1b[B\x1b[Zq")
+    );
+}
@@ -0,0 +1,182 @@
+use crate::game::constants;
+use crate::headless::{HeadlessRenderer, HeadlessScene, Layer};
+use crate::model::{ISize, Point};
+use crate::view::PLAYER_TEXTURE_OFFSET;
+use std::fmt::Write;
+
+const EMPTY_GLYPH: char = ' ';
+const CURSOR_GLYPH: char = '+';
+
+/// One character cell of the terminal. Each cell is drawn two characters wide
+/// so that tiles come out roughly square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalCell {
+    pub background: [u8; 3],
+    pub glyph: char,
+}
+
+/// Draws a headless scene as a grid of colored cells. Everything below the
+/// entity z-level is rasterized into the cell background colors, and entity
+/// sprites are drawn as glyphs in the cell for the tile they're standing on
+pub struct TerminalRenderer {
+    size: ISize,
+    points_per_cell: f64,
+    glyphs: Vec<(&'static str, char)>,
+}
+
+impl TerminalRenderer {
+    /// Create a renderer for a grid of the given size in cells where each
+    /// cell covers the given number of screen points
+    pub fn new(size: ISize, points_per_cell: f64) -> TerminalRenderer {
+        TerminalRenderer {
+            size,
+            points_per_cell,
+            glyphs: vec![("character.png", '@')],
+        }
+    }
+
+    /// Get the glyph to use for an entity sprite with the given texture
+    fn get_glyph(&self, texture_name: Option<&str>) -> char {
+        texture_name
+            .and_then(|name| {
+                self.glyphs
+                    .iter()
+                    .find(|(glyph_name, _)| *glyph_name == name)
+                    .map(|(_, glyph)| *glyph)
+            })
+            .unwrap_or('?')
+    }
+
+    /// Render the given scene into rows of cells
+    pub fn render_cells(&self, scene: &HeadlessScene) -> Vec<TerminalCell> {
+        let rgb = HeadlessRenderer::new(self.size)
+            .with_points_per_pixel(self.points_per_cell)
+            .with_max_z_level(constants::ENTITY_Z_LEVEL)
+            .render(scene);
+
+        let mut cells: Vec<TerminalCell> = rgb
+            .chunks_exact(3)
+            .map(|px| TerminalCell {
+                background: [px[0], px[1], px[2]],
+                glyph: EMPTY_GLYPH,
+            })
+            .collect();
+
+        let viewport = scene.viewport();
+        let cell_world_size = self.points_per_cell * viewport.scale;
+
+        scene
+            .visible_sprites()
+            .iter()
+            .filter(|s| s.layer == Layer::World)
+            .filter(|s| s.z_level >= constants::ENTITY_Z_LEVEL)
+            .for_each(|sprite| {
+                let tile = sprite.location - PLAYER_TEXTURE_OFFSET;
+                let tile_center =
+                    Point::new(tile.x.floor() + 0.5, tile.y.floor() + 0.5);
+
+                let cell_x = ((tile_center.x - viewport.location.x)
+                    / cell_world_size)
+                    .floor();
+                let cell_y = ((tile_center.y - viewport.location.y)
+                    / cell_world_size)
+                    .floor();
+
+                if cell_x < 0.
+                    || cell_y < 0.
+                    || cell_x >= self.size.width as f64
+                    || cell_y >= self.size.height as f64
+                {
+                    return;
+                }
+
+                let index = cell_y as usize * self.size.width + cell_x as usize;
+
+                cells[index].glyph =
+                    self.get_glyph(sprite.texture.as_ref().map(|t| t.name()));
+            });
+
+        cells
+    }
+
+    /// Render the given scene as ansi escape sequences into the given target.
+    /// The cursor is drawn over the cell with the given coordinates if given
+    pub fn render_ansi(
+        &self,
+        scene: &HeadlessScene,
+        cursor: Option<(usize, usize)>,
+        target: &mut String,
+    ) {
+        let cells = self.render_cells(scene);
+
+        // Move to the top-left corner of the terminal
+        target.push_str("\x1b[H");
+
+        for (y, row) in cells.chunks(self.size.width).enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let [r, g, b] = cell.background;
+
+                let glyph = if cursor == Some((x, y)) {
+                    CURSOR_GLYPH
+                } else {
+                    cell.glyph
+                };
+
+                let _ = write!(
+                    target,
+                    "\x1b[48;2;{};{};{}m\x1b[1;97m{}{}",
+                    r, g, b, glyph, EMPTY_GLYPH
+                );
+            }
+
+            target.push_str("\x1b[0m\r\n");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::headless::{HeadlessNativeView, HeadlessTexture};
+    use crate::model::Rect;
+    use crate::ui::{
+        HasMutableColor, HasMutableScale, HasMutableZLevel, HasViewport,
+        Sprite, SpriteSource,
+    };
+
+    #[test]
+    fn test_render_cells() {
+        let view = HeadlessNativeView::new();
+
+        let terrain = view.create_sprite();
+        terrain.set_8_bit_color(10, 200, 10, 255);
+        terrain.set_rect(&Rect::new(0., 0., 4., 3.));
+        terrain.set_z_level(constants::TERRAIN_Z_LEVEL);
+
+        let player_texture = HeadlessTexture::from_rgba(
+            "character.png".to_owned(),
+            ISize::new(1, 1),
+            vec![255, 0, 0, 255],
+        );
+
+        let player = view.create_sprite();
+        player.set_texture(&player_texture);
+        player.set_rect(&Rect {
+            top_left: Point::new(2., 1.) + PLAYER_TEXTURE_OFFSET,
+            size: crate::model::Size::new(2., 2.),
+        });
+        player.set_z_level(constants::ENTITY_Z_LEVEL);
+
+        view.get_viewport().set_scale_and_location(0.5, 0., 0.);
+
+        // each cell covers 2 screen points, so one tile
+        let renderer = TerminalRenderer::new(ISize::new(4, 3), 2.);
+        let cells = renderer.render_cells(view.scene());
+
+        assert_eq!(12, cells.len());
+        assert!(cells.iter().all(|c| c.background == [10, 200, 10]));
+
+        let glyphs: String = cells.iter().map(|c| c.glyph).collect();
+        assert_eq!("      @     ", glyphs);
+    }
+}
@@ -31,7 +31,14 @@ pub use self::sprite_group::SpriteGroup;
 pub use self::sprite_source::SpriteSource;
 pub use self::tap::Tap;
 pub use self::tap_event::TapEvent;
-pub use self::terrain_texture_provider::TerrainTextureProvider;
+pub use self::terrain_autotiler::{TerrainAutotiler, AUTOTILE_SIDE_LENGTH};
+pub use self::terrain_chunk_key::TerrainChunkKey;
+pub use self::terrain_job_queue::{
+    GeneratedTerrainChunk, TerrainJob, TerrainJobQueue,
+};
+pub use self::terrain_texture_provider::{
+    get_texture_data_for_rect, TerrainTextureProvider,
+};
 pub use self::terrain_update_info::TerrainUpdateInfo;
 pub use self::touch::Touch;
 pub use self::touch_event::TouchEvent;
@@ -73,6 +80,9 @@ mod sprite_group;
 mod sprite_source;
 mod tap;
 mod tap_event;
+mod terrain_autotiler;
+mod terrain_chunk_key;
+mod terrain_job_queue;
 mod terrain_texture_provider;
 mod terrain_update_info;
 mod touch;
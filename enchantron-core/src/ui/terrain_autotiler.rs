@@ -0,0 +1,461 @@
+use crate::game::{TerrainProvider, TerrainType};
+use crate::img::PngDecoder;
+use crate::model::{IRect, ISize};
+use crate::native::{
+    overworld_tiles, AtlasTile, ResourceLoader, OVERWORLD_TEXTURE,
+};
+use crate::util::ByteBuffer;
+use std::fs;
+
+/// Length in pixels of the sides of the tiles in the overworld atlas. Each
+/// tile of autotiled terrain covers a square of this many pixels
+pub const AUTOTILE_SIDE_LENGTH: usize = 16;
+
+const TOP_LEFT: u8 = 1;
+const TOP_RIGHT: u8 = 2;
+const BOTTOM_LEFT: u8 = 4;
+const BOTTOM_RIGHT: u8 = 8;
+const ALL_CORNERS: u8 = TOP_LEFT | TOP_RIGHT | BOTTOM_LEFT | BOTTOM_RIGHT;
+
+/// Atlas tile of plain grass that the other terrain types are drawn into
+const GRASS_TILE: AtlasTile = overworld_tiles::GRASS;
+
+/// Blocks of tiles in the overworld atlas that draw a type of terrain as
+/// patches in grass
+struct TransitionTiles {
+    terrain: TerrainType,
+    /// 3x3 block of tiles that draw a single patch. The center tile is solid
+    /// and the others are grass with the patch reaching into their corners
+    /// and edges
+    outer: AtlasTile,
+    /// 2x2 block of tiles that draw a single grass tile surrounded by the
+    /// patch, used for grass with three patch corners
+    inner: AtlasTile,
+}
+
+/// Terrain types with transition tiles in the atlas. When grass borders more
+/// than one of them, the transition for the first one is drawn
+const TRANSITIONS: [TransitionTiles; 3] = [
+    TransitionTiles {
+        terrain: TerrainType::Water,
+        outer: overworld_tiles::WATER_PATCH,
+        inner: overworld_tiles::WATER_INNER_CORNERS,
+    },
+    TransitionTiles {
+        terrain: TerrainType::Sand,
+        outer: overworld_tiles::SAND_PATCH,
+        inner: overworld_tiles::SAND_INNER_CORNERS,
+    },
+    TransitionTiles {
+        terrain: TerrainType::Dirt,
+        outer: overworld_tiles::DIRT_PATCH,
+        inner: overworld_tiles::DIRT_INNER_CORNERS,
+    },
+];
+
+impl TransitionTiles {
+    /// Get the atlas tile for a grass tile whose corners in the given mask
+    /// touch this terrain
+    fn get_tile(&self, corner_mask: u8) -> (usize, usize) {
+        let (x, y) = (self.outer.left, self.outer.top);
+        let (inner_x, inner_y) = (self.inner.left, self.inner.top);
+
+        match corner_mask {
+            BOTTOM_RIGHT => (x, y),
+            m if m == BOTTOM_LEFT | BOTTOM_RIGHT => (x + 1, y),
+            BOTTOM_LEFT => (x + 2, y),
+            m if m == TOP_RIGHT | BOTTOM_RIGHT => (x, y + 1),
+            m if m == TOP_LEFT | BOTTOM_LEFT => (x + 2, y + 1),
+            TOP_RIGHT => (x, y + 2),
+            m if m == TOP_LEFT | TOP_RIGHT => (x + 1, y + 2),
+            TOP_LEFT => (x + 2, y + 2),
+            m if m == ALL_CORNERS & !BOTTOM_RIGHT => (inner_x, inner_y),
+            m if m == ALL_CORNERS & !BOTTOM_LEFT => (inner_x + 1, inner_y),
+            m if m == ALL_CORNERS & !TOP_RIGHT => (inner_x, inner_y + 1),
+            m if m == ALL_CORNERS & !TOP_LEFT => (inner_x + 1, inner_y + 1),
+            // The atlas has no tiles for a patch touching only opposite
+            // corners, so those are filled in along with fully covered tiles
+            _ => (x + 1, y + 1),
+        }
+    }
+}
+
+/// How a single tile of terrain is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TileSource {
+    /// Copy the tile at the given tile coordinates in the atlas
+    Atlas(usize, usize),
+    /// Fill the tile with the terrain's color. Used for terrain types that
+    /// don't have tiles in the atlas
+    Flat(TerrainType),
+}
+
+/// Get the corners of the center tile of the given 3x3 neighborhood that are
+/// shared with a tile of the given terrain type
+fn get_corner_mask(
+    neighborhood: &[[TerrainType; 3]; 3],
+    terrain: TerrainType,
+) -> u8 {
+    let is_terrain = |x: usize, y: usize| neighborhood[y][x] == terrain;
+
+    let mut mask = 0;
+
+    if is_terrain(0, 0) || is_terrain(1, 0) || is_terrain(0, 1) {
+        mask |= TOP_LEFT;
+    }
+    if is_terrain(2, 0) || is_terrain(1, 0) || is_terrain(2, 1) {
+        mask |= TOP_RIGHT;
+    }
+    if is_terrain(0, 2) || is_terrain(1, 2) || is_terrain(0, 1) {
+        mask |= BOTTOM_LEFT;
+    }
+    if is_terrain(2, 2) || is_terrain(1, 2) || is_terrain(2, 1) {
+        mask |= BOTTOM_RIGHT;
+    }
+
+    mask
+}
+
+/// Pick how to draw the center tile of the given 3x3 neighborhood of terrain
+/// indexed by row then column. Patches of terrain with transition tiles
+/// spill into the grass around them, so only grass tiles have transitions
+fn pick_tile(neighborhood: &[[TerrainType; 3]; 3]) -> TileSource {
+    let center = neighborhood[1][1];
+
+    if center != TerrainType::Grass {
+        return TRANSITIONS
+            .iter()
+            .find(|transition| transition.terrain == center)
+            .map(|transition| transition.get_tile(ALL_CORNERS))
+            .map(|(x, y)| TileSource::Atlas(x, y))
+            .unwrap_or(TileSource::Flat(center));
+    }
+
+    TRANSITIONS
+        .iter()
+        .find_map(|transition| {
+            match get_corner_mask(neighborhood, transition.terrain) {
+                0 => None,
+                mask => Some(transition.get_tile(mask)),
+            }
+        })
+        .map(|(x, y)| TileSource::Atlas(x, y))
+        .unwrap_or(TileSource::Atlas(GRASS_TILE.left, GRASS_TILE.top))
+}
+
+/// Composes terrain textures out of tiles from the overworld atlas, picking
+/// transition tiles for each tile based on its neighbors
+pub struct TerrainAutotiler {
+    atlas_size: ISize,
+    atlas_rgba: Vec<u8>,
+}
+
+impl TerrainAutotiler {
+    /// Read the pixels of the overworld atlas from the texture's file, so the
+    /// terrain workers can compose textures without going through the native
+    /// texture loader
+    pub fn load(resource_loader: &impl ResourceLoader) -> TerrainAutotiler {
+        let path =
+            resource_loader.get_texture_path(OVERWORLD_TEXTURE.to_owned());
+
+        let atlas_png = fs::read(&path).unwrap_or_else(|e| {
+            error!("Failed to read the overworld atlas {:?}: {:?}", path, e);
+            panic!("Failed to read the overworld atlas");
+        });
+
+        TerrainAutotiler::new(&atlas_png)
+    }
+
+    fn new(atlas_png: &[u8]) -> TerrainAutotiler {
+        let (atlas_size, atlas_rgba) = PngDecoder::get_rgba(atlas_png);
+
+        TerrainAutotiler {
+            atlas_size,
+            atlas_rgba,
+        }
+    }
+
+    /// Check if the terrain in the given rect drawn into a texture of the
+    /// given size is drawn with atlas tiles rather than flat colors
+    pub fn is_autotiled(rect: &IRect, texture_size: &ISize) -> bool {
+        texture_size.width == rect.size.width * AUTOTILE_SIDE_LENGTH
+            && texture_size.height == rect.size.height * AUTOTILE_SIDE_LENGTH
+    }
+
+    /// Draw the terrain in the given rect into RGB pixel data where each tile
+    /// covers `AUTOTILE_SIDE_LENGTH` pixels on each side
+    pub fn get_texture_data_for_rect(
+        &self,
+        rect: &IRect,
+        terrain_provider: &impl TerrainProvider,
+    ) -> ByteBuffer {
+        // Include the tiles bordering the rect so the tiles on its edges can
+        // see all their neighbors
+        let terrain = terrain_provider.get_for_rect(&rect.expanded_by(1));
+        let texture_width = rect.size.width * AUTOTILE_SIDE_LENGTH;
+
+        let mut data = vec![
+            0u8;
+            rect.size.area()
+                * AUTOTILE_SIDE_LENGTH
+                * AUTOTILE_SIDE_LENGTH
+                * 3
+        ];
+
+        let mut neighborhood = [[TerrainType::Grass; 3]; 3];
+
+        for y in 0..rect.size.height {
+            for x in 0..rect.size.width {
+                for (dy, row) in neighborhood.iter_mut().enumerate() {
+                    for (dx, neighbor) in row.iter_mut().enumerate() {
+                        *neighbor = terrain
+                            .get(x + dx, y + dy)
+                            .expect("Terrain missing around autotiled rect")
+                            .1;
+                    }
+                }
+
+                self.draw_tile(
+                    pick_tile(&neighborhood),
+                    &mut data,
+                    texture_width,
+                    x * AUTOTILE_SIDE_LENGTH,
+                    y * AUTOTILE_SIDE_LENGTH,
+                );
+            }
+        }
+
+        ByteBuffer::new(data)
+    }
+
+    /// Draw the given tile into the RGB pixel data with its top left at the
+    /// given pixel
+    fn draw_tile(
+        &self,
+        source: TileSource,
+        data: &mut [u8],
+        texture_width: usize,
+        left: usize,
+        top: usize,
+    ) {
+        for row in 0..AUTOTILE_SIDE_LENGTH {
+            let start = ((top + row) * texture_width + left) * 3;
+            let pixels = data[start..start + AUTOTILE_SIDE_LENGTH * 3]
+                .chunks_exact_mut(3);
+
+            match source {
+                TileSource::Flat(terrain) => pixels
+                    .for_each(|pixel| pixel.copy_from_slice(terrain.get_rgb())),
+                TileSource::Atlas(tile_x, tile_y) => {
+                    let atlas_start = ((tile_y * AUTOTILE_SIDE_LENGTH + row)
+                        * self.atlas_size.width
+                        + tile_x * AUTOTILE_SIDE_LENGTH)
+                        * 4;
+
+                    pixels
+                        .zip(self.atlas_rgba[atlas_start..].chunks_exact(4))
+                        .for_each(|(pixel, atlas_pixel)| {
+                            pixel.copy_from_slice(&atlas_pixel[..3])
+                        });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::BiomeTerrain;
+    use crate::headless::HeadlessResourceLoader;
+    use crate::model::IPoint;
+    use crate::util::ValueRect;
+
+    use crate::game::TerrainType::{Dirt, Grass, Rock, Sand, Water};
+
+    /// Terrain provider that returns the terrain from a grid of terrain types
+    /// with its top left at the origin, and grass everywhere else
+    struct GridTerrain(Vec<Vec<TerrainType>>);
+
+    impl TerrainProvider for GridTerrain {
+        fn get_for(&self, position: &IPoint) -> TerrainType {
+            if position.x < 0 || position.y < 0 {
+                return Grass;
+            }
+
+            self.0
+                .get(position.y as usize)
+                .and_then(|row| row.get(position.x as usize))
+                .copied()
+                .unwrap_or(Grass)
+        }
+
+        fn get_for_rect(&self, rect: &IRect) -> ValueRect<(f64, TerrainType)> {
+            self.get_for_rect_with_stride(rect, 1)
+        }
+    }
+
+    #[test]
+    fn test_corner_mask() {
+        let neighborhood = [
+            [Grass, Grass, Dirt],
+            [Grass, Grass, Grass],
+            [Sand, Dirt, Grass],
+        ];
+
+        assert_eq!(
+            BOTTOM_LEFT | BOTTOM_RIGHT | TOP_RIGHT,
+            get_corner_mask(&neighborhood, Dirt)
+        );
+        assert_eq!(BOTTOM_LEFT, get_corner_mask(&neighborhood, Sand));
+        assert_eq!(0, get_corner_mask(&neighborhood, Water));
+    }
+
+    #[test]
+    fn test_pick_tile() {
+        let pick = |top: [TerrainType; 3],
+                    middle: [TerrainType; 3],
+                    bottom: [TerrainType; 3]| {
+            pick_tile(&[top, middle, bottom])
+        };
+
+        assert_eq!(
+            TileSource::Atlas(0, 0),
+            pick([Grass; 3], [Grass; 3], [Grass; 3])
+        );
+
+        // Solid patches use the center of their outer block
+        assert_eq!(
+            TileSource::Atlas(1, 30),
+            pick([Grass; 3], [Grass, Dirt, Grass], [Grass; 3])
+        );
+
+        // Grass above a patch of dirt has the dirt along its bottom
+        assert_eq!(
+            TileSource::Atlas(1, 29),
+            pick([Grass; 3], [Grass; 3], [Dirt; 3])
+        );
+
+        // Grass diagonal to sand has the sand in one corner
+        assert_eq!(
+            TileSource::Atlas(2, 5),
+            pick([Sand, Grass, Grass], [Grass; 3], [Grass; 3])
+        );
+
+        // Grass with water on two sides is an inner corner
+        assert_eq!(
+            TileSource::Atlas(2, 9),
+            pick([Water, Water, Grass], [Water, Grass, Grass], [Grass; 3])
+        );
+
+        // Water takes priority over dirt
+        assert_eq!(
+            TileSource::Atlas(3, 8),
+            pick([Dirt, Water, Dirt], [Grass; 3], [Grass; 3])
+        );
+
+        // Opposite corners have no transition tile so the patch fills them
+        assert_eq!(
+            TileSource::Atlas(1, 30),
+            pick([Dirt, Grass, Grass], [Grass; 3], [Grass, Grass, Dirt])
+        );
+
+        // Terrain without atlas tiles is drawn flat and doesn't spill into
+        // the grass around it
+        assert_eq!(
+            TileSource::Flat(Rock),
+            pick([Grass; 3], [Grass, Rock, Grass], [Grass; 3])
+        );
+        assert_eq!(
+            TileSource::Atlas(0, 0),
+            pick([Rock; 3], [Grass; 3], [Grass; 3])
+        );
+    }
+
+    /// Get the RGB pixels of the given tile in the atlas
+    fn atlas_tile(
+        autotiler: &TerrainAutotiler,
+        tile_x: usize,
+        tile_y: usize,
+    ) -> Vec<u8> {
+        let mut result = Vec::new();
+
+        for row in 0..AUTOTILE_SIDE_LENGTH {
+            let start = ((tile_y * AUTOTILE_SIDE_LENGTH + row)
+                * autotiler.atlas_size.width
+                + tile_x * AUTOTILE_SIDE_LENGTH)
+                * 4;
+
+            autotiler.atlas_rgba[start..start + AUTOTILE_SIDE_LENGTH * 4]
+                .chunks_exact(4)
+                .for_each(|pixel| result.extend_from_slice(&pixel[..3]));
+        }
+
+        result
+    }
+
+    /// Get the RGB pixels of the tile at the given tile coordinates out of
+    /// texture data for a rect of the given width in tiles
+    fn texture_tile(data: &[u8], width: usize, x: usize, y: usize) -> Vec<u8> {
+        let texture_width = width * AUTOTILE_SIDE_LENGTH;
+        let mut result = Vec::new();
+
+        for row in 0..AUTOTILE_SIDE_LENGTH {
+            let start = ((y * AUTOTILE_SIDE_LENGTH + row) * texture_width
+                + x * AUTOTILE_SIDE_LENGTH)
+                * 3;
+
+            result.extend_from_slice(
+                &data[start..start + AUTOTILE_SIDE_LENGTH * 3],
+            );
+        }
+
+        result
+    }
+
+    #[test]
+    fn test_texture_composed_from_atlas() {
+        // A single dirt tile at (1, 1) with rock below it, and the rect's
+        // top left tile bordering dirt outside of the rect
+        let terrain = GridTerrain(vec![
+            vec![Grass, Grass, Grass],
+            vec![Grass, Dirt, Grass],
+            vec![Grass, Rock, Grass],
+        ]);
+
+        let autotiler =
+            TerrainAutotiler::load(&HeadlessResourceLoader::default());
+        let rect = IRect::new(0, 0, 3, 3);
+        let data = autotiler.get_texture_data_for_rect(&rect, &terrain);
+
+        assert_eq!(rect.size.area() * 16 * 16 * 3, data.len());
+
+        assert_eq!(atlas_tile(&autotiler, 0, 29), texture_tile(&data, 3, 0, 0));
+        assert_eq!(atlas_tile(&autotiler, 1, 29), texture_tile(&data, 3, 1, 0));
+        assert_eq!(atlas_tile(&autotiler, 1, 30), texture_tile(&data, 3, 1, 1));
+        assert_eq!(atlas_tile(&autotiler, 2, 30), texture_tile(&data, 3, 2, 1));
+
+        let rock_tile = texture_tile(&data, 3, 1, 2);
+
+        rock_tile
+            .chunks_exact(3)
+            .for_each(|pixel| assert_eq!(&Rock.get_rgb()[..], pixel));
+
+        // Terrain outside of the rect is taken into account
+        let shifted = autotiler
+            .get_texture_data_for_rect(&IRect::new(2, 0, 1, 1), &terrain);
+
+        assert_eq!(
+            atlas_tile(&autotiler, 2, 29),
+            texture_tile(&shifted, 1, 0, 0)
+        );
+
+        // The generated terrain can be autotiled
+        let generated = autotiler.get_texture_data_for_rect(
+            &IRect::new(-8, 40, 16, 16),
+            &BiomeTerrain::default(),
+        );
+
+        assert_eq!(256 * 256 * 3, generated.len());
+    }
+}
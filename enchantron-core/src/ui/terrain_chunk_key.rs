@@ -0,0 +1,10 @@
+use crate::model::{IRect, ISize};
+
+/// Key for a chunk of terrain drawn into a texture. The texture size relative
+/// to the terrain rect is the zoom level, so a chunk drawn at different zoom
+/// levels is a different chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TerrainChunkKey {
+    pub terrain_rect: IRect,
+    pub texture_size: ISize,
+}
@@ -0,0 +1,302 @@
+use super::terrain_texture_provider::get_texture_data_for_rect;
+use super::{TerrainAutotiler, TerrainChunkKey};
+use crate::game::{BiomeTerrain, ModifiedTerrain};
+use crate::util::ByteBuffer;
+use std::collections::HashSet;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Request to generate the texture for a chunk of terrain. Chunks needed to
+/// fill the viewport are generated before prefetched chunks, and within those
+/// groups the chunks closest to the viewport center are generated first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerrainJob {
+    pub key: TerrainChunkKey,
+    pub prefetch: bool,
+    /// Squared distance in tiles from the viewport center to the chunk center
+    pub distance: u64,
+}
+
+/// Pixels for a chunk of terrain produced by a terrain worker
+#[derive(Clone)]
+pub struct GeneratedTerrainChunk {
+    pub key: TerrainChunkKey,
+    pub pixel_data: ByteBuffer,
+    /// Revision of the terrain modifications the chunk was generated with
+    pub revision: u64,
+}
+
+#[derive(Default)]
+struct QueueState {
+    /// Jobs waiting to be picked up, sorted so the next job is at the end
+    jobs: Vec<TerrainJob>,
+    in_progress: HashSet<TerrainChunkKey>,
+    stopped: bool,
+}
+
+#[derive(Default)]
+struct SharedQueue {
+    state: Mutex<QueueState>,
+    job_available: Condvar,
+}
+
+/// Pool of worker threads that generate terrain chunk textures off of the
+/// presenter's event loop. The set of jobs is replaced wholesale each time
+/// the viewport changes, so chunks that scroll away before a worker picks
+/// them up are never generated
+pub struct TerrainJobQueue {
+    shared: Arc<SharedQueue>,
+}
+
+impl TerrainJobQueue {
+    pub fn new(
+        worker_count: usize,
+        terrain_generator: Arc<ModifiedTerrain<BiomeTerrain>>,
+        autotiler: Arc<TerrainAutotiler>,
+        sender: UnboundedSender<GeneratedTerrainChunk>,
+    ) -> TerrainJobQueue {
+        let shared = Arc::new(SharedQueue::default());
+
+        for worker_index in 0..worker_count {
+            let shared = shared.clone();
+            let terrain_generator = terrain_generator.clone();
+            let autotiler = autotiler.clone();
+            let sender = sender.clone();
+
+            thread::Builder::new()
+                .name(format!("terrain-worker-{}", worker_index))
+                .spawn(move || {
+                    run_worker(&shared, &terrain_generator, &autotiler, &sender)
+                })
+                .expect("Failed to start terrain worker");
+        }
+
+        TerrainJobQueue { shared }
+    }
+
+    /// Replace all the waiting jobs with the given ones. Jobs for chunks that
+    /// are already being generated are dropped
+    pub fn schedule(&self, jobs: impl IntoIterator<Item = TerrainJob>) {
+        let mut state = self.lock_state();
+
+        let mut new_jobs = jobs
+            .into_iter()
+            .filter(|job| !state.in_progress.contains(&job.key))
+            .collect::<Vec<_>>();
+
+        // Keep only the most urgent job for each chunk, and put the most
+        // urgent job last so it's popped first
+        let mut scheduled_keys = HashSet::new();
+
+        new_jobs.sort_by_key(|job| (job.prefetch, job.distance));
+        new_jobs.retain(|job| scheduled_keys.insert(job.key));
+        new_jobs.reverse();
+
+        state.jobs = new_jobs;
+
+        self.shared.job_available.notify_all();
+    }
+
+    /// Get the number of jobs that are waiting for a worker
+    #[cfg(test)]
+    pub fn waiting_count(&self) -> usize {
+        self.lock_state().jobs.len()
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, QueueState> {
+        self.shared
+            .state
+            .lock()
+            .expect("Terrain job queue poisoned")
+    }
+}
+
+impl Drop for TerrainJobQueue {
+    fn drop(&mut self) {
+        self.lock_state().stopped = true;
+        self.shared.job_available.notify_all();
+    }
+}
+
+/// Take the next job, blocking until one is available. Returns None once the
+/// queue is stopped
+fn take_job(shared: &SharedQueue) -> Option<TerrainJob> {
+    let mut state = shared.state.lock().expect("Terrain job queue poisoned");
+
+    loop {
+        if state.stopped {
+            return None;
+        }
+
+        if let Some(job) = state.jobs.pop() {
+            state.in_progress.insert(job.key);
+            return Some(job);
+        }
+
+        state = shared
+            .job_available
+            .wait(state)
+            .expect("Terrain job queue poisoned");
+    }
+}
+
+fn run_worker(
+    shared: &SharedQueue,
+    terrain_generator: &ModifiedTerrain<BiomeTerrain>,
+    autotiler: &TerrainAutotiler,
+    sender: &UnboundedSender<GeneratedTerrainChunk>,
+) {
+    while let Some(job) = take_job(shared) {
+        let key = job.key;
+
+        // Autotiled chunks depend on the tiles just outside them too
+        let affected_rect = key.terrain_rect.expanded_by(1);
+
+        let (pixel_data, revision) = loop {
+            let revision = terrain_generator.revision();
+
+            let pixel_data = get_texture_data_for_rect(
+                &key.terrain_rect,
+                &key.texture_size,
+                terrain_generator,
+                autotiler,
+            );
+
+            let mut state =
+                shared.state.lock().expect("Terrain job queue poisoned");
+
+            // If the terrain was modified while the chunk was generated, the
+            // chunk couldn't be scheduled again because it was in progress,
+            // so it's generated again here instead
+            if terrain_generator.modified_since(&affected_rect, revision) {
+                continue;
+            }
+
+            state.in_progress.remove(&key);
+
+            break (pixel_data, revision);
+        };
+
+        if sender
+            .send(GeneratedTerrainChunk {
+                key,
+                pixel_data,
+                revision,
+            })
+            .is_err()
+        {
+            debug!("Terrain chunk receiver dropped, stopping worker");
+            return;
+        }
+    }
+
+    debug!("Terrain worker stopped");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::TerrainService;
+    use crate::headless::HeadlessResourceLoader;
+    use crate::model::{IRect, ISize};
+    use futures::executor::block_on;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    fn job(x: i64, prefetch: bool, distance: u64) -> TerrainJob {
+        TerrainJob {
+            key: TerrainChunkKey {
+                terrain_rect: IRect::new(x, 0, 8, 8),
+                texture_size: ISize::new(8, 8),
+            },
+            prefetch,
+            distance,
+        }
+    }
+
+    fn default_autotiler() -> Arc<TerrainAutotiler> {
+        Arc::new(TerrainAutotiler::load(&HeadlessResourceLoader::default()))
+    }
+
+    fn default_terrain() -> Arc<ModifiedTerrain<BiomeTerrain>> {
+        Arc::new(ModifiedTerrain::new(
+            BiomeTerrain::default(),
+            TerrainService::default(),
+        ))
+    }
+
+    #[test]
+    fn test_schedule_orders_and_replaces_jobs() {
+        let (sender, _receiver) = unbounded_channel();
+
+        // Without workers the jobs stay in the queue to be inspected
+        let queue = TerrainJobQueue::new(
+            0,
+            default_terrain(),
+            default_autotiler(),
+            sender,
+        );
+
+        queue.schedule(vec![
+            job(0, true, 1),
+            job(8, false, 9),
+            job(16, false, 4),
+            job(16, false, 4),
+        ]);
+
+        assert_eq!(3, queue.waiting_count());
+
+        let taken = (0..3)
+            .filter_map(|_| take_job(&queue.shared))
+            .map(|job| job.key.terrain_rect.top_left.x)
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec![16, 8, 0], taken);
+
+        // Jobs in progress aren't queued again and missing jobs are cancelled
+        queue.schedule(vec![job(8, false, 1), job(24, false, 2)]);
+
+        assert_eq!(1, queue.waiting_count());
+        assert_eq!(
+            Some(24),
+            take_job(&queue.shared).map(|job| job.key.terrain_rect.top_left.x)
+        );
+    }
+
+    #[test]
+    fn test_workers_generate_chunks() {
+        let (sender, mut receiver) = unbounded_channel();
+        let terrain_generator = default_terrain();
+        let autotiler = default_autotiler();
+
+        let queue = TerrainJobQueue::new(
+            2,
+            terrain_generator.clone(),
+            autotiler.clone(),
+            sender,
+        );
+
+        let jobs = vec![job(0, false, 0), job(8, false, 1), job(16, true, 0)];
+        queue.schedule(jobs.clone());
+
+        let mut generated = (0..jobs.len())
+            .map(|_| block_on(receiver.recv()).expect("Worker stopped"))
+            .collect::<Vec<_>>();
+
+        generated.sort_by_key(|chunk| chunk.key.terrain_rect.top_left.x);
+
+        for (job, chunk) in jobs.iter().zip(generated) {
+            let expected = get_texture_data_for_rect(
+                &job.key.terrain_rect,
+                &job.key.texture_size,
+                terrain_generator.as_ref(),
+                autotiler.as_ref(),
+            );
+
+            assert_eq!(job.key, chunk.key);
+            assert_eq!(&expected[..], &chunk.pixel_data[..]);
+        }
+
+        assert_eq!(0, queue.waiting_count());
+    }
+}
@@ -1,90 +1,90 @@
+use super::{
+    GeneratedTerrainChunk, TerrainAutotiler, TerrainChunkKey, TerrainJob,
+    TerrainJobQueue,
+};
 use crate::application_context::Ao;
-use crate::game::constants;
 use crate::game::{
-    SimplexTerrain1 as PerlinTerrain1, TerrainProvider, TerrainType,
+    BiomeTerrain, ModifiedTerrain, TerrainProvider, TerrainService, TerrainType,
 };
-use crate::img::PngGenerator;
 use crate::model::{IRect, ISize};
-use crate::native::{ResourceLoader, RuntimeResources};
-use crate::util::ByteBuffer;
+use crate::native::{PixelFormat, ResourceLoader, RuntimeResources};
+use crate::util::{ByteBuffer, CacheStats, LruCache};
 use crate::view_types::ViewTypes;
-use std::ptr::copy_nonoverlapping;
-use std::sync::Arc;
-
-const BROWN_BYTES: [u8; 3] = constants::DIRT_BROWN_RGB;
-const GREEN_BYTES: [u8; 3] = constants::GRASS_GREEN_RGB;
-
-/// Write to the given image-data slice in the locations corresponding to
-/// the
-unsafe fn fill_rect_with_terrain(
-    target: *mut u8,
-    image_width: usize,
-    tile_width: usize,
-    tile_height: usize,
-    terrain: &TerrainType,
-) {
-    let src_bytes = match terrain {
-        TerrainType::Grass => GREEN_BYTES.as_ptr(),
-        TerrainType::Dirt => BROWN_BYTES.as_ptr(),
-    };
-
-    let mut target = target;
-
-    for _ in 0..tile_height {
-        for _ in 0..tile_width {
-            copy_nonoverlapping(src_bytes, target, 3);
-            target = target.add(3);
-        }
-        target = target.add(3 * (image_width - tile_width));
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+/// Number of terrain chunks kept around for when they scroll back into
+/// view
+const CHUNK_CACHE_CAPACITY: usize = 256;
+
+/// Most threads to generate terrain on. One core is left for the presenters
+const MAX_TERRAIN_WORKER_COUNT: usize = 4;
+
+/// Get the format of the pixel data generated for the given chunk
+fn get_pixel_format(key: &TerrainChunkKey) -> PixelFormat {
+    if TerrainAutotiler::is_autotiled(&key.terrain_rect, &key.texture_size) {
+        PixelFormat::Rgb
+    } else {
+        PixelFormat::Indexed
     }
 }
 
-fn get_texture_data_for_rect(
+/// Draw the terrain in the given rect into pixel data of the given size. When
+/// the texture has room for it, the terrain is autotiled into RGB pixels.
+/// Otherwise each pixel is the palette index of its terrain type
+pub fn get_texture_data_for_rect(
     rect: &IRect,
     texture_size: &ISize,
     terrain_generator: &impl TerrainProvider,
+    autotiler: &TerrainAutotiler,
 ) -> ByteBuffer {
-    let texture_size = &rect.size;
-
-    let x_tile_pixels = texture_size.width / rect.size.width;
-    let y_tile_pixels = texture_size.height / rect.size.height;
-
-    debug_assert_eq!(x_tile_pixels * rect.size.width, texture_size.width);
-    debug_assert_eq!(y_tile_pixels * rect.size.height, texture_size.height);
-
-    let mut data = Vec::<u8>::with_capacity(texture_size.area() * 3);
-
-    unsafe {
-        data.set_len(data.capacity());
-        let data_ptr = data.as_mut_ptr();
-
-        let terrain_data = terrain_generator.get_for_rect(rect);
-
-        terrain_data.for_each_value_coord(|coord, (val, terrain)| {
-            let curr_ptr = data_ptr.add(
-                3 * x_tile_pixels
-                    * (coord.x + coord.y * y_tile_pixels * rect.size.width),
-            );
-            fill_rect_with_terrain(
-                curr_ptr,
-                texture_size.width,
-                x_tile_pixels,
-                y_tile_pixels,
-                terrain,
-            )
-        });
+    if TerrainAutotiler::is_autotiled(rect, texture_size) {
+        return autotiler.get_texture_data_for_rect(rect, terrain_generator);
     }
 
-    let mut result = ByteBuffer::new(Vec::<u8>::with_capacity(4096));
+    // When the rect has more tiles than the texture has pixels, only sample
+    // the terrain once per pixel. Otherwise each tile covers a block of pixels
+    let stride = (rect.size.width / texture_size.width).max(1);
+    let samples_wide = rect.size.width / stride;
+    let samples_high = rect.size.height / stride;
+
+    let x_tile_pixels = texture_size.width / samples_wide;
+    let y_tile_pixels = texture_size.height / samples_high;
 
-    PngGenerator::get_png(data.as_slice(), texture_size, &mut result);
+    debug_assert_eq!(x_tile_pixels * samples_wide, texture_size.width);
+    debug_assert_eq!(y_tile_pixels * samples_high, texture_size.height);
 
-    result
+    let mut data = vec![0u8; texture_size.area()];
+
+    terrain_generator
+        .get_for_rect_with_stride(rect, stride)
+        .for_each_value_coord(|coord, (_, terrain)| {
+            let left = coord.x * x_tile_pixels;
+            let top = coord.y * y_tile_pixels;
+
+            for y in top..top + y_tile_pixels {
+                let row_start = y * texture_size.width + left;
+
+                data[row_start..row_start + x_tile_pixels]
+                    .fill(terrain.palette_index());
+            }
+        });
+
+    ByteBuffer::new(data)
 }
+
+/// Source of terrain textures. Textures are requested from a pool of terrain
+/// workers and received as they finish
 pub struct TerrainTextureProvider<T: ViewTypes> {
-    terrain_generator: PerlinTerrain1,
+    terrain_generator: Arc<ModifiedTerrain<BiomeTerrain>>,
     runtime_resources: Ao<RuntimeResources<T>>,
     texture_loader: T::ResourceLoader,
+    /// Cached chunks are shared so a cache hit doesn't copy the pixels while
+    /// the cache is locked
+    chunk_cache: Mutex<LruCache<TerrainChunkKey, Arc<ByteBuffer>>>,
+    palette: ByteBuffer,
+    job_queue: TerrainJobQueue,
+    generated_chunks: UnboundedReceiver<GeneratedTerrainChunk>,
 }
 
 impl<T> TerrainTextureProvider<T>
@@ -94,40 +94,193 @@ where
     pub fn new(
         runtime_resources: Ao<RuntimeResources<T>>,
         texture_loader: T::ResourceLoader,
+        seed: u64,
+        terrain_service: TerrainService,
     ) -> TerrainTextureProvider<T> {
+        let terrain_generator = Arc::new(ModifiedTerrain::new(
+            BiomeTerrain::from_config(
+                &runtime_resources.terrain_config().biome,
+                seed,
+            ),
+            terrain_service,
+        ));
+
+        let autotiler = Arc::new(TerrainAutotiler::load(&texture_loader));
+        let (sender, generated_chunks) = unbounded_channel();
+
+        let worker_count = num_cpus::get()
+            .saturating_sub(1)
+            .clamp(1, MAX_TERRAIN_WORKER_COUNT);
+
+        let job_queue = TerrainJobQueue::new(
+            worker_count,
+            terrain_generator.clone(),
+            autotiler,
+            sender,
+        );
+
         TerrainTextureProvider {
             runtime_resources,
             texture_loader,
-            terrain_generator: Default::default(),
+            terrain_generator,
+            chunk_cache: Mutex::new(LruCache::new(CHUNK_CACHE_CAPACITY)),
+            palette: ByteBuffer::new(TerrainType::get_palette()),
+            job_queue,
+            generated_chunks,
         }
     }
 
-    pub fn get_texture_for_rect(
+    /// Get the texture for the given chunk only if it's already cached
+    pub fn get_cached_texture(
         &self,
+        key: &TerrainChunkKey,
+    ) -> Option<T::Texture> {
+        let pixel_data = self
+            .chunk_cache
+            .lock()
+            .expect("Terrain chunk cache poisoned")
+            .get(key)
+            .cloned()?;
+
+        Some(self.load_texture(key, &pixel_data))
+    }
+
+    /// Replace the jobs waiting for the terrain workers with the given jobs.
+    /// Jobs for chunks that are already cached are skipped
+    pub fn schedule(&self, jobs: Vec<TerrainJob>) {
+        let chunk_cache = self
+            .chunk_cache
+            .lock()
+            .expect("Terrain chunk cache poisoned");
+
+        self.job_queue.schedule(
+            jobs.into_iter()
+                .filter(|job| !chunk_cache.contains_key(&job.key)),
+        );
+    }
+
+    /// Drop the cached chunks that show any of the tiles in the given rect so
+    /// they are generated again with the current terrain
+    pub fn invalidate(&self, rect: &IRect) {
+        // Autotiled chunks show the edges of the tiles around them too
+        let affected_rect = rect.expanded_by(1);
+
+        self.chunk_cache
+            .lock()
+            .expect("Terrain chunk cache poisoned")
+            .retain(|key, _| {
+                key.terrain_rect.intersection(&affected_rect).is_none()
+            });
+    }
+
+    /// Wait for the next chunk generated by the terrain workers, and cache it
+    /// in case it's needed again after scrolling away. Chunks generated from
+    /// terrain that was modified since are dropped, because they have already
+    /// been scheduled again
+    pub async fn next_generated_texture(
+        &mut self,
+    ) -> Option<(TerrainChunkKey, T::Texture)> {
+        let (key, pixel_data) = loop {
+            let GeneratedTerrainChunk {
+                key,
+                pixel_data,
+                revision,
+            } = self.generated_chunks.recv().await?;
+
+            if !self
+                .terrain_generator
+                .modified_since(&key.terrain_rect.expanded_by(1), revision)
+            {
+                break (key, pixel_data);
+            }
+        };
+
+        let pixel_data = Arc::new(pixel_data);
+
+        self.chunk_cache
+            .lock()
+            .expect("Terrain chunk cache poisoned")
+            .insert(key, pixel_data.clone());
+
+        Some((key, self.load_texture(&key, &pixel_data)))
+    }
+
+    /// Get the texture for the given terrain rect from the cache, or block
+    /// until the terrain workers generate it
+    #[cfg(test)]
+    pub fn get_texture_for_rect(
+        &mut self,
         rect: &IRect,
         texture_size: &ISize,
     ) -> T::Texture {
-        self.texture_loader.load_texture_from_png_data(
-            get_texture_data_for_rect(
-                rect,
-                texture_size,
-                &self.terrain_generator,
-            ),
+        let key = TerrainChunkKey {
+            terrain_rect: *rect,
+            texture_size: *texture_size,
+        };
+
+        if let Some(texture) = self.get_cached_texture(&key) {
+            return texture;
+        }
+
+        self.schedule(vec![TerrainJob {
+            key,
+            prefetch: false,
+            distance: 0,
+        }]);
+
+        loop {
+            let (generated_key, texture) =
+                futures::executor::block_on(self.next_generated_texture())
+                    .expect("Terrain workers stopped");
+
+            if generated_key == key {
+                return texture;
+            }
+        }
+    }
+
+    /// Upload the given pixels as a texture. The loader takes ownership of the
+    /// pixels it's given, so they are copied
+    fn load_texture(
+        &self,
+        key: &TerrainChunkKey,
+        pixel_data: &ByteBuffer,
+    ) -> T::Texture {
+        self.texture_loader.load_texture_from_pixels(
+            pixel_data.clone(),
+            self.palette.clone(),
+            key.texture_size.width as i64,
+            key.texture_size.height as i64,
+            get_pixel_format(key).code(),
         )
     }
+
+    /// Get the hit and miss counts of the terrain chunk cache
+    pub fn get_cache_stats(&self) -> CacheStats {
+        self.chunk_cache
+            .lock()
+            .expect("Terrain chunk cache poisoned")
+            .stats()
+    }
 }
 
 #[allow(dead_code, unused_imports)]
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::headless::{HeadlessResourceLoader, HeadlessViewTypes};
+    use crate::img::PngGenerator;
+    use crate::model::IPoint;
+    use crate::native::{Animations, Textures};
     use std::fs::File;
     use std::io::prelude::*;
     use std::time::SystemTime;
 
     #[test]
     fn test_generate_texture_data() {
-        let terrain_generator = PerlinTerrain1::default();
+        let terrain_generator = BiomeTerrain::default();
+        let autotiler =
+            TerrainAutotiler::load(&HeadlessResourceLoader::default());
 
         let terrain_rect = IRect::new(0, 0, 16, 16);
         let image_size = ISize::new(16, 16);
@@ -138,6 +291,7 @@ mod test {
             &terrain_rect,
             &image_size,
             &terrain_generator,
+            &autotiler,
         );
 
         println!("Terrain texture generation time: {:?}", now.elapsed());
@@ -154,4 +308,251 @@ mod test {
         //     pos += bytes_written;
         // }
     }
+
+    fn create_provider() -> (
+        Box<RuntimeResources<HeadlessViewTypes>>,
+        TerrainTextureProvider<HeadlessViewTypes>,
+    ) {
+        create_provider_with(TerrainService::default())
+    }
+
+    /// Create a texture provider along with the runtime resources it refers
+    /// to, which must be kept alive as long as the provider is used
+    fn create_provider_with(
+        terrain_service: TerrainService,
+    ) -> (
+        Box<RuntimeResources<HeadlessViewTypes>>,
+        TerrainTextureProvider<HeadlessViewTypes>,
+    ) {
+        let resource_loader = HeadlessResourceLoader::default();
+        let textures =
+            Textures::<HeadlessViewTypes>::new(&resource_loader, &|_| {});
+        let animations = Animations::new(&resource_loader, &textures);
+        let runtime_resources = Box::new(RuntimeResources::new(
+            textures,
+            animations,
+            Default::default(),
+        ));
+
+        let provider = TerrainTextureProvider::new(
+            Ao::new(&runtime_resources),
+            resource_loader,
+            0,
+            terrain_service,
+        );
+
+        (runtime_resources, provider)
+    }
+
+    fn assert_pixel_is(texture_pixel: [u8; 4], terrain: TerrainType) {
+        assert_eq!(&texture_pixel[..3], &terrain.get_rgb()[..]);
+        assert_eq!(255, texture_pixel[3]);
+    }
+
+    #[test]
+    fn test_coarse_texture_samples_terrain() {
+        let (_runtime_resources, mut provider) = create_provider();
+        let terrain = BiomeTerrain::default();
+
+        // Each pixel covers a 32x32 block of tiles, sampled at its top left
+        let rect = IRect::new(-1024, 512, 512, 512);
+        let size = ISize::new(16, 16);
+
+        let texture = provider.get_texture_for_rect(&rect, &size);
+
+        for y in 0..16 {
+            for x in 0..16 {
+                let position = IPoint::new(
+                    rect.top_left.x + 32 * x as i64,
+                    rect.top_left.y + 32 * y as i64,
+                );
+                assert_pixel_is(
+                    texture.get_pixel(x, y),
+                    terrain.get_for(&position),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fine_texture_scales_tiles() {
+        let (_runtime_resources, mut provider) = create_provider();
+        let terrain = BiomeTerrain::default();
+
+        // Each tile covers a 4x4 block of pixels
+        let rect = IRect::new(100, -60, 4, 4);
+        let size = ISize::new(16, 16);
+
+        let texture = provider.get_texture_for_rect(&rect, &size);
+
+        for y in 0..16 {
+            for x in 0..16 {
+                let position = IPoint::new(
+                    rect.top_left.x + (x / 4) as i64,
+                    rect.top_left.y + (y / 4) as i64,
+                );
+                assert_pixel_is(
+                    texture.get_pixel(x, y),
+                    terrain.get_for(&position),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalidate_regenerates_modified_chunks() {
+        let terrain_service = TerrainService::default();
+        let (_runtime_resources, mut provider) =
+            create_provider_with(terrain_service.clone());
+
+        let rect = IRect::new(0, 0, 4, 4);
+        let neighbor_rect = IRect::new(4, 0, 4, 4);
+        let far_rect = IRect::new(64, 0, 4, 4);
+        let size = ISize::new(4, 4);
+        let position = IPoint::new(3, 1);
+
+        for rect in &[rect, neighbor_rect, far_rect] {
+            provider.get_texture_for_rect(rect, &size);
+        }
+
+        terrain_service.modify(position, Some(TerrainType::Snow));
+        provider.invalidate(&IRect::new(3, 1, 1, 1));
+
+        // The neighbor shows the edge of the modified tile when autotiled, so
+        // it's dropped too
+        let cached = |rect: &IRect| {
+            provider
+                .get_cached_texture(&TerrainChunkKey {
+                    terrain_rect: *rect,
+                    texture_size: size,
+                })
+                .is_some()
+        };
+
+        assert!(!cached(&rect));
+        assert!(!cached(&neighbor_rect));
+        assert!(cached(&far_rect));
+
+        let texture = provider.get_texture_for_rect(&rect, &size);
+
+        assert_pixel_is(texture.get_pixel(3, 1), TerrainType::Snow);
+    }
+
+    #[test]
+    fn test_detail_texture_is_autotiled() {
+        let (_runtime_resources, mut provider) = create_provider();
+        let terrain = BiomeTerrain::default();
+
+        // Each tile covers a full atlas tile, so the chunk is autotiled
+        let rect = IRect::new(-30, 12, 2, 2);
+        let size = ISize::new(32, 32);
+
+        let texture = provider.get_texture_for_rect(&rect, &size);
+        let expected =
+            TerrainAutotiler::load(&HeadlessResourceLoader::default())
+                .get_texture_data_for_rect(&rect, &terrain);
+
+        for y in 0..32 {
+            for x in 0..32 {
+                let offset = (y * 32 + x) * 3;
+                let pixel = texture.get_pixel(x, y);
+
+                assert_eq!(&expected[offset..offset + 3], &pixel[..3]);
+                assert_eq!(255, pixel[3]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pixel_upload_against_png() {
+        let terrain_generator = BiomeTerrain::default();
+        let loader = HeadlessResourceLoader::default();
+        let autotiler = TerrainAutotiler::load(&loader);
+        let palette = TerrainType::get_palette();
+        let size = ISize::new(128, 128);
+
+        let chunks = (0..16)
+            .map(|i| {
+                get_texture_data_for_rect(
+                    &IRect::new(128 * i, 0, 128, 128),
+                    &size,
+                    &terrain_generator,
+                    &autotiler,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let png_textures = chunks
+            .iter()
+            .map(|pixel_data| {
+                let mut png_data = ByteBuffer::new(Vec::with_capacity(4096));
+
+                PngGenerator::get_png_indexed(
+                    pixel_data,
+                    palette.clone(),
+                    &size,
+                    &mut png_data,
+                );
+
+                loader.load_texture_from_png_data(png_data)
+            })
+            .collect::<Vec<_>>();
+
+        let pixel_textures = chunks
+            .iter()
+            .map(|pixel_data| {
+                loader.load_texture_from_pixels(
+                    pixel_data.clone(),
+                    ByteBuffer::new(palette.clone()),
+                    size.width as i64,
+                    size.height as i64,
+                    PixelFormat::Indexed.code(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for (png_texture, pixel_texture) in
+            png_textures.iter().zip(&pixel_textures)
+        {
+            for y in 0..size.height {
+                for x in 0..size.width {
+                    assert_eq!(
+                        png_texture.get_pixel(x, y),
+                        pixel_texture.get_pixel(x, y)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunk_cache() {
+        let (_runtime_resources, mut provider) = create_provider();
+
+        let first = IRect::new(0, 0, 16, 16);
+        let second = IRect::new(16, 0, 16, 16);
+        let size = ISize::new(16, 16);
+
+        let texture = provider.get_texture_for_rect(&first, &size);
+        provider.get_texture_for_rect(&second, &size);
+        let cached_texture = provider.get_texture_for_rect(&first, &size);
+
+        for y in 0..16 {
+            for x in 0..16 {
+                assert_eq!(
+                    texture.get_pixel(x, y),
+                    cached_texture.get_pixel(x, y)
+                );
+            }
+        }
+
+        assert_eq!(
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                evictions: 0,
+            },
+            provider.get_cache_stats()
+        );
+    }
 }
@@ -1,16 +1,16 @@
 pub use self::boxed_any::*;
 pub use self::byte_buffer::ByteBuffer;
+pub use self::cache_stats::CacheStats;
 pub use self::concurrent_slotmap::ConcurrentSlotmap;
 pub use self::corner_values::CornerValues;
 #[cfg(test)]
 pub use self::default_xxhash_ipoint_hasher::DefaultXxHashIPointHasher;
 pub use self::dyn_action_sink::{AnyConsumer, DynActionSink, Selector};
-pub use self::harmonic_perlin_generator::HarmonicPerlinGenerator;
 pub use self::immutable_thread_local::ImmutableThreadLocal;
 pub use self::ipoint_hasher::IPointHasher;
+pub use self::lru_cache::LruCache;
 pub use self::restricted_xx_hasher::RestrictedXxHasher;
 pub use self::simplex_generator::SimplexGenerator;
-pub use self::single_perlin_generator::SinglePerlinGenerator;
 pub use self::thread_id::thread_id;
 pub use self::value_rect::ValueRect;
 
@@ -19,14 +19,14 @@ mod default_xxhash_ipoint_hasher;
 
 mod boxed_any;
 mod byte_buffer;
+mod cache_stats;
 mod concurrent_slotmap;
 mod corner_values;
 mod dyn_action_sink;
-mod harmonic_perlin_generator;
 mod immutable_thread_local;
 mod ipoint_hasher;
+mod lru_cache;
 mod restricted_xx_hasher;
 mod simplex_generator;
-mod single_perlin_generator;
 mod thread_id;
 mod value_rect;
@@ -1,5 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
+#[derive(Clone)]
 pub struct ByteBuffer(Vec<u8>);
 
 impl ByteBuffer {
@@ -3,10 +3,13 @@ use crate::util::ImmutableThreadLocal;
 use ev_slotmap::{MapReadRef, ReadGuard, ReadHandle, WriteHandle};
 use evmap::ShallowCopy;
 use one_way_slot_map::{SlotMap, SlotMapKey};
+use std::ops::Deref;
 use tokio::sync::Mutex;
 
 /// Centralization of the components of a ev_slotmap in a component that can
-/// be shared safely across a _limited_ number of threads
+/// be shared safely across a _limited_ number of threads. Values are stored
+/// as options so removed values can be emptied out of their slots, because
+/// the slot map only drops removed values once their slots are reused
 #[derive(Debug)]
 pub struct ConcurrentSlotmap<K, P, V>
 where
@@ -14,8 +17,22 @@ where
     P: Send,
     V: ShallowCopy + Send,
 {
-    writer: Mutex<WriteHandle<K, P, V>>,
-    readers: ImmutableThreadLocal<ReadHandle<K, P, V>>,
+    writer: Mutex<WriteHandle<K, P, Option<V>>>,
+    readers: ImmutableThreadLocal<ReadHandle<K, P, Option<V>>>,
+}
+
+/// Guard for reading a value in a concurrent slot map. The map can't change
+/// while this is held, so it should be dropped as soon as possible
+pub struct ConcurrentSlotmapRef<'a, V>(ReadGuard<'a, Option<V>>);
+
+impl<'a, V> Deref for ConcurrentSlotmapRef<'a, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        (*self.0)
+            .as_ref()
+            .expect("Only filled values are handed out")
+    }
 }
 
 impl<K, P, V> ConcurrentSlotmap<K, P, V>
@@ -24,12 +41,19 @@ where
     P: Send,
     V: ShallowCopy + Send,
 {
-    pub fn new() -> ConcurrentSlotmap<K, P, V> {
+    pub fn new() -> ConcurrentSlotmap<K, P, V>
+    where
+        V: Clone,
+    {
         Self::new_with_data(SlotMap::new())
     }
 
-    pub fn new_with_data(data: SlotMap<K, P, V>) -> ConcurrentSlotmap<K, P, V> {
-        let (_, slotmap) = ev_slotmap::new_with_data(data);
+    pub fn new_with_data(data: SlotMap<K, P, V>) -> ConcurrentSlotmap<K, P, V>
+    where
+        V: Clone,
+    {
+        let (_, slotmap) =
+            ev_slotmap::new_with_data(data.map(|value| Some(value.clone())));
 
         let num_readers = *NUM_CPUS * 3;
         let mut readers = Vec::with_capacity(num_readers);
@@ -47,14 +71,55 @@ where
     }
 
     pub async fn insert(&self, pointer: P, value: V) -> K {
-        self.writer.lock().await.insert(pointer, value)
+        self.writer.lock().await.insert(pointer, Some(value))
     }
 
-    pub fn get(&self, key: &K) -> Option<ReadGuard<V>> {
-        self.readers.get().get(key)
+    /// Replace the value for the given key, which must be present
+    pub async fn update(&self, key: K, value: V) {
+        self.writer.lock().await.update(key, Some(value))
+    }
+
+    /// Drop the value for the given key but keep its slot, so the keys of
+    /// later inserts stay the same until the slot is removed
+    pub async fn empty(&self, key: &K)
+    where
+        K: Copy,
+    {
+        let mut writer = self.writer.lock().await;
+
+        if self.readers.get().contains_key(key) {
+            // Each write only reaches the second copy of the map with the
+            // next write, and that's when the replaced value is dropped
+            writer.update(*key, None);
+            writer.update(*key, None);
+        }
+    }
+
+    /// Remove the value for the given key. The value is emptied out of its
+    /// slot before the slot is removed, so it's dropped right away
+    pub async fn remove(&self, key: &K)
+    where
+        K: Copy,
+    {
+        let mut writer = self.writer.lock().await;
+
+        if self.readers.get().contains_key(key) {
+            writer.update(*key, None);
+            writer.remove(key);
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<ConcurrentSlotmapRef<V>> {
+        self.readers
+            .get()
+            .get(key)
+            .filter(|value| value.is_some())
+            .map(ConcurrentSlotmapRef)
     }
 
-    pub fn reader(&self) -> MapReadRef<'_, K, P, V> {
+    /// Get a reader for the whole map. Values are only empty while they're
+    /// being removed
+    pub fn reader(&self) -> MapReadRef<'_, K, P, Option<V>> {
         self.readers
             .get()
             .read()
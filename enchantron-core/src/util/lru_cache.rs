@@ -0,0 +1,216 @@
+use super::CacheStats;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Map with a bounded number of entries that evicts the least recently used
+/// entry when it's full. Lookups are counted so the effectiveness of the cache
+/// can be monitored
+#[derive(Debug)]
+pub struct LruCache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    recency: BTreeMap<u64, K>,
+    next_stamp: u64,
+    stats: CacheStats,
+}
+
+impl<K, V> LruCache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    pub fn new(capacity: usize) -> LruCache<K, V> {
+        assert!(capacity > 0, "LRU cache capacity must be positive");
+
+        LruCache {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            recency: BTreeMap::new(),
+            next_stamp: 0,
+            stats: Default::default(),
+        }
+    }
+
+    fn take_stamp(&mut self) -> u64 {
+        let result = self.next_stamp;
+        self.next_stamp += 1;
+        result
+    }
+
+    /// Get the value for the given key and mark it as the most recently used
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let stamp = self.take_stamp();
+
+        match self.entries.get_mut(key) {
+            Some((value, entry_stamp)) => {
+                self.stats.hits += 1;
+
+                let key = self
+                    .recency
+                    .remove(entry_stamp)
+                    .expect("LRU cache recency out of sync");
+                self.recency.insert(stamp, key);
+                *entry_stamp = stamp;
+
+                Some(value)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Insert the given value as the most recently used, evicting the least
+    /// recently used entry if the cache is full. Any value already stored for
+    /// the key is returned
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let stamp = self.take_stamp();
+
+        if let Some((previous, previous_stamp)) =
+            self.entries.insert(key.clone(), (value, stamp))
+        {
+            self.recency.remove(&previous_stamp);
+            self.recency.insert(stamp, key);
+            return Some(previous);
+        }
+
+        self.recency.insert(stamp, key);
+
+        if self.entries.len() > self.capacity {
+            self.evict_oldest();
+        }
+
+        None
+    }
+
+    /// Check if the given key is cached without marking it as used or
+    /// counting it as a lookup
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(value, stamp)| {
+            self.recency.remove(&stamp);
+            value
+        })
+    }
+
+    /// Remove all the entries the given predicate rejects. Removed entries
+    /// don't count as evictions
+    pub fn retain(&mut self, mut keep: impl FnMut(&K, &V) -> bool) {
+        let recency = &mut self.recency;
+
+        self.entries.retain(|key, (value, stamp)| {
+            let result = keep(key, value);
+
+            if !result {
+                recency.remove(stamp);
+            }
+
+            result
+        });
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest_stamp = *self
+            .recency
+            .keys()
+            .next()
+            .expect("LRU cache recency out of sync");
+        let key = self
+            .recency
+            .remove(&oldest_stamp)
+            .expect("LRU cache recency out of sync");
+
+        self.entries.remove(&key);
+        self.stats.evictions += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+
+        // Touching 1 makes 2 the least recently used
+        assert_eq!(Some(&"one"), cache.get(&1));
+
+        cache.insert(3, "three");
+
+        assert_eq!(2, cache.len());
+        assert_eq!(None, cache.get(&2));
+        assert_eq!(Some(&"one"), cache.get(&1));
+        assert_eq!(Some(&"three"), cache.get(&3));
+
+        assert_eq!(
+            CacheStats {
+                hits: 3,
+                misses: 1,
+                evictions: 1,
+            },
+            cache.stats()
+        );
+    }
+
+    #[test]
+    fn test_replace_and_remove() {
+        let mut cache = LruCache::new(2);
+
+        assert_eq!(None, cache.insert("a", 1));
+        assert_eq!(Some(1), cache.insert("a", 2));
+        assert_eq!(1, cache.len());
+
+        cache.insert("b", 3);
+        assert_eq!(Some(2), cache.remove(&"a"));
+        cache.insert("c", 4);
+
+        assert_eq!(2, cache.len());
+        assert_eq!(0, cache.stats().evictions);
+        assert_eq!(Some(&3), cache.get(&"b"));
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut cache = LruCache::new(3);
+
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        cache.insert(3, "three");
+
+        cache.retain(|key, _| key % 2 == 1);
+
+        assert_eq!(2, cache.len());
+        assert!(!cache.contains_key(&2));
+
+        // Removed entries don't hold on to their recency
+        cache.insert(4, "four");
+        cache.insert(5, "five");
+
+        assert_eq!(3, cache.len());
+        assert_eq!(None, cache.get(&1));
+        assert_eq!(Some(&"three"), cache.get(&3));
+        assert_eq!(1, cache.stats().evictions);
+    }
+}
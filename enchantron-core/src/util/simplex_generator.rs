@@ -1,3 +1,4 @@
+use super::{IPointHasher, RestrictedXxHasher, ValueRect};
 use crate::model::{IPoint, Point};
 
 const INV_SQRT_2: f32 = 1.;
@@ -7,8 +8,18 @@ const CY: f32 = 0.3660254; // 0.5 * (sqrt(3.0)-1.0)
 const CZ: f32 = -0.57735026; // -1 + 2 * CX
 const CW: f32 = 0.024390243; // 1.0/41.0
 
+/// Offsets are picked from [0, 289) so that different seeds land on distinct
+/// parts of the permutation
+const NOISE_PERIOD_THOUSANDTHS: u64 = 289_000;
+
+/// Number of points evaluated together by `generate_rect`. Batches are fixed
+/// size arrays, so the loop over them has no dependencies between iterations
+/// and can be vectorized
+const LANES: usize = 8;
+
 type Vec3 = [f32; 3];
 type Vec2 = [f32; 2];
+type Lanes = [f32; LANES];
 
 fn mod_n_2(v: &mut Vec2, n: f32) {
     v.iter_mut().for_each(|e| {
@@ -72,9 +83,104 @@ fn vec_3(v: f32) -> Vec3 {
     [v, v, v]
 }
 
+fn mod_289(v: f32) -> f32 {
+    v - (v / 289.).floor() * 289.
+}
+
+fn permute(v: f32) -> f32 {
+    mod_289((v * 34. + 1.) * v)
+}
+
+/// Get the radial falloff of a simplex corner at the given offset
+fn falloff(x: f32, y: f32) -> f32 {
+    let m = 0.0_f32.max(0.5 - (x * x + y * y));
+    let m = m * m;
+
+    m * m
+}
+
+/// Get the contribution of a simplex corner at the given offset with the
+/// given falloff and permuted hash
+fn corner_contribution(m: f32, p: f32, x: f32, y: f32) -> f32 {
+    let gx = 2.0f32 * (p * CW).fract() - 1.0;
+    let h = gx.abs() - 0.5;
+    let a0 = gx - (gx + 0.5).floor();
+
+    let m = m * (1.7928429 - 0.85373473 * ((a0 * a0) * (h * h)));
+
+    m * (a0 * x + h * y)
+}
+
+/// Evaluate the noise at a batch of points. Each lane goes through exactly
+/// the same floating point operations as `SimplexGenerator::generate` so the
+/// results are identical
+#[inline]
+fn generate_lanes(xs: &Lanes, ys: &Lanes) -> Lanes {
+    let mut result = [0.; LANES];
+
+    for lane in 0..LANES {
+        let (vx, vy) = (xs[lane], ys[lane]);
+
+        let skew = vx * CY + vy * CY;
+        let ix = (vx + skew).floor();
+        let iy = (vy + skew).floor();
+
+        let unskew = ix * CX + iy * CX;
+        let x0x = (vx - ix) + unskew;
+        let x0y = (vy - iy) + unskew;
+
+        let bottom_half = x0x > x0y;
+        let i1x = bottom_half as i32 as f32;
+        let i1y = (!bottom_half) as i32 as f32;
+
+        let x1x = (x0x + CX) - i1x;
+        let x1y = (x0y + CX) - i1y;
+        let x2x = x0x + CZ;
+        let x2y = x0y + CZ;
+
+        let ix = mod_289(ix);
+        let iy = mod_289(iy);
+
+        let p0 = permute(permute(iy) + ix);
+        let p1 = permute(permute(i1y + iy) + (ix + i1x));
+        let p2 = permute(permute(iy + 1.) + (ix + 1.));
+
+        let c0 = corner_contribution(falloff(x0x, x0y), p0, x0x, x0y);
+        let c1 = corner_contribution(falloff(x1x, x1y), p1, x1x, x1y);
+        let c2 = corner_contribution(falloff(x2x, x2y), p2, x2x, x2y);
+
+        result[lane] = 130.0_f32 * (c0 + c1 + c2);
+    }
+
+    result
+}
+
+#[derive(Debug)]
 pub struct SimplexGenerator {}
 
 impl SimplexGenerator {
+    /// Get the offset to add to points before generating the noise field with
+    /// the given index for the world with the given seed. Each seed and field
+    /// gets its own stretch of the noise
+    pub fn get_seed_offset(seed: u64, field_index: u64) -> Point {
+        let mut hasher = RestrictedXxHasher::default();
+
+        // The hasher is only usable once it has been given four seed values
+        hasher.seed_u64(seed);
+        hasher.seed_u64(field_index);
+        hasher.seed_u64(NOISE_PERIOD_THOUSANDTHS);
+        hasher.seed_u64(0);
+
+        let offset_component = |point: IPoint| {
+            (hasher.hash(&point) % NOISE_PERIOD_THOUSANDTHS) as f64 / 1000.
+        };
+
+        Point::new(
+            offset_component(IPoint::new(0, 0)),
+            offset_component(IPoint::new(1, 0)),
+        )
+    }
+
     pub fn generate(&self, point: Point) -> f32 {
         let v: Vec2 = [point.x as f32, point.y as f32];
         let mut i = floor_2(&plus_2(&v, &vec_2(dot_2(&v, &[CY, CY]))));
@@ -136,12 +242,68 @@ impl SimplexGenerator {
 
         130.0_f32 * dot_3(&m, &g)
     }
+
+    /// Add octaves of noise to every value in the given rect. Octaves are
+    /// pairs of the number of tiles per unit of noise and the weight the
+    /// octave is added with, and points are offset by the given amount after
+    /// scaling. The values are the same as summing `generate` for each point
+    /// and octave in order, but the points are evaluated in batches
+    pub fn generate_rect(
+        &self,
+        target: &mut ValueRect<f32>,
+        offset: &Point,
+        octaves: impl IntoIterator<Item = (f64, f32)>,
+    ) {
+        let rect = *target.rect();
+        let x_stride = *target.x_stride();
+        let y_stride = *target.y_stride();
+        let values_width = *target.values_width();
+
+        if values_width == 0 {
+            return;
+        }
+
+        let mut xs = [0.; LANES];
+
+        for (scale, weight) in octaves {
+            let inv_scale = 1. / scale;
+
+            let rows = target
+                .get_raw_values_mut()
+                .chunks_exact_mut(values_width)
+                .enumerate();
+
+            for (value_y, row) in rows {
+                let y = rect.top_left.y + (value_y * y_stride) as i64;
+                let ys = [(y as f64 * inv_scale + offset.y) as f32; LANES];
+
+                for (batch_index, batch) in row.chunks_mut(LANES).enumerate() {
+                    // The lanes past the end of the last batch are still
+                    // evaluated, but their results are dropped
+                    for (lane, scaled_x) in xs.iter_mut().enumerate() {
+                        let value_x = batch_index * LANES + lane;
+                        let x = rect.top_left.x + (value_x * x_stride) as i64;
+
+                        *scaled_x = (x as f64 * inv_scale + offset.x) as f32;
+                    }
+
+                    let noise = generate_lanes(&xs, &ys);
+
+                    batch
+                        .iter_mut()
+                        .zip(noise.iter())
+                        .for_each(|(value, noise)| *value += weight * noise);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use crate::model::IRect;
 
     #[test]
     fn test_generate() {
@@ -149,6 +311,41 @@ mod test {
         let v = g.generate(Point::new(1., 1.));
     }
 
+    #[test]
+    fn test_generate_rect_matches_points() {
+        let g = SimplexGenerator {};
+        let offset = Point::new(123.456, 7.89);
+        let octaves = [(60., 1.), (13., 0.5), (2.5, 0.25)];
+
+        // The width isn't a multiple of the batch size, so the last batch of
+        // each row is partial
+        for &(rect, stride) in &[
+            (IRect::new(-37, 12, 21, 9), 1),
+            (IRect::new(-4096, -2048, 512, 256), 32),
+        ] {
+            let mut values =
+                ValueRect::new_from_rect_with_defaults(rect, stride, stride);
+
+            g.generate_rect(&mut values, &offset, octaves.iter().copied());
+
+            values.for_each_mut(|point, value| {
+                let mut expected = 0f32;
+
+                for &(scale, weight) in &octaves {
+                    let mut scaled_point =
+                        Point::new(point.x as f64, point.y as f64);
+
+                    scaled_point *= 1. / scale;
+                    scaled_point += &offset;
+
+                    expected += weight * g.generate(scaled_point);
+                }
+
+                assert_eq!(expected.to_bits(), value.to_bits(), "{:?}", point);
+            });
+        }
+    }
+
     #[test]
     fn test_x0_variability() {
         let v: Vec2 = [101003.0, 230234.0];
@@ -8,6 +8,7 @@ use crate::view_types::ViewTypes;
 const MAX_WIDTH_FRAC: f64 = 0.7;
 const HEIGHT_FRAC: f64 = 0.2;
 const BUTTON_ASPECT_RATIO: f64 = 1.618;
+const BUTTON_GAP_FRAC: f64 = 0.1;
 
 /// Calculate the rectangle for the loading progress bar based on the size
 /// of the screen
@@ -30,11 +31,14 @@ pub trait MainMenuView: 'static + Sized + Send + Sync + NativeView {
     fn transition_to_game_view(&self);
 
     fn get_start_new_game_button(&self) -> Self::B;
+
+    fn get_continue_game_button(&self) -> Self::B;
 }
 
 view_impl!(MainMenuView<T> {
     widgets {
-        start_new_game_button: Button
+        start_new_game_button: Button,
+        continue_game_button: Button
     }
 
 
@@ -55,6 +59,10 @@ where
     fn get_start_new_game_button(&self) -> Self::B {
         self.start_new_game_button.clone()
     }
+
+    fn get_continue_game_button(&self) -> Self::B {
+        self.continue_game_button.clone()
+    }
 }
 
 impl<T> MainMenuViewPrivate<T>
@@ -63,22 +71,35 @@ where
 {
     fn init(&mut self) {
         self.start_new_game_button
-            .set_color(T::Color::new(123, 190, 200, 255))
+            .set_color(T::Color::new(123, 190, 200, 255));
+        self.continue_game_button
+            .set_color(T::Color::new(140, 200, 123, 255));
     }
 
     fn on_layout(&mut self, size: Size, _scale: f64) {
-        let new_game_button_rect = calculate_rect_from_size(size);
+        // The buttons are stacked around the center with new game on top
+        let mut new_game_button_rect = calculate_rect_from_size(size);
+        let mut continue_button_rect = new_game_button_rect;
+        let offset = new_game_button_rect.size.height * (0.5 + BUTTON_GAP_FRAC);
+
+        new_game_button_rect.top_left.y -= offset;
+        continue_button_rect.top_left.y += offset;
+
         self.start_new_game_button.set_rect(new_game_button_rect);
+        self.continue_game_button.set_rect(continue_button_rect);
     }
 
     fn on_touch(&mut self, touch_event: RawTouchEvent) {
-        if touch_event.state == TouchEventType::End
-            && self
-                .start_new_game_button
-                .rect
-                .contains_point(&touch_event.touch.point)
-        {
+        if touch_event.state != TouchEventType::End {
+            return;
+        }
+
+        let point = &touch_event.touch.point;
+
+        if self.start_new_game_button.rect.contains_point(point) {
             self.start_new_game_button.on_click()
+        } else if self.continue_game_button.rect.contains_point(point) {
+            self.continue_game_button.on_click()
         }
     }
 }
@@ -12,7 +12,7 @@ use crate::view_types::ViewTypes;
 /// This adjusts where the player's sprite is placed relative to the sprite's
 /// origin. The player's textures are all referenced from the center, but the
 /// tiled terrain is referenced from the top-left corner of every tile
-const PLAYER_TEXTURE_OFFSET: Point = Point {
+pub(crate) const PLAYER_TEXTURE_OFFSET: Point = Point {
     x: 1. / 2.,
     y: -1. / 8.,
 };
@@ -35,17 +35,22 @@ fn get_final_point_in_texture_coordinates(
     (start + dir.get_point()).into()
 }
 
-/// Convert the given start time and speed into a duration length
+/// Convert the given start time and speed into the remaining duration of half
+/// a step in the given direction. Diagonal steps are longer, so they take
+/// longer at the same speed
 fn get_animation_duration(
     start_time: f64,
+    dir: &Direction,
     speed_in_tiles_per_second: f64,
     time: &Time,
 ) -> f64 {
-    0.5 / speed_in_tiles_per_second - (time.now() - start_time)
+    0.5 * dir.get_length() / speed_in_tiles_per_second
+        - (time.now() - start_time)
 }
 
 pub trait PlayerView: 'static + Send + Sync + Unpin + EntityView {
-    fn rest(&self);
+    fn set_tile(&self, tile: &IPoint);
+    fn rest(&self, direction: Direction);
     fn start_walk(
         &self,
         direction: Direction,
@@ -75,11 +80,10 @@ impl<T: ViewTypes> PlayerViewImpl<T> {
         time: Time,
     ) -> PlayerViewImpl<T> {
         bound_sprite
-            .set_texture(runtime_resources.textures().gist.south_rest());
+            .set_texture(runtime_resources.textures().character.south_rest());
         bound_sprite.set_visible(true);
         bound_sprite.set_z_level(constants::ENTITY_Z_LEVEL);
-        bound_sprite.set_size(2., 2.);
-        bound_sprite.set_location_point(&PLAYER_TEXTURE_OFFSET);
+        bound_sprite.set_size(1., 2.);
 
         PlayerViewImpl {
             bound_sprite,
@@ -90,6 +94,13 @@ impl<T: ViewTypes> PlayerViewImpl<T> {
 }
 
 impl<T: ViewTypes> PlayerView for PlayerViewImpl<T> {
+    fn set_tile(&self, tile: &IPoint) {
+        let location: Point = tile.into();
+
+        self.bound_sprite
+            .set_location_point(&(location + PLAYER_TEXTURE_OFFSET));
+    }
+
     fn start_walk(
         &self,
         direction: Direction,
@@ -100,10 +111,13 @@ impl<T: ViewTypes> PlayerView for PlayerViewImpl<T> {
         let midpoint =
             get_halfway_point_in_texture_coordinates(start_tile, &direction);
 
-        let duration = get_animation_duration(start_time, speed, &self.time);
+        let duration =
+            get_animation_duration(start_time, &direction, speed, &self.time);
 
         self.bound_sprite.animate(
-            &self.runtime_resources.animations().player_walk_south,
+            self.runtime_resources
+                .animations()
+                .character_walk(&direction),
             1. / 6.,
         );
 
@@ -123,7 +137,8 @@ impl<T: ViewTypes> PlayerView for PlayerViewImpl<T> {
         let destination =
             get_final_point_in_texture_coordinates(start_tile, &direction);
 
-        let duration = get_animation_duration(start_time, speed, &self.time);
+        let duration =
+            get_animation_duration(start_time, &direction, speed, &self.time);
 
         self.bound_sprite.set_location_point_animated(
             &(destination + PLAYER_TEXTURE_OFFSET),
@@ -131,9 +146,10 @@ impl<T: ViewTypes> PlayerView for PlayerViewImpl<T> {
         );
     }
 
-    fn rest(&self) {
-        self.bound_sprite
-            .set_texture(self.runtime_resources.textures().gist.south_rest());
+    fn rest(&self, direction: Direction) {
+        self.bound_sprite.set_texture(
+            self.runtime_resources.textures().character.rest(&direction),
+        );
     }
 }
 